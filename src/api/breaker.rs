@@ -0,0 +1,58 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct BreakerState {
+    consecutive_failures: u32,
+    open_until: Option<Instant>,
+}
+
+// Stops hammering the Corkboard Server once it's clearly down. After `threshold`
+// consecutive failures every call is short-circuited until `cooldown` has passed, at
+// which point calls are let through again and a single success closes the breaker.
+pub struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+    threshold: u32,
+    cooldown: Duration,
+}
+
+impl CircuitBreaker {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            state: Mutex::new(BreakerState {
+                consecutive_failures: 0,
+                open_until: None,
+            }),
+            threshold,
+            cooldown,
+        }
+    }
+
+    // Returns how long the breaker will stay open if calls should be short-circuited.
+    pub fn check(&self) -> Option<Duration> {
+        let state = self.state.lock().unwrap();
+        state
+            .open_until
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        if state.open_until.is_some() {
+            println!("Corkboard Server is responding again, closing circuit breaker.");
+        }
+        state.consecutive_failures = 0;
+        state.open_until = None;
+    }
+
+    pub fn record_failure(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= self.threshold {
+            println!(
+                "Corkboard Server failed {} times in a row, opening circuit breaker for {:?}.",
+                state.consecutive_failures, self.cooldown
+            );
+            state.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// In-memory read-through cache of a collection per guild. Entries expire after `ttl`
+// and are dropped whenever the bot itself changes something in that guild.
+pub struct GuildCache<T> {
+    ttl: Duration,
+    entries: Mutex<HashMap<i64, (Instant, Vec<T>)>>,
+}
+
+impl<T: Clone> GuildCache<T> {
+    pub fn new(ttl: Duration) -> Self {
+        GuildCache {
+            ttl,
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn get(&self, guild_id: i64) -> Option<Vec<T>> {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(&guild_id) {
+            Some((fetched_at, items)) if fetched_at.elapsed() < self.ttl => Some(items.clone()),
+            _ => None,
+        }
+    }
+
+    pub fn insert(&self, guild_id: i64, items: Vec<T>) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.insert(guild_id, (Instant::now(), items));
+    }
+
+    pub fn invalidate(&self, guild_id: i64) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.remove(&guild_id);
+    }
+}
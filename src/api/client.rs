@@ -0,0 +1,362 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use rand::Rng;
+use reqwest::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use reqwest::{Client, RequestBuilder};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serenity::prelude::TypeMapKey;
+use uuid::Uuid;
+
+use super::breaker::CircuitBreaker;
+use super::cache::GuildCache;
+use crate::commands::events::{self, Event, NewEvent};
+use crate::commands::faqs::{Faq, NewFaq};
+use crate::commands::luckydex::LuckymonHistory;
+use crate::commands::luckymon::NewLuckymonHistory;
+use crate::commands::pins::{NewPin, Pin};
+use crate::config::Config;
+use crate::error::BotError;
+
+// Only idempotent GETs are retried. Mutations go out exactly once so a slow response
+// can't end up creating the same pin or trade twice.
+static MAX_GET_ATTEMPTS: u32 = 4;
+static BACKOFF_BASE_MS: u64 = 250;
+static BACKOFF_MAX_MS: u64 = 4000;
+static BREAKER_THRESHOLD: u32 = 5;
+static BREAKER_COOLDOWN_SECS: u64 = 30;
+
+// Typed wrapper around the Corkboard Server REST API. Every endpoint the bot talks to
+// lives here so a change to the server schema only has to be handled in one place.
+// A single instance is shared through the client data so every command reuses the
+// same connection pool.
+pub struct CorkboardClient {
+    http: Client,
+    base_url: String,
+    breaker: CircuitBreaker,
+    pins: GuildCache<Pin>,
+    events: GuildCache<Event>,
+    // Past events too, for the archive views. Dropped along with `events`.
+    all_events: GuildCache<Event>,
+    faqs: GuildCache<Faq>,
+}
+
+impl CorkboardClient {
+    pub fn new(config: &Config) -> reqwest::Result<Self> {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = &config.api_token {
+            let mut value = HeaderValue::from_str(&format!("Bearer {}", token))
+                .expect("CORKBOARD_API_TOKEN contains invalid header characters!");
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        if let Some(key) = &config.api_key {
+            let mut value = HeaderValue::from_str(key)
+                .expect("CORKBOARD_API_KEY contains invalid header characters!");
+            value.set_sensitive(true);
+            headers.insert("X-API-Key", value);
+        }
+
+        let http = Client::builder()
+            .timeout(config.api_timeout())
+            .default_headers(headers)
+            .build()?;
+
+        Ok(CorkboardClient {
+            http,
+            base_url: config.api_url.clone(),
+            breaker: CircuitBreaker::new(
+                BREAKER_THRESHOLD,
+                Duration::from_secs(BREAKER_COOLDOWN_SECS),
+            ),
+            pins: GuildCache::new(config.cache_ttl()),
+            events: GuildCache::new(config.cache_ttl()),
+            all_events: GuildCache::new(config.cache_ttl()),
+            faqs: GuildCache::new(config.cache_ttl()),
+        })
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{}", self.base_url, path)
+    }
+
+    async fn send<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, BotError> {
+        if let Some(retry_after) = self.breaker.check() {
+            return Err(BotError::Unavailable { retry_after });
+        }
+
+        let result = self.execute(request).await;
+        match &result {
+            Err(e) if e.is_transient() => self.breaker.record_failure(),
+            _ => self.breaker.record_success(),
+        }
+        result
+    }
+
+    async fn execute<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T, BotError> {
+        let request = request.build().map_err(|source| BotError::Unreachable {
+            url: self.base_url.clone(),
+            source,
+        })?;
+        let url = request.url().to_string();
+
+        let response =
+            self.http
+                .execute(request)
+                .await
+                .map_err(|source| BotError::Unreachable {
+                    url: url.clone(),
+                    source,
+                })?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|source| BotError::Unreachable {
+                url: url.clone(),
+                source,
+            })?;
+
+        if !status.is_success() {
+            return Err(BotError::Status {
+                url,
+                status,
+                body: String::from_utf8_lossy(&body).to_string(),
+            });
+        }
+
+        serde_json::from_slice::<T>(&body).map_err(|e| BotError::Malformed {
+            url,
+            reason: e.to_string(),
+        })
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, BotError> {
+        let mut attempt = 0;
+        loop {
+            match self.send(self.http.get(self.url(path))).await {
+                Err(e) if e.is_transient() && attempt + 1 < MAX_GET_ATTEMPTS => {
+                    let delay = backoff_delay(attempt);
+                    println!("GET {} failed ({}), retrying in {:?}", path, e, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    async fn post<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, BotError> {
+        self.send(self.http.post(self.url(path)).json(body)).await
+    }
+
+    async fn put<B: Serialize, T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<T, BotError> {
+        self.send(self.http.put(self.url(path)).json(body)).await
+    }
+
+    async fn put_empty<T: DeserializeOwned>(&self, path: &str) -> Result<T, BotError> {
+        self.send(
+            self.http
+                .put(self.url(path))
+                .header(CONTENT_TYPE, "application/json"),
+        )
+        .await
+    }
+
+    async fn delete<T: DeserializeOwned>(&self, path: &str) -> Result<T, BotError> {
+        self.send(
+            self.http
+                .delete(self.url(path))
+                .header(CONTENT_TYPE, "application/json"),
+        )
+        .await
+    }
+
+    // Drops every cached collection for a guild so the next read goes to the server.
+    pub fn invalidate_guild(&self, guild_id: i64) {
+        self.pins.invalidate(guild_id);
+        self.invalidate_events(guild_id);
+        self.faqs.invalidate(guild_id);
+    }
+
+    // Pins
+
+    // The guild's current pins. Archived ones are left out everywhere but the archive.
+    pub async fn list_pins(&self, guild_id: i64) -> Result<Vec<Pin>, BotError> {
+        let pins = self.list_all_pins(guild_id).await?;
+        Ok(pins.into_iter().filter(|pin| !pin.is_archived()).collect())
+    }
+
+    pub async fn list_archived_pins(&self, guild_id: i64) -> Result<Vec<Pin>, BotError> {
+        let pins = self.list_all_pins(guild_id).await?;
+        Ok(pins.into_iter().filter(Pin::is_archived).collect())
+    }
+
+    async fn list_all_pins(&self, guild_id: i64) -> Result<Vec<Pin>, BotError> {
+        if let Some(pins) = self.pins.get(guild_id) {
+            return Ok(pins);
+        }
+        let pins: Vec<Pin> = self.get(&format!("pin/guild/{}", guild_id)).await?;
+        self.pins.insert(guild_id, pins.clone());
+        Ok(pins)
+    }
+
+    pub async fn create_pin(&self, pin: &NewPin) -> Result<Pin, BotError> {
+        let result = self.post("pin", pin).await;
+        self.pins.invalidate(pin.guild_id);
+        result
+    }
+
+    pub async fn update_pin(&self, pin: &Pin) -> Result<Pin, BotError> {
+        let result = self.put(&format!("pin/{}", pin.id), pin).await;
+        self.pins.invalidate(pin.guild_id);
+        result
+    }
+
+    pub async fn delete_pin(&self, id: Uuid) -> Result<Pin, BotError> {
+        let pin: Pin = self.delete(&format!("pin/delete/{}", id)).await?;
+        self.pins.invalidate(pin.guild_id);
+        Ok(pin)
+    }
+
+    // Events
+
+    pub async fn list_current_events(&self, guild_id: i64) -> Result<Vec<Event>, BotError> {
+        if let Some(events) = self.events.get(guild_id) {
+            return Ok(events);
+        }
+        let mut events: Vec<Event> = self
+            .get(&format!("event/current/guild/{}", guild_id))
+            .await?;
+        // `/event/current` filters on the stored end date, which for a recurring event is
+        // that of its first occurrence, so series are taken from the full list instead.
+        let now = Utc::now().naive_utc();
+        let series: Vec<Event> = self
+            .list_all_events(guild_id)
+            .await?
+            .into_iter()
+            .filter(|event| {
+                event.recurrence.is_some()
+                    && !events.iter().any(|current| current.id == event.id)
+                    && events::series_running(event, now)
+            })
+            .collect();
+        events.extend(series);
+        self.events.insert(guild_id, events.clone());
+        Ok(events)
+    }
+
+    // Every event the guild has had, including those that are over.
+    pub async fn list_all_events(&self, guild_id: i64) -> Result<Vec<Event>, BotError> {
+        if let Some(events) = self.all_events.get(guild_id) {
+            return Ok(events);
+        }
+        let events: Vec<Event> = self.get(&format!("event/guild/{}", guild_id)).await?;
+        self.all_events.insert(guild_id, events.clone());
+        Ok(events)
+    }
+
+    pub async fn create_event(&self, event: &NewEvent) -> Result<Event, BotError> {
+        let result = self.post("event", event).await;
+        self.invalidate_events(event.guild_id);
+        result
+    }
+
+    pub async fn update_event(&self, event: &Event) -> Result<Event, BotError> {
+        let result = self.put(&format!("event/{}", event.id), event).await;
+        self.invalidate_events(event.guild_id);
+        result
+    }
+
+    pub async fn delete_event(&self, id: Uuid) -> Result<Event, BotError> {
+        let event: Event = self.delete(&format!("event/delete/{}", id)).await?;
+        self.invalidate_events(event.guild_id);
+        Ok(event)
+    }
+
+    fn invalidate_events(&self, guild_id: i64) {
+        self.events.invalidate(guild_id);
+        self.all_events.invalidate(guild_id);
+    }
+
+    // FAQs
+
+    pub async fn list_faqs(&self, guild_id: i64) -> Result<Vec<Faq>, BotError> {
+        if let Some(faqs) = self.faqs.get(guild_id) {
+            return Ok(faqs);
+        }
+        let faqs: Vec<Faq> = self.get(&format!("faq/guild/{}", guild_id)).await?;
+        self.faqs.insert(guild_id, faqs.clone());
+        Ok(faqs)
+    }
+
+    pub async fn create_faq(&self, faq: &NewFaq) -> Result<Faq, BotError> {
+        let result = self.post("faq", faq).await;
+        self.faqs.invalidate(faq.guild_id);
+        result
+    }
+
+    pub async fn update_faq(&self, faq: &Faq) -> Result<Faq, BotError> {
+        let result = self.put(&format!("faq/{}", faq.id), faq).await;
+        self.faqs.invalidate(faq.guild_id);
+        result
+    }
+
+    pub async fn delete_faq(&self, id: Uuid) -> Result<Faq, BotError> {
+        let faq: Faq = self.delete(&format!("faq/delete/{}", id)).await?;
+        self.faqs.invalidate(faq.guild_id);
+        Ok(faq)
+    }
+
+    // Luckymon
+
+    pub async fn get_luckymon_history(
+        &self,
+        user_id: i64,
+    ) -> Result<Vec<LuckymonHistory>, BotError> {
+        self.get(&format!("luckymon-history/user-id/{}", user_id))
+            .await
+    }
+
+    pub async fn get_luckymon_history_entry(&self, id: Uuid) -> Result<LuckymonHistory, BotError> {
+        self.get(&format!("luckymon-history/{}", id)).await
+    }
+
+    pub async fn create_luckymon_history(
+        &self,
+        hist: &NewLuckymonHistory,
+        trade: bool,
+    ) -> Result<LuckymonHistory, BotError> {
+        if trade {
+            self.post("luckymon-history?trade=true", hist).await
+        } else {
+            self.post("luckymon-history", hist).await
+        }
+    }
+
+    pub async fn mark_traded(&self, id: Uuid) -> Result<LuckymonHistory, BotError> {
+        self.put_empty(&format!("luckymon-history/traded/{}", id))
+            .await
+    }
+}
+
+// Exponential backoff with jitter so a restarting server isn't hit by every waiting
+// command at the same instant.
+fn backoff_delay(attempt: u32) -> Duration {
+    let max = (BACKOFF_BASE_MS << attempt).min(BACKOFF_MAX_MS);
+    Duration::from_millis(rand::thread_rng().gen_range(max / 2..=max))
+}
+
+impl TypeMapKey for CorkboardClient {
+    type Value = Arc<CorkboardClient>;
+}
@@ -0,0 +1,17 @@
+use std::sync::Arc;
+
+use serenity::prelude::Context;
+
+mod breaker;
+mod cache;
+pub mod client;
+
+use client::CorkboardClient;
+
+// Fetches the shared Corkboard Server client that was built in main().
+pub async fn client(ctx: &Context) -> Arc<CorkboardClient> {
+    let data = ctx.data.read().await;
+    data.get::<CorkboardClient>()
+        .expect("Expected CorkboardClient in the client data.")
+        .clone()
+}
@@ -0,0 +1,63 @@
+use std::collections::HashSet;
+use std::time::Duration;
+
+use chrono::Utc;
+use serenity::prelude::*;
+use uuid::Uuid;
+
+use crate::api;
+use crate::commands::pins::Pin;
+
+static CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+// Moves pins whose expiry has passed into the archive. They stay on the server with the
+// date they were archived, so `.pins archive` can list them and `.restore_pin` can
+// bring them back.
+pub async fn run(ctx: Context) {
+    println!("Started pin archiver.");
+    let mut unarchivable = HashSet::new();
+    loop {
+        for guild_id in ctx.cache.guilds() {
+            archive_expired(&ctx, guild_id.0 as i64, &mut unarchivable).await;
+        }
+
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+// `unarchivable` holds the pins the server wouldn't mark as archived. They're left alone
+// from then on rather than sent again every few minutes.
+async fn archive_expired(ctx: &Context, guild_id: i64, unarchivable: &mut HashSet<Uuid>) {
+    let client = api::client(ctx).await;
+    let pins = match client.list_pins(guild_id).await {
+        Ok(pins) => pins,
+        Err(why) => {
+            why.log("pin archiver");
+            return;
+        }
+    };
+
+    let now = Utc::now().naive_utc();
+    let expired: Vec<Pin> = pins
+        .into_iter()
+        .filter(|pin| {
+            pin.expires_at.is_some_and(|expires_at| expires_at <= now)
+                && !unarchivable.contains(&pin.id)
+        })
+        .collect();
+    for mut pin in expired {
+        pin.archived_at = Some(now);
+        println!("Archiving expired Pin {:?}", pin.id);
+        match client.update_pin(&pin).await {
+            Ok(updated) if updated.is_archived() => {}
+            Ok(updated) => {
+                println!(
+                    "The Corkboard Server didn't keep archived_at for Pin {:?}, so it stays in .pins.",
+                    updated.id
+                );
+                unarchivable.insert(updated.id);
+            }
+            Err(why) => why.log("pin archiver"),
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use serenity::model::application::interaction::autocomplete::AutocompleteInteraction;
+use serenity::model::id::UserId;
+use serenity::prelude::*;
+use uuid::Uuid;
+
+use crate::api;
+use crate::error::BotError;
+use crate::timezone;
+use crate::validation::validation;
+
+use super::luckydex::LuckymonHistory;
+use super::pins;
+
+// Discord rejects autocomplete responses with more than 25 choices or names over 100 chars.
+static MAX_CHOICES: usize = 25;
+static MAX_NAME_LEN: usize = 100;
+
+// Suggests values for the focused option of a slash command while the user is typing.
+// The value sent back is exactly what the command itself accepts, so picking a
+// suggestion is the same as typing it by hand.
+pub async fn respond(ctx: &Context, autocomplete: &AutocompleteInteraction) {
+    let focused = match autocomplete.data.options.iter().find(|o| o.focused) {
+        Some(option) => option,
+        None => return,
+    };
+    let typed = focused
+        .value
+        .as_ref()
+        .and_then(|v| v.as_str())
+        .unwrap_or("")
+        .to_lowercase();
+
+    let choices = match suggestions(ctx, autocomplete, &focused.name, &typed).await {
+        Ok(choices) => choices,
+        Err(why) => {
+            why.log(&autocomplete.data.name);
+            Vec::new()
+        }
+    };
+
+    if let Err(why) = autocomplete
+        .create_autocomplete_response(&ctx.http, |r| {
+            for (name, value) in choices.into_iter().take(MAX_CHOICES) {
+                r.add_string_choice(truncate(&name), value);
+            }
+            r
+        })
+        .await
+    {
+        println!(
+            "Unable to send autocomplete choices for {}: {:?}",
+            autocomplete.data.name, why
+        );
+    }
+}
+
+async fn suggestions(
+    ctx: &Context,
+    autocomplete: &AutocompleteInteraction,
+    option: &str,
+    typed: &str,
+) -> Result<Vec<(String, String)>, BotError> {
+    let guild_id = match autocomplete.guild_id {
+        Some(guild_id) => i64::from(guild_id),
+        None => return Ok(Vec::new()),
+    };
+    let client = api::client(ctx).await;
+
+    let choices = match (autocomplete.data.name.as_str(), option) {
+        (
+            "edit_pin" | "delete_pin" | "move_pin" | "feature_pin" | "expire_pin" | "pin_form",
+            "id",
+        ) => client
+            .list_pins(guild_id)
+            .await?
+            .iter()
+            .map(|pin| item_choice(&pin.id, &pin.title))
+            .collect(),
+        ("restore_pin", "id") => client
+            .list_archived_pins(guild_id)
+            .await?
+            .iter()
+            .map(|pin| item_choice(&pin.id, &pin.title))
+            .collect(),
+        ("pins", "filter") => {
+            let pins = client.list_pins(guild_id).await?;
+            let mut choices: Vec<(String, String)> = pins::categories(&pins)
+                .into_iter()
+                .map(|category| (category.clone(), pins::category_filter(&category)))
+                .collect();
+            choices.extend(pins::tags(&pins).into_iter().map(|tag| {
+                let tag = format!("#{}", tag);
+                (tag.clone(), tag)
+            }));
+            choices.push((pins::ARCHIVE.to_string(), pins::ARCHIVE.to_string()));
+            choices
+        }
+        ("add_pin" | "edit_pin", "category") => {
+            pins::categories(&client.list_pins(guild_id).await?)
+                .into_iter()
+                .map(|category| (category.clone(), category))
+                .collect()
+        }
+        ("event" | "edit_event" | "delete_event" | "repeat_event" | "event_form", "id") => client
+            .list_current_events(guild_id)
+            .await?
+            .iter()
+            .map(|event| item_choice(&event.id, &event.title))
+            .collect(),
+        ("edit_faq" | "delete_faq" | "faq_form", "id") => client
+            .list_faqs(guild_id)
+            .await?
+            .iter()
+            .map(|faq| item_choice(&faq.id, &faq.question))
+            .collect(),
+        ("luckytrade", "offer") => {
+            let hists = client
+                .get_luckymon_history(i64::from(autocomplete.user.id))
+                .await?;
+            luckymon_choices(hists)
+        }
+        ("luckytrade", "request") => match target_user(autocomplete) {
+            Some(user_id) => {
+                let hists = client.get_luckymon_history(i64::from(user_id)).await?;
+                luckymon_choices(hists)
+            }
+            None => Vec::new(),
+        },
+        ("timezone" | "guild_timezone", "zone") => {
+            let mut zones: Vec<(String, String)> = timezone::matching_timezones(typed)
+                .into_iter()
+                .map(|zone| (zone.to_string(), zone.to_string()))
+                .collect();
+            if autocomplete.data.name == "timezone" {
+                zones.insert(0, ("reset".to_string(), "reset".to_string()));
+            }
+            zones
+        }
+        _ => Vec::new(),
+    };
+
+    Ok(choices
+        .into_iter()
+        .filter(|(name, _)| typed.is_empty() || name.to_lowercase().contains(typed))
+        .collect())
+}
+
+fn item_choice(id: &Uuid, title: &str) -> (String, String) {
+    let short_id = validation::short_id(id);
+    (format!("{} - {}", short_id, title), short_id)
+}
+
+// One choice per distinct untraded Luckymon, plus `n/a` for gifting.
+fn luckymon_choices(hists: Vec<LuckymonHistory>) -> Vec<(String, String)> {
+    let mut choices = vec![("N/A (nothing)".to_string(), "n/a".to_string())];
+    for hist in hists.into_iter().filter(|hist| !hist.traded) {
+        let (name, value) = if hist.shiny {
+            (
+                format!("#{} ✨ Shiny {}", hist.pokemon_id, hist.pokemon_name),
+                format!("{}s", hist.pokemon_id),
+            )
+        } else {
+            (
+                format!("#{} {}", hist.pokemon_id, hist.pokemon_name),
+                hist.pokemon_id.to_string(),
+            )
+        };
+
+        if !choices.iter().any(|(_, v)| *v == value) {
+            choices.push((name, value));
+        }
+    }
+
+    choices
+}
+
+// Options aren't resolved during autocomplete, so the picked user only arrives as the
+// raw snowflake string.
+fn target_user(autocomplete: &AutocompleteInteraction) -> Option<UserId> {
+    autocomplete
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "user")
+        .and_then(|option| option.value.as_ref())
+        .and_then(|value| value.as_str())
+        .and_then(|value| value.parse::<u64>().ok())
+        .map(UserId)
+}
+
+fn truncate(name: &str) -> String {
+    if name.chars().count() <= MAX_NAME_LEN {
+        return name.to_string();
+    }
+
+    let mut truncated: String = name.chars().take(MAX_NAME_LEN - 3).collect();
+    truncated.push_str("...");
+    truncated
+}
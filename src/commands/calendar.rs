@@ -0,0 +1,381 @@
+use std::borrow::Cow;
+use std::io::Cursor;
+
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveTime, Timelike, Utc};
+use chrono_tz::Tz;
+use image::codecs::png::PngEncoder;
+use image::{ImageBuffer, ImageEncoder, Rgba};
+use imageproc::drawing::{draw_filled_rect_mut, draw_hollow_rect_mut, draw_text_mut};
+use imageproc::rect::Rect;
+use rusttype::{Font, Scale};
+use serenity::builder::CreateEmbed;
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::{AttachmentType, Message};
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+
+use crate::api;
+use crate::config;
+use crate::dates;
+use crate::store;
+use crate::timezone;
+use crate::validation::validation;
+
+use super::events::{self, Event};
+use super::invocation::{Invocation, Reply};
+use super::luckydex;
+
+// Layout of the month image, in pixels.
+static CELL_WIDTH: u32 = 180;
+static CELL_HEIGHT: u32 = 140;
+static TITLE_HEIGHT: u32 = 70;
+static WEEKDAY_HEIGHT: u32 = 36;
+static DAY_LABEL_HEIGHT: u32 = 26;
+static LANE_HEIGHT: u32 = 22;
+// Bars that don't fit in a day are summed up as "+N more" at the bottom of the day.
+static MAX_LANES: usize = 4;
+static WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+static BACKGROUND: Rgba<u8> = Rgba([255, 255, 255, 255]);
+static OTHER_MONTH: Rgba<u8> = Rgba([236, 236, 236, 255]);
+static TODAY: Rgba<u8> = Rgba([255, 244, 204, 255]);
+static GRID: Rgba<u8> = Rgba([190, 190, 190, 255]);
+static TEXT: Rgba<u8> = Rgba([40, 40, 40, 255]);
+static FADED_TEXT: Rgba<u8> = Rgba([150, 150, 150, 255]);
+static BAR_TEXT: Rgba<u8> = Rgba([255, 255, 255, 255]);
+// Every occurrence of a series gets the same colour.
+static BAR_COLOURS: [Rgba<u8>; 6] = [
+    Rgba([52, 101, 164, 255]),
+    Rgba([204, 0, 0, 255]),
+    Rgba([78, 154, 6, 255]),
+    Rgba([117, 80, 123, 255]),
+    Rgba([206, 92, 0, 255]),
+    Rgba([6, 152, 154, 255]),
+];
+
+#[command]
+#[description = "Draws this month's events as a calendar, or another month's with e.g. `.calendar december`, `.calendar dec 2024` or `.calendar 2024-12`. Also gives a link to subscribe to the events from a calendar app. Admins can use `.calendar reset` to replace the link, so the old one stops working."]
+#[usage = "[month] | reset"]
+async fn calendar(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let inv = Invocation::Prefix(msg);
+    if args.current() == Some("reset") {
+        return run_calendar_reset(ctx, &inv).await;
+    }
+    run_calendar(ctx, &inv, args.rest()).await
+}
+
+pub async fn run_calendar(ctx: &Context, inv: &Invocation<'_>, month: &str) -> CommandResult {
+    println!("Got calendar command..");
+    let guild_id = inv.guild_id().unwrap();
+    let store = store::store(ctx).await;
+    let tz = store.guild_timezone(guild_id.into());
+    let today = timezone::from_utc(tz, Utc::now().naive_utc()).date();
+    let month = if month.trim().is_empty() {
+        today.with_day(1).unwrap()
+    } else {
+        match dates::parse_year_month(month, today) {
+            Some(month) => month,
+            None => {
+                let _msg = inv
+                    .say(
+                        ctx,
+                        format!(
+                            ":bangbang: Error :bangbang: - `{}` isn't a month. Try `december`, `dec 2024` or `2024-12`.",
+                            month.trim()
+                        ),
+                    )
+                    .await;
+                return Ok(());
+            }
+        }
+    };
+
+    let client = api::client(ctx).await;
+    let events = client.list_all_events(i64::from(guild_id)).await?;
+    let image = draw_month(&events, tz, month, today);
+    let mut png: Vec<u8> = Vec::new();
+    PngEncoder::new(&mut Cursor::new(&mut png))
+        .write_image(
+            &image,
+            image.width(),
+            image.height(),
+            image::ColorType::Rgba8,
+        )
+        .expect("Error encoding image");
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(format!("Events in {}", month.format("%B %Y")))
+        .image("attachment://calendar.png")
+        .footer(|f| f.text(format!("Days are shown in {}.", tz.name())))
+        .timestamp(Timestamp::now());
+    if let Some(url) = feed_url(ctx, guild_id.into()).await {
+        embed.description(format!("Subscribe from your calendar app: <{}>", url));
+    }
+
+    let file = AttachmentType::Bytes {
+        data: Cow::Owned(png),
+        filename: "calendar.png".to_string(),
+    };
+    let _msg = inv.reply(ctx, Reply::embed(embed).file(file)).await;
+
+    println!("Finished processing calendar command!");
+    Ok(())
+}
+
+pub async fn run_calendar_reset(ctx: &Context, inv: &Invocation<'_>) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let guild_id = u64::from(inv.guild_id().unwrap());
+    store::store(ctx)
+        .await
+        .update_guild(guild_id, |settings| settings.feed_token = None);
+    let reply = match feed_url(ctx, guild_id).await {
+        Some(url) => format!(
+            ":calendar: The old calendar link no longer works. Subscribe with the new one:\n<{}>",
+            url
+        ),
+        None => "The calendar feed isn't enabled on this bot. Use `.events ics` to download the events instead.".to_string(),
+    };
+    let _msg = inv.say(ctx, reply).await;
+
+    Ok(())
+}
+
+// The guild's calendar feed URL, or `None` if the bot doesn't serve feeds.
+async fn feed_url(ctx: &Context, guild_id: u64) -> Option<String> {
+    let base_url = config::config(ctx).await.feed_url()?;
+    let token = store::store(ctx).await.feed_token(guild_id);
+    Some(format!("{}/calendar/{}/{}.ics", base_url, guild_id, token))
+}
+
+// A month grid starting on Monday, with each occurrence drawn as a bar across the days
+// it's on. Bars for events that cross a week boundary continue on the next row.
+fn draw_month(
+    events: &[Event],
+    tz: Tz,
+    month: NaiveDate,
+    today: NaiveDate,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let next_month = month.checked_add_months(Months::new(1)).unwrap();
+    let grid_start = month - Duration::days(month.weekday().num_days_from_monday() as i64);
+    let weeks = ((next_month - grid_start).num_days() as u32).div_ceil(7);
+    let grid_end = grid_start + Duration::days(weeks as i64 * 7);
+
+    let width = CELL_WIDTH * 7;
+    let height = TITLE_HEIGHT + WEEKDAY_HEIGHT + CELL_HEIGHT * weeks;
+    let mut img = ImageBuffer::from_pixel(width, height, BACKGROUND);
+    let font = luckydex::get_font();
+
+    let title = month.format("%B %Y").to_string();
+    let title_scale = Scale::uniform(40.0);
+    let title_x = (width as f32 - luckydex::text_width(&font, title_scale, &title)) / 2.0;
+    draw_text_mut(
+        &mut img,
+        TEXT,
+        title_x as i32,
+        16,
+        title_scale,
+        &font,
+        &title,
+    );
+
+    let label_scale = Scale::uniform(18.0);
+    for (col, weekday) in WEEKDAYS.iter().enumerate() {
+        let text_x = col as f32 * CELL_WIDTH as f32
+            + (CELL_WIDTH as f32 - luckydex::text_width(&font, label_scale, weekday)) / 2.0;
+        draw_text_mut(
+            &mut img,
+            TEXT,
+            text_x as i32,
+            (TITLE_HEIGHT + 8) as i32,
+            label_scale,
+            &font,
+            weekday,
+        );
+    }
+
+    for week in 0..weeks {
+        for col in 0..7 {
+            let day = grid_start + Duration::days((week * 7 + col) as i64);
+            let (x, y) = cell_origin(week, col);
+            let fill = if day == today {
+                TODAY
+            } else if day.month() != month.month() {
+                OTHER_MONTH
+            } else {
+                BACKGROUND
+            };
+            let cell = Rect::at(x, y).of_size(CELL_WIDTH, CELL_HEIGHT);
+            draw_filled_rect_mut(&mut img, cell, fill);
+            draw_hollow_rect_mut(&mut img, cell, GRID);
+            let colour = if day.month() == month.month() {
+                TEXT
+            } else {
+                FADED_TEXT
+            };
+            draw_text_mut(
+                &mut img,
+                colour,
+                x + 6,
+                y + 4,
+                label_scale,
+                &font,
+                &day.day().to_string(),
+            );
+        }
+    }
+
+    let from = timezone::to_utc(tz, grid_start.and_time(NaiveTime::MIN));
+    let to = timezone::to_utc(tz, grid_end.and_time(NaiveTime::MIN));
+    let occurrences = events::overlapping_occurrences(events, tz, from, to);
+    for week in 0..weeks {
+        let week_start = grid_start + Duration::days(week as i64 * 7);
+        let week_end = week_start + Duration::days(6);
+        draw_week(
+            &mut img,
+            &font,
+            &occurrences,
+            tz,
+            week,
+            week_start,
+            week_end,
+        );
+    }
+
+    img
+}
+
+fn draw_week(
+    img: &mut ImageBuffer<Rgba<u8>, Vec<u8>>,
+    font: &Font,
+    occurrences: &[Event],
+    tz: Tz,
+    week: u32,
+    week_start: NaiveDate,
+    week_end: NaiveDate,
+) {
+    // Each occurrence's first and last day within this week. Longer bars go first so they
+    // get the top lanes.
+    let mut segments: Vec<(&Event, NaiveDate, NaiveDate)> = occurrences
+        .iter()
+        .filter_map(|occurrence| {
+            let (first, last) = local_days(occurrence, tz);
+            if last < week_start || first > week_end {
+                return None;
+            }
+            Some((occurrence, first.max(week_start), last.min(week_end)))
+        })
+        .collect();
+    segments.sort_by_key(|(_, first, last)| (*first, -(*last - *first).num_days()));
+
+    let bar_scale = Scale::uniform(14.0);
+    // The last day taken in each lane.
+    let mut lanes: Vec<NaiveDate> = Vec::new();
+    let mut hidden = [0; 7];
+    for (occurrence, first, last) in segments {
+        let lane = match lanes.iter().position(|taken| *taken < first) {
+            Some(lane) => lane,
+            None => {
+                lanes.push(first - Duration::days(1));
+                lanes.len() - 1
+            }
+        };
+        let first_col = (first - week_start).num_days() as u32;
+        let last_col = (last - week_start).num_days() as u32;
+        if lane >= MAX_LANES {
+            for count in &mut hidden[first_col as usize..=last_col as usize] {
+                *count += 1;
+            }
+            continue;
+        }
+        lanes[lane] = last;
+
+        let (x, y) = cell_origin(week, first_col);
+        let bar_width = (last_col - first_col + 1) * CELL_WIDTH - 8;
+        let bar_y = y + (DAY_LABEL_HEIGHT + lane as u32 * LANE_HEIGHT) as i32;
+        let bar = Rect::at(x + 4, bar_y).of_size(bar_width, LANE_HEIGHT - 3);
+        draw_filled_rect_mut(img, bar, bar_colour(occurrence));
+
+        // The start time is only shown where the event actually starts.
+        let label = match short_time(occurrence, tz) {
+            Some(time) if local_days(occurrence, tz).0 == first => {
+                format!("{} {}", time, occurrence.title)
+            }
+            _ => occurrence.title.clone(),
+        };
+        let label = fit(font, bar_scale, &label, bar_width as f32 - 8.0);
+        draw_text_mut(img, BAR_TEXT, x + 8, bar_y + 2, bar_scale, font, &label);
+    }
+
+    let more_scale = Scale::uniform(13.0);
+    for (col, count) in hidden.iter().enumerate() {
+        if *count == 0 {
+            continue;
+        }
+        let (x, y) = cell_origin(week, col as u32);
+        draw_text_mut(
+            img,
+            TEXT,
+            x + 8,
+            y + (DAY_LABEL_HEIGHT + MAX_LANES as u32 * LANE_HEIGHT) as i32,
+            more_scale,
+            font,
+            &format!("+{} more", count),
+        );
+    }
+}
+
+fn cell_origin(week: u32, col: u32) -> (i32, i32) {
+    (
+        (col * CELL_WIDTH) as i32,
+        (TITLE_HEIGHT + WEEKDAY_HEIGHT + week * CELL_HEIGHT) as i32,
+    )
+}
+
+// The first and last local day an occurrence is on. One ending at midnight isn't on the
+// day that midnight starts, so all-day events stay on their own days.
+fn local_days(occurrence: &Event, tz: Tz) -> (NaiveDate, NaiveDate) {
+    let start = timezone::from_utc(tz, occurrence.start_date);
+    let end = timezone::from_utc(tz, occurrence.end_date);
+    let last = if end > start && end.time() == NaiveTime::MIN {
+        end.date() - Duration::days(1)
+    } else {
+        end.date()
+    };
+    (start.date(), last.max(start.date()))
+}
+
+// `7pm` or `7:30pm`, or `None` for events starting at midnight, which are usually all-day.
+fn short_time(occurrence: &Event, tz: Tz) -> Option<String> {
+    let start = timezone::from_utc(tz, occurrence.start_date);
+    let format = match (start.hour(), start.minute()) {
+        (0, 0) => return None,
+        (_, 0) => "%-I%P",
+        _ => "%-I:%M%P",
+    };
+    Some(start.format(format).to_string())
+}
+
+fn bar_colour(occurrence: &Event) -> Rgba<u8> {
+    BAR_COLOURS[(occurrence.id.as_u128() % BAR_COLOURS.len() as u128) as usize]
+}
+
+// Shortens `text` with an ellipsis until it fits in `max_width`.
+fn fit(font: &Font, scale: Scale, text: &str, max_width: f32) -> String {
+    if luckydex::text_width(font, scale, text) <= max_width {
+        return text.to_string();
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let shortened = format!("{}…", chars.iter().collect::<String>().trim_end());
+        if luckydex::text_width(font, scale, &shortened) <= max_width {
+            return shortened;
+        }
+    }
+    String::new()
+}
@@ -1,22 +1,56 @@
 extern crate serde;
 extern crate serde_json;
 
-use std::collections::HashMap;
+use std::borrow::Cow;
+use std::time::Duration as StdDuration;
 
-use chrono::NaiveDateTime;
-use reqwest::header::CONTENT_TYPE;
+use chrono::{Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
+use serenity::builder::{CreateActionRow, CreateComponents, CreateEmbed};
 use serenity::framework::standard::macros::command;
 use serenity::framework::standard::{Args, CommandResult};
-use serenity::model::channel::Message;
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::application::interaction::InteractionResponseType;
+use serenity::model::channel::{AttachmentType, Message};
 use serenity::model::Timestamp;
 use serenity::prelude::*;
 use uuid::Uuid;
 
+use crate::api;
+use crate::api::client::CorkboardClient;
+use crate::dates;
+use crate::error::BotError;
+use crate::ics;
+use crate::links;
+use crate::migration;
+use crate::recurrence::Recurrence;
+use crate::scheduled;
+use crate::store;
+use crate::timezone;
 use crate::validation::validation;
 
-#[derive(Serialize, Deserialize, Debug)]
+use super::invocation::{Invocation, Reply};
+use super::rsvp;
+
+// Format used when an event's dates are shown for editing. It's also one of the formats
+// `dates::parse_start` accepts, so the shown value can be submitted unchanged.
+pub static DATE_FORMAT: &str = "%m/%d/%Y %-I:%M%p";
+// Recurring events are listed by their occurrences within this many days, a few each
+// when looking ahead. Looking back, every occurrence within the window is listed.
+static LISTED_DAYS: i64 = 30;
+static LISTED_PER_SERIES: usize = 3;
+// Well under Discord's 25 fields, since event descriptions count towards the 6000
+// characters an embed may hold.
+static EVENTS_PER_PAGE: usize = 10;
+static PAGE_TIMEOUT: StdDuration = StdDuration::from_secs(120);
+static SCOPE_TIMEOUT: StdDuration = StdDuration::from_secs(60);
+
+// The zone event dates are stored in, which each event is marked with. An unmarked
+// event may still be in Pacific time from before time zone support.
+pub static STORED_TZ: &str = "UTC";
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Event {
     pub id: Uuid,
     pub guild_id: i64,
@@ -25,26 +59,27 @@ pub struct Event {
     pub description: String,
     pub start_date: NaiveDateTime,
     pub end_date: NaiveDateTime,
+    // An iCalendar RRULE; the dates above are then those of the first occurrence. Both
+    // are always sent, so turning a repeat off in an edit reaches the server.
+    #[serde(default)]
+    pub recurrence: Option<String>,
+    // Dates, in the guild's time zone, on which a recurring event is skipped.
+    #[serde(default)]
+    pub exceptions: Vec<NaiveDate>,
+    #[serde(default)]
+    pub tz: Option<String>,
 }
 
 impl Event {
     pub fn new(
-        id: &str,
+        id: Uuid,
         guild_id: i64,
         title: String,
         url: String,
         description: String,
-        start_date: &str,
-        end_date: &str,
+        start_date: NaiveDateTime,
+        end_date: NaiveDateTime,
     ) -> Self {
-        let id = Uuid::parse_str(id).expect("Bad UUID");
-
-        let fmt = "%Y-%m-%dT%H:%M:%S%.f";
-        let start_date = NaiveDateTime::parse_from_str(start_date, fmt)
-            .expect("Unable to parse start_date NaiveDateTime for Event.");
-        let end_date = NaiveDateTime::parse_from_str(end_date, fmt)
-            .expect("Unable to parse end_date NaiveDateTime for Event.");
-
         Event {
             id,
             guild_id,
@@ -53,29 +88,25 @@ impl Event {
             description,
             start_date,
             end_date,
+            recurrence: None,
+            exceptions: Vec::new(),
+            tz: Some(STORED_TZ.to_string()),
         }
     }
 
-    pub fn to_event(event_map: HashMap<String, Value>) -> Event {
-        Event::new(
-            event_map.get("id").unwrap().as_str().unwrap(),
-            event_map.get("guild_id").unwrap().as_i64().unwrap(),
-            event_map
-                .get("title")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .to_string(),
-            event_map.get("url").unwrap().as_str().unwrap().to_string(),
-            event_map
-                .get("description")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .to_string(),
-            event_map.get("start_date").unwrap().as_str().unwrap(),
-            event_map.get("end_date").unwrap().as_str().unwrap(),
-        )
+    // The event's repeat rule, if it has one the bot understands.
+    pub fn recurrence(&self, tz: Tz) -> Option<Recurrence> {
+        let rule = self.recurrence.as_ref()?;
+        match Recurrence::parse(rule, tz) {
+            Ok(recurrence) => Some(recurrence),
+            Err(_) => {
+                println!(
+                    "Ignoring unreadable recurrence {:?} on event {}",
+                    rule, self.id
+                );
+                None
+            }
+        }
     }
 }
 
@@ -87,6 +118,9 @@ pub struct NewEvent {
     pub description: String,
     pub start_date: NaiveDateTime,
     pub end_date: NaiveDateTime,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<String>,
+    pub tz: String,
 }
 
 impl NewEvent {
@@ -95,15 +129,9 @@ impl NewEvent {
         title: String,
         url: String,
         description: String,
-        start_date: &str,
-        end_date: &str,
+        start_date: NaiveDateTime,
+        end_date: NaiveDateTime,
     ) -> Self {
-        let fmt = "%m/%d/%Y %-I:%M%p";
-        let start_date = NaiveDateTime::parse_from_str(start_date, fmt)
-            .expect("Unable to parse start_date NaiveDateTime for Event.");
-        let end_date = NaiveDateTime::parse_from_str(end_date, fmt)
-            .expect("Unable to parse end_date NaiveDateTime for Event.");
-
         NewEvent {
             guild_id,
             title,
@@ -111,141 +139,579 @@ impl NewEvent {
             description,
             start_date,
             end_date,
+            recurrence: None,
+            tz: STORED_TZ.to_string(),
         }
     }
 }
 
-#[command]
-#[description = "Retrieves all events. All times using PST/PDT."]
-async fn events(ctx: &Context, msg: &Message) -> CommandResult {
-    println!("Got events command..");
-    let resp = reqwest::get(format!(
-        "http://localhost:8000/api/v1/event/current/guild/{}",
-        msg.guild_id.unwrap()
-    ))
-    .await?
-    .json::<Vec<HashMap<String, Value>>>()
-    .await?;
+// Reads the start and end typed by a user in their time zone and returns them in UTC,
+// which is how the Corkboard Server stores event dates.
+pub fn parse_event_dates(
+    start_date: &str,
+    end_date: &str,
+    tz: Tz,
+) -> Result<(NaiveDateTime, NaiveDateTime), String> {
+    let now = Utc::now().naive_utc();
+    let start_date = dates::parse_start(start_date, tz, now)?;
+    let end_date = dates::parse_end(end_date, start_date, tz, now)?;
+    if end_date < start_date {
+        return Err(format!(
+            ":bangbang: Error :bangbang: - The event would end ({}) before it starts ({}).",
+            local_date(tz, end_date),
+            local_date(tz, start_date)
+        ));
+    }
+
+    Ok((start_date, end_date))
+}
+
+pub fn local_date(tz: Tz, utc: NaiveDateTime) -> String {
+    timezone::from_utc(tz, utc)
+        .format("%a %m/%d/%Y %-I:%M%p")
+        .to_string()
+}
+
+// Echoes the dates back as they were understood, so a misread date is easy to spot.
+fn confirmation(event: &Event, tz: Tz) -> String {
+    format!(
+        "Start: `{}`, End: `{}` ({})",
+        local_date(tz, event.start_date),
+        local_date(tz, event.end_date),
+        tz.name()
+    )
+}
 
-    let mut events: Vec<Event> = Vec::new();
-    for event_map in resp {
-        events.push(Event::to_event(event_map));
+// Each occurrence of `event` as a copy carrying that occurrence's dates, in order. A
+// one-off event is its own only occurrence.
+pub fn occurrences(event: &Event, tz: Tz) -> Box<dyn Iterator<Item = Event> + '_> {
+    let recurrence = match event.recurrence(tz) {
+        Some(recurrence) => recurrence,
+        None => return Box::new(std::iter::once(event.clone())),
+    };
+
+    let duration = event.end_date - event.start_date;
+    let first = timezone::from_utc(tz, event.start_date);
+    Box::new(
+        recurrence
+            .occurrences(first)
+            .filter(move |local| !event.exceptions.contains(&local.date()))
+            .map_while(move |local| {
+                let start_date = timezone::to_utc(tz, local);
+                Some(Event {
+                    start_date,
+                    end_date: start_date.checked_add_signed(duration)?,
+                    ..event.clone()
+                })
+            }),
+    )
+}
+
+// Whether a recurring series still has an occurrence that hasn't ended. The guild's
+// time zone isn't known here, so the rule is read in UTC with a day of slack.
+pub fn series_running(event: &Event, now: NaiveDateTime) -> bool {
+    let cutoff = now - Duration::days(1);
+    occurrences(event, chrono_tz::UTC).any(|occurrence| occurrence.end_date >= cutoff)
+}
+
+// The occurrence on `date` in the guild's time zone, or the next one that hasn't ended.
+pub fn find_occurrence(event: &Event, tz: Tz, date: Option<NaiveDate>) -> Option<Event> {
+    match date {
+        Some(date) => occurrences(event, tz)
+            .map(|occurrence| (occurrence_date(&occurrence, tz), occurrence))
+            .take_while(|(day, _)| *day <= date)
+            .find(|(day, _)| *day == date)
+            .map(|(_, occurrence)| occurrence),
+        None => {
+            let now = Utc::now().naive_utc();
+            occurrences(event, tz).find(|occurrence| occurrence.end_date >= now)
+        }
     }
+}
 
-    let mut event_fields: Vec<(String, String, bool)> = Vec::new();
-    let mut i = 1;
+// Replaces each recurring event by its occurrences that haven't ended yet and start
+// within `horizon`, at most `per_series` of them, then sorts everything by start.
+pub fn upcoming_occurrences(
+    events: &[Event],
+    tz: Tz,
+    now: NaiveDateTime,
+    horizon: Duration,
+    per_series: usize,
+) -> Vec<Event> {
+    let until = now
+        .checked_add_signed(horizon)
+        .unwrap_or(NaiveDateTime::MAX);
+    let mut upcoming = Vec::new();
     for event in events {
-        event_fields.push((
-            format!("{}.", i),
-            format!(
-                "[{}]({}): {}\n**Start:** {}\n**End:** {}",
-                event.title,
-                event.url,
-                event.description,
-                event.start_date.format("%m/%d/%Y %-I:%M%p").to_string(),
-                event.end_date.format("%m/%d/%Y %-I:%M%p").to_string()
-            ),
-            false,
-        ));
-        i += 1;
+        if event.recurrence.is_none() {
+            upcoming.push(event.clone());
+            continue;
+        }
+        upcoming.extend(
+            occurrences(event, tz)
+                .skip_while(|occurrence| occurrence.end_date < now)
+                .take_while(|occurrence| occurrence.start_date <= until)
+                .take(per_series),
+        );
     }
 
-    if event_fields.len() == 0 {
-        event_fields.push((
-            "Empty!".to_string(),
-            "No current events found!".to_string(),
-            false,
-        ));
+    upcoming.sort_by_key(|event| event.start_date);
+    upcoming
+}
+
+// Events that are over, oldest first. Recurring events only contribute the occurrences
+// of the last `LISTED_DAYS`, so a long-running series doesn't bury everything else.
+fn past_occurrences(events: &[Event], tz: Tz, now: NaiveDateTime) -> Vec<Event> {
+    let cutoff = now - Duration::days(LISTED_DAYS);
+    let mut past = Vec::new();
+    for event in events {
+        if event.recurrence.is_none() {
+            if event.end_date < now {
+                past.push(event.clone());
+            }
+            continue;
+        }
+        past.extend(
+            occurrences(event, tz)
+                .skip_while(|occurrence| occurrence.start_date < cutoff)
+                .take_while(|occurrence| occurrence.end_date < now),
+        );
     }
 
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Events (using PST/PDT)")
-                    .image("attachment://cork-board.png")
-                    .fields(event_fields)
-                    .timestamp(Timestamp::now())
+    past.sort_by_key(|event| event.start_date);
+    past
+}
+
+// Every occurrence starting in the month beginning on `month`, in the guild's time zone.
+fn month_occurrences(events: &[Event], tz: Tz, month: NaiveDate) -> Vec<Event> {
+    let from = timezone::to_utc(tz, month.and_time(NaiveTime::MIN));
+    let to = match month.checked_add_months(Months::new(1)) {
+        Some(next) => timezone::to_utc(tz, next.and_time(NaiveTime::MIN)),
+        None => return Vec::new(),
+    };
+
+    let mut listed: Vec<Event> = events
+        .iter()
+        .flat_map(|event| {
+            occurrences(event, tz)
+                .skip_while(move |occurrence| occurrence.start_date < from)
+                .take_while(move |occurrence| occurrence.start_date < to)
+        })
+        .collect();
+    listed.sort_by_key(|event| event.start_date);
+    listed
+}
+
+// Every occurrence that's on at some point between `from` and `to`, sorted by start.
+pub fn overlapping_occurrences(
+    events: &[Event],
+    tz: Tz,
+    from: NaiveDateTime,
+    to: NaiveDateTime,
+) -> Vec<Event> {
+    let mut listed: Vec<Event> = events
+        .iter()
+        .flat_map(|event| {
+            occurrences(event, tz)
+                .skip_while(move |occurrence| occurrence.end_date < from)
+                .take_while(move |occurrence| occurrence.start_date < to)
+        })
+        .collect();
+    listed.sort_by_key(|event| event.start_date);
+    listed
+}
+
+pub fn occurrence_date(event: &Event, tz: Tz) -> NaiveDate {
+    timezone::from_utc(tz, event.start_date).date()
+}
+
+// `3fa85f`, or `3fa85f@2024-12-06` for one occurrence of a recurring event.
+pub fn event_handle(event: &Event, tz: Tz) -> String {
+    let id = validation::short_id(&event.id);
+    if event.recurrence.is_some() {
+        format!("{}@{}", id, occurrence_date(event, tz).format("%Y-%m-%d"))
+    } else {
+        id
+    }
+}
+
+// Splits a handle like `3fa85f@2024-12-06` into the event's ID and the occurrence date.
+pub fn split_handle(handle: &str) -> Result<(&str, Option<NaiveDate>), String> {
+    match handle.split_once('@') {
+        None => Ok((handle, None)),
+        Some((id, date)) => NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+            .map(|date| (id, Some(date)))
+            .map_err(|_| {
+                format!(
+                    ":bangbang: Error :bangbang: - `{}` isn't an occurrence date. Use the handle shown in `.events`, like `{}@2024-12-06`.",
+                    date, id
+                )
+            }),
+    }
+}
+
+// Which events `.events` lists.
+pub enum EventView {
+    Upcoming,
+    Past,
+    // Everything, or with a month only what starts in it (in the guild's time zone).
+    All(Option<NaiveDate>),
+}
+
+impl EventView {
+    // `upcoming`, `past`, `all` or `all <month>`; nothing at all means upcoming.
+    pub fn parse(input: &str, tz: Tz) -> Result<EventView, String> {
+        let input = input.trim().to_lowercase();
+        let (view, month) = input.split_once(' ').unwrap_or((&input, ""));
+        match (view, month.trim()) {
+            ("" | "upcoming", "") => Ok(EventView::Upcoming),
+            ("past", "") => Ok(EventView::Past),
+            ("all", "") => Ok(EventView::All(None)),
+            ("all", month) => {
+                let today = timezone::from_utc(tz, Utc::now().naive_utc()).date();
+                dates::parse_year_month(month, today)
+                    .map(|month| EventView::All(Some(month)))
+                    .ok_or_else(|| {
+                        format!(
+                            ":bangbang: Error :bangbang: - `{}` isn't a month. Try `december`, `dec 2024` or `2024-12`.",
+                            month
+                        )
+                    })
+            }
+            _ => Err(format!(
+                ":bangbang: Error :bangbang: - `{}` isn't a view. Use `.events upcoming`, `.events past`, `.events all [month]` or `.events ics`.",
+                input
+            )),
+        }
+    }
+}
+
+#[command]
+#[description = "Retrieves events, shown in your own local time. `.events upcoming` (the default) and `.events past` list what's coming and what's over, `.events all [month]` lists everything or just one month, and `.events ics` attaches the events as an iCalendar file."]
+#[usage = "[upcoming|past|all [month]|ics]"]
+async fn events(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let inv = Invocation::Prefix(msg);
+    if args.current() == Some("ics") {
+        return run_events_ics(ctx, &inv).await;
+    }
+
+    let tz = store::store(ctx)
+        .await
+        .guild_timezone(msg.guild_id.unwrap().into());
+    match EventView::parse(args.rest(), tz) {
+        Ok(view) => run_events(ctx, &inv, view).await,
+        Err(error) => {
+            let _msg = inv.say(ctx, error).await;
+            Ok(())
+        }
+    }
+}
+
+pub async fn run_events(ctx: &Context, inv: &Invocation<'_>, view: EventView) -> CommandResult {
+    println!("Got events command..");
+    let guild_id = inv.guild_id().unwrap();
+    let client = api::client(ctx).await;
+    let tz = store::store(ctx).await.guild_timezone(guild_id.into());
+    let now = Utc::now().naive_utc();
+    let horizon = Duration::days(LISTED_DAYS);
+
+    let (title, listed) = match view {
+        EventView::Upcoming => {
+            let events = client.list_current_events(i64::from(guild_id)).await?;
+            (
+                "Upcoming Events".to_string(),
+                upcoming_occurrences(&events, tz, now, horizon, LISTED_PER_SERIES),
+            )
+        }
+        EventView::Past => {
+            let events = client.list_all_events(i64::from(guild_id)).await?;
+            (
+                "Past Events".to_string(),
+                past_occurrences(&events, tz, now),
+            )
+        }
+        EventView::All(None) => {
+            let events = client.list_all_events(i64::from(guild_id)).await?;
+            let current: Vec<Event> = events
+                .iter()
+                .filter(|event| event.recurrence.is_some() || event.end_date >= now)
+                .cloned()
+                .collect();
+            let mut listed = past_occurrences(&events, tz, now);
+            listed.extend(upcoming_occurrences(
+                &current,
+                tz,
+                now,
+                horizon,
+                LISTED_PER_SERIES,
+            ));
+            ("All Events".to_string(), listed)
+        }
+        EventView::All(Some(month)) => {
+            let events = client.list_all_events(i64::from(guild_id)).await?;
+            (
+                format!("Events in {}", month.format("%B %Y")),
+                month_occurrences(&events, tz, month),
+            )
+        }
+    };
+
+    // Past events open on their last page, the one closest to now.
+    let last_page = listed.len().saturating_sub(1) / EVENTS_PER_PAGE;
+    let first_page = match view {
+        EventView::Past => last_page,
+        _ => 0,
+    };
+    show_pages(ctx, inv, &title, &listed, tz, first_page).await?;
+
+    println!("Finished processing events command!");
+    Ok(())
+}
+
+// Sends one page of `listed` with Previous/Next buttons, then turns the page for whoever
+// ran the command until nobody has clicked for a while.
+async fn show_pages(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    title: &str,
+    listed: &[Event],
+    tz: Tz,
+    first_page: usize,
+) -> serenity::Result<()> {
+    let total_pages = listed.len().max(1).div_ceil(EVENTS_PER_PAGE);
+    let mut current_page = first_page;
+
+    let (embed, components) = create_embed_page(title, listed, tz, current_page, total_pages);
+    let mut reply = Reply::embed(embed).file("./resources/cork-board.png");
+    if total_pages > 1 {
+        reply = reply.components(components);
+    }
+    let mut message = inv.reply(ctx, reply).await?;
+    if total_pages == 1 {
+        return Ok(());
+    }
+
+    let owner = inv.user().id;
+    while let Some(interaction) = message
+        .await_component_interaction(ctx)
+        .timeout(PAGE_TIMEOUT)
+        .await
+    {
+        let custom_id = interaction.data.custom_id.as_str();
+        if interaction.user.id == owner {
+            if custom_id == "events_prev" && current_page > 0 {
+                current_page -= 1;
+            } else if custom_id == "events_next" && current_page < total_pages - 1 {
+                current_page += 1;
+            }
+        }
+
+        let (embed, components) = create_embed_page(title, listed, tz, current_page, total_pages);
+        interaction
+            .create_interaction_response(&ctx.http, |r| {
+                r.kind(InteractionResponseType::UpdateMessage)
+                    .interaction_response_data(|d| d.set_embed(embed).set_components(components))
             })
-            .add_file("./resources/cork-board.png")
+            .await?;
+    }
+
+    // The buttons stop working once the collector is gone, so they're taken away.
+    let _ = message
+        .edit(&ctx.http, |m| m.set_components(CreateComponents::default()))
+        .await;
+    Ok(())
+}
+
+fn create_embed_page(
+    title: &str,
+    listed: &[Event],
+    tz: Tz,
+    current_page: usize,
+    total_pages: usize,
+) -> (CreateEmbed, CreateComponents) {
+    let now = Utc::now().naive_utc();
+    let mut fields: Vec<(String, String, bool)> = listed
+        .iter()
+        .skip(current_page * EVENTS_PER_PAGE)
+        .take(EVENTS_PER_PAGE)
+        .map(|event| {
+            let (name, value, inline) = event_field(event, tz);
+            (name, format!("{}\n*{}*", value, when(event, now)), inline)
         })
+        .collect();
+    if fields.is_empty() {
+        fields.push(("Empty!".to_string(), "No events found!".to_string(), false));
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title(title)
+        .image("attachment://cork-board.png")
+        .fields(fields)
+        .footer(|f| f.text(format!("Page {} of {}", current_page + 1, total_pages)))
+        .timestamp(Timestamp::now());
+
+    let action_row = CreateActionRow::default()
+        .create_button(|b| {
+            b.style(ButtonStyle::Primary)
+                .custom_id("events_prev")
+                .disabled(current_page == 0)
+                .label("Previous")
+        })
+        .create_button(|b| {
+            b.style(ButtonStyle::Primary)
+                .custom_id("events_next")
+                .disabled(current_page + 1 >= total_pages)
+                .label("Next")
+        })
+        .clone();
+    let mut components = CreateComponents::default();
+    components.add_action_row(action_row);
+
+    (embed, components)
+}
+
+// `Starts in 3 days`, `Happening now, ends in 2 hours` or `Ended 5 days ago`.
+fn when(event: &Event, now: NaiveDateTime) -> String {
+    if event.start_date > now {
+        format!("Starts {}", timezone::discord_relative(event.start_date))
+    } else if event.end_date >= now {
+        format!(
+            "Happening now, ends {}",
+            timezone::discord_relative(event.end_date)
+        )
+    } else {
+        format!("Ended {}", timezone::discord_relative(event.end_date))
+    }
+}
+
+pub async fn run_events_ics(ctx: &Context, inv: &Invocation<'_>) -> CommandResult {
+    println!("Got events ics command..");
+    let guild_id = inv.guild_id().unwrap();
+    let client = api::client(ctx).await;
+    let events = client.list_current_events(i64::from(guild_id)).await?;
+
+    let tz = store::store(ctx).await.guild_timezone(guild_id.into());
+    let file = AttachmentType::Bytes {
+        data: Cow::Owned(ics::calendar(&events, tz).into_bytes()),
+        filename: "events.ics".to_string(),
+    };
+    let _msg = inv
+        .reply(
+            ctx,
+            Reply::text(format!(
+                "{} current and upcoming events. Open the file to add them to your calendar, or use `.calendar` to subscribe instead.",
+                events.len()
+            ))
+            .file(file),
+        )
         .await;
 
-    println!("Finished processing events command!");
+    println!("Finished processing events ics command!");
     Ok(())
 }
 
 #[command]
 #[allowed_roles("corkboard")]
-#[description = "Add an Event. All times using PST/PDT."]
-#[usage = "title url description start_date end_date"]
+#[description = "Add an Event. Times are read in your time zone (see `.timezone`). Start with the URL instead of a title to have the title and description read from the page."]
+#[usage = "title url description start_date end_date | url start_date end_date"]
 async fn add_event(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    // With the URL first, the title and description are read from the page.
+    if args.len() == 3 {
+        let url = args.single_quoted::<String>().unwrap();
+        if validation::is_web_url(&url) {
+            let start_date = args.single_quoted::<String>().unwrap();
+            let end_date = args.single_quoted::<String>().unwrap();
+            return run_add_event(
+                ctx,
+                &Invocation::Prefix(msg),
+                String::new(),
+                url,
+                String::new(),
+                &start_date,
+                &end_date,
+            )
+            .await;
+        }
+    }
+
     let arg_names = vec!["Title", "URL", "Description", "Start Date", "End Date"];
-    if !validation::has_corkboard_role(ctx, msg).await
-        || !validation::has_correct_arg_count(ctx, msg, 5, args.len(), arg_names, "add_event").await
-    {
+    if !validation::has_correct_arg_count(ctx, msg, 5, args.len(), arg_names, "add_event").await {
         return Ok(());
     }
 
-    let guild_id = i64::from(msg.guild_id.unwrap());
     let title = args.single_quoted::<String>().unwrap();
     let url = args.single_quoted::<String>().unwrap();
     let description = args.single_quoted::<String>().unwrap();
     let start_date = args.single_quoted::<String>().unwrap();
     let end_date = args.single_quoted::<String>().unwrap();
+    run_add_event(
+        ctx,
+        &Invocation::Prefix(msg),
+        title,
+        url,
+        description,
+        &start_date,
+        &end_date,
+    )
+    .await
+}
+
+// A title or description left empty is read from the page the URL points at.
+pub async fn run_add_event(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    title: String,
+    url: String,
+    description: String,
+    start_date: &str,
+    end_date: &str,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+    if !migration::events_writable(ctx, inv).await {
+        return Ok(());
+    }
+    if !validation::has_web_url(ctx, inv, &url).await {
+        return Ok(());
+    }
+
+    let guild_id = inv.guild_id().unwrap();
+    let store = store::store(ctx).await;
+    let tz = store.timezone_for(guild_id.into(), inv.user().id.into());
+    let (start_date, end_date) = match parse_event_dates(start_date, end_date, tz) {
+        Ok(dates) => dates,
+        Err(error) => {
+            let _msg = inv.say(ctx, error).await;
+            return Ok(());
+        }
+    };
+    let (title, description) = match links::complete(ctx, inv, &url, title, description).await {
+        Some(completed) => completed,
+        None => return Ok(()),
+    };
     let new = NewEvent::new(
-        guild_id,
+        i64::from(guild_id),
         title,
         url,
         description,
-        start_date.as_str(),
-        end_date.as_str(),
+        start_date,
+        end_date,
     );
 
     println!("Sending new Event creation request with {:?}", new);
-    let client = reqwest::Client::new();
-    let resp = client
-        .post("http://localhost:8000/api/v1/event")
-        .json(&new)
-        .send()
-        .await?
-        .json::<HashMap<String, Value>>()
-        .await?;
+    let client = api::client(ctx).await;
+    let event = client.create_event(&new).await?;
+    scheduled::mirror(ctx, &event).await;
 
-    let title = resp.get("title").unwrap();
-    let url = resp.get("url").unwrap();
-    let description = resp.get("description").unwrap();
-    let start_date = resp.get("start_date").unwrap();
-    let end_date = resp.get("end_date").unwrap();
-
-    let fmt = "%Y-%m-%dT%H:%M:%S%.f";
-    let start_date = NaiveDateTime::parse_from_str(start_date.as_str().unwrap(), fmt)
-        .expect("Unable to parse start_date NaiveDateTime for Event.");
-    let end_date = NaiveDateTime::parse_from_str(end_date.as_str().unwrap(), fmt)
-        .expect("Unable to parse start_date NaiveDateTime for Event.");
-
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Created New Event (using PST/PDT)")
-                    .image("attachment://cork-board.png")
-                    .field(
-                        format!("1. "),
-                        format!(
-                            "[{}]({}): {}\n**Start:** {}\n**End:** {}",
-                            title,
-                            url,
-                            description,
-                            start_date.format("%m/%d/%Y %-I:%M%p").to_string(),
-                            end_date.format("%m/%d/%Y %-I:%M%p").to_string()
-                        ),
-                        false,
-                    )
-                    .timestamp(Timestamp::now())
-            })
-            .add_file("./resources/cork-board.png")
-        })
+    let guild_tz = store.guild_timezone(guild_id.into());
+    let _msg = inv
+        .reply(
+            ctx,
+            Reply::corkboard("Created New Event", vec![event_field(&event, guild_tz)])
+                .content(confirmation(&event, tz)),
+        )
         .await;
 
     Ok(())
@@ -253,7 +719,7 @@ async fn add_event(ctx: &Context, msg: &Message, mut args: Args) -> CommandResul
 
 #[command]
 #[allowed_roles("corkboard")]
-#[description = "Edit an Event. All times using PST/PDT."]
+#[description = "Edit an Event. Times are read in your time zone (see `.timezone`). For a recurring event, use the `id@date` shown in `.events` to pick an occurrence; you'll be asked whether to change just that one or the whole series."]
 #[usage = "event_id title url description start_date end_date"]
 async fn edit_event(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let arg_names = vec![
@@ -264,14 +730,10 @@ async fn edit_event(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
         "Start Date",
         "End Date",
     ];
-    if !validation::has_corkboard_role(ctx, msg).await
-        || !validation::has_correct_arg_count(ctx, msg, 6, args.len(), arg_names, "edit_event")
-            .await
-    {
+    if !validation::has_correct_arg_count(ctx, msg, 6, args.len(), arg_names, "edit_event").await {
         return Ok(());
     }
 
-    let guild_id = i64::from(msg.guild_id.unwrap());
     let id = args.current().unwrap().to_string();
     args.advance();
     let title = args.single_quoted::<String>().unwrap();
@@ -279,96 +741,144 @@ async fn edit_event(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
     let description = args.single_quoted::<String>().unwrap();
     let start_date = args.single_quoted::<String>().unwrap();
     let end_date = args.single_quoted::<String>().unwrap();
+    run_edit_event(
+        ctx,
+        &Invocation::Prefix(msg),
+        &id,
+        title,
+        url,
+        description,
+        &start_date,
+        &end_date,
+    )
+    .await
+}
 
-    let fmt = "%m/%d/%Y %-I:%M%p";
-    let start_date = NaiveDateTime::parse_from_str(start_date.as_str(), fmt)
-        .expect("Unable to parse start_date NaiveDateTime for Event.");
-    let end_date = NaiveDateTime::parse_from_str(end_date.as_str(), fmt)
-        .expect("Unable to parse start_date NaiveDateTime for Event.");
-
-    let id_int = match id.parse::<i32>() {
-        Ok(i) => i,
-        _error => {
-            let _msg = msg
-                .channel_id
-                .say(
-                    &ctx.http,
-                    ":bangbang: Error :bangbang: - Unable to parse ID.",
-                )
-                .await;
+#[allow(clippy::too_many_arguments)]
+pub async fn run_edit_event(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    id: &str,
+    title: String,
+    url: String,
+    description: String,
+    start_date: &str,
+    end_date: &str,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+    if !migration::events_writable(ctx, inv).await {
+        return Ok(());
+    }
+    if !validation::has_web_url(ctx, inv, &url).await {
+        return Ok(());
+    }
+
+    let store = store::store(ctx).await;
+    let tz = store.timezone_for(inv.guild_id().unwrap().into(), inv.user().id.into());
+    let (start_date, end_date) = match parse_event_dates(start_date, end_date, tz) {
+        Ok(dates) => dates,
+        Err(error) => {
+            let _msg = inv.say(ctx, error).await;
             return Ok(());
         }
     };
 
-    let id_map = retrieve_events_id_map(guild_id).await;
-    let real_id_maybe = id_map.get(&id_int).clone();
-    let real_id = match real_id_maybe {
-        Some(i) => i,
-        None => {
-            let _msg = msg
-                .channel_id.say(
-                    &ctx.http,
-                    ":bangbang: Error :bangbang: - Invalid ID! Run the `.events` command to see a list of usable IDs."
-                )
-                .await;
-            return Ok(());
-        }
+    let guild_id = i64::from(inv.guild_id().unwrap());
+    let guild_tz = store.guild_timezone(guild_id as u64);
+    let client = api::client(ctx).await;
+    let (existing, occurrence) =
+        match retrieve_event(ctx, inv, &client, guild_id, id, guild_tz).await? {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+    let mut new = Event {
+        recurrence: existing.recurrence.clone(),
+        exceptions: existing.exceptions.clone(),
+        ..Event::new(
+            existing.id,
+            guild_id,
+            title,
+            url,
+            description,
+            start_date,
+            end_date,
+        )
     };
 
-    let new = Event::new(
-        real_id.as_str(),
-        guild_id,
-        title,
-        url,
-        description,
-        start_date.format("%Y-%m-%dT%H:%M:%S").to_string().as_str(),
-        end_date.format("%Y-%m-%dT%H:%M:%S").to_string().as_str(),
-    );
+    if let Some(occurrence) = occurrence {
+        match ask_scope(ctx, inv, &occurrence, "Edit").await? {
+            None => return Ok(()),
+            // The occurrence carries on as an event of its own and is skipped in the
+            // series. The new event is created first, so a failure leaves the occurrence
+            // where it was rather than dropping it.
+            Some(SeriesScope::ThisOccurrence) => {
+                let new = NewEvent::new(
+                    guild_id,
+                    new.title,
+                    new.url,
+                    new.description,
+                    new.start_date,
+                    new.end_date,
+                );
 
-    println!("Sending Event edit request with {:?}", new);
-    let client = reqwest::Client::new();
-    let resp = client
-        .put(format!("http://localhost:8000/api/v1/event/{}", real_id).as_str())
-        .json(&new)
-        .send()
-        .await?
-        .json::<HashMap<String, Value>>()
-        .await?;
+                println!("Sending new Event creation request with {:?}", new);
+                let event = client.create_event(&new).await?;
+                let series = match skip_occurrence(&client, existing, &occurrence, guild_tz).await {
+                    Ok(series) => series,
+                    Err(why) => {
+                        println!("Sending Event delete request with ID {:?}", event.id);
+                        if let Err(undo) = client.delete_event(event.id).await {
+                            undo.log("edit_event");
+                        }
+                        return Err(why.into());
+                    }
+                };
+                scheduled::mirror(ctx, &series).await;
+                scheduled::mirror(ctx, &event).await;
 
-    let title = resp.get("title").unwrap();
-    let url = resp.get("url").unwrap();
-    let description = resp.get("description").unwrap();
-    let start_date = resp.get("start_date").unwrap();
-    let end_date = resp.get("end_date").unwrap();
-
-    let fmt = "%Y-%m-%dT%H:%M:%S%.f";
-    let start_date = NaiveDateTime::parse_from_str(start_date.as_str().unwrap(), fmt)
-        .expect("Unable to parse start_date NaiveDateTime for Event.");
-    let end_date = NaiveDateTime::parse_from_str(end_date.as_str().unwrap(), fmt)
-        .expect("Unable to parse start_date NaiveDateTime for Event.");
-
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Edited Event (using PST/PDT)")
-                    .image("attachment://cork-board.png")
-                    .field(
-                        format!("{}. ", id),
-                        format!(
-                            "[{}]({}): {}\n**Start:** {}\n**End:** {}",
-                            title,
-                            url,
-                            description,
-                            start_date.format("%m/%d/%Y %-I:%M%p").to_string(),
-                            end_date.format("%m/%d/%Y %-I:%M%p").to_string()
-                        ),
-                        false,
+                let _msg = inv
+                    .reply(
+                        ctx,
+                        Reply::corkboard("Edited Occurrence", vec![event_field(&event, guild_tz)])
+                            .content(confirmation(&event, tz)),
                     )
-                    .timestamp(Timestamp::now())
-            })
-            .add_file("./resources/cork-board.png")
-        })
+                    .await;
+                return Ok(());
+            }
+            // The new dates are for the occurrence, so the series is moved by as much.
+            Some(SeriesScope::WholeSeries) => {
+                match shift_series(&existing, &occurrence, start_date, end_date, guild_tz) {
+                    Some((start_date, end_date)) => {
+                        new.start_date = start_date;
+                        new.end_date = end_date;
+                    }
+                    None => {
+                        let _msg = inv
+                            .say(
+                                ctx,
+                                ":bangbang: Error :bangbang: - That moves the series out of the range of dates that can be stored.",
+                            )
+                            .await;
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    println!("Sending Event edit request with {:?}", new);
+    let event = client.update_event(&new).await?;
+    scheduled::mirror(ctx, &event).await;
+
+    let _msg = inv
+        .reply(
+            ctx,
+            Reply::corkboard("Edited Event", vec![event_field(&event, guild_tz)])
+                .content(confirmation(&event, tz)),
+        )
         .await;
 
     Ok(())
@@ -376,119 +886,384 @@ async fn edit_event(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
 
 #[command]
 #[allowed_roles("corkboard")]
-#[description = "Delete an Event. All times using PST/PDT."]
+#[description = "Make an Event repeat, e.g. `weekly`, `every 2 weeks on mon, wed` or `monthly until 12/31/2024`. Use `off` to make it a one-off again."]
+#[usage = "event_id rule"]
+async fn repeat_event(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let arg_names = vec!["Event_id", "Rule"];
+    if !validation::has_correct_arg_count(ctx, msg, 2, args.len(), arg_names, "repeat_event").await
+    {
+        return Ok(());
+    }
+
+    let id = args.current().unwrap().to_string();
+    args.advance();
+    let rule = args.single_quoted::<String>().unwrap();
+    run_repeat_event(ctx, &Invocation::Prefix(msg), &id, &rule).await
+}
+
+pub async fn run_repeat_event(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    id: &str,
+    rule: &str,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+    if !migration::events_writable(ctx, inv).await {
+        return Ok(());
+    }
+
+    let guild_id = i64::from(inv.guild_id().unwrap());
+    let guild_tz = store::store(ctx).await.guild_timezone(guild_id as u64);
+    let client = api::client(ctx).await;
+    let (mut event, _) = match retrieve_event(ctx, inv, &client, guild_id, id, guild_tz).await? {
+        Some(found) => found,
+        None => return Ok(()),
+    };
+
+    if rule.trim().eq_ignore_ascii_case("off") {
+        event.recurrence = None;
+        event.exceptions.clear();
+    } else {
+        match Recurrence::parse(rule, guild_tz) {
+            Ok(recurrence) => event.recurrence = Some(recurrence.to_rrule()),
+            Err(error) => {
+                let _msg = inv.say(ctx, error).await;
+                return Ok(());
+            }
+        }
+    }
+
+    println!("Sending Event edit request with {:?}", event);
+    let event = client.update_event(&event).await?;
+    scheduled::mirror(ctx, &event).await;
+
+    let summary = match (
+        event.recurrence(guild_tz),
+        find_occurrence(&event, guild_tz, None),
+    ) {
+        (Some(recurrence), Some(next)) => format!(
+            "Repeats {}. Next up: {}",
+            recurrence.describe(),
+            timezone::discord_timestamp(next.start_date)
+        ),
+        (Some(recurrence), None) => format!(
+            "Repeats {}, but has no occurrences left.",
+            recurrence.describe()
+        ),
+        (None, _) => "No longer repeats.".to_string(),
+    };
+    let _msg = inv
+        .reply(
+            ctx,
+            Reply::corkboard("Edited Event", vec![event_field(&event, guild_tz)]).content(summary),
+        )
+        .await;
+
+    Ok(())
+}
+
+#[command]
+#[allowed_roles("corkboard")]
+#[description = "Delete an Event. For a recurring event you'll be asked whether to skip just one occurrence (`id@date` from `.events`, or the next one) or delete the whole series."]
 #[usage = "event_id"]
 async fn delete_event(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let arg_names = vec!["Event_id"];
-    if !validation::has_corkboard_role(ctx, msg).await
-        || !validation::has_correct_arg_count(ctx, msg, 1, args.len(), arg_names, "delete_event")
-            .await
+    if !validation::has_correct_arg_count(ctx, msg, 1, args.len(), arg_names, "delete_event").await
     {
         return Ok(());
     }
 
-    let guild_id = i64::from(msg.guild_id.unwrap());
     args.quoted();
     let id = args.current().unwrap().to_string();
-    let id_int = match id.parse::<i32>() {
-        Ok(i) => i,
-        _error => {
-            let _msg = msg
-                .channel_id
-                .say(
-                    &ctx.http,
-                    ":bangbang: Error :bangbang: - Unable to parse ID.",
-                )
-                .await;
-            return Ok(());
+    run_delete_event(ctx, &Invocation::Prefix(msg), &id).await
+}
+
+pub async fn run_delete_event(ctx: &Context, inv: &Invocation<'_>, id: &str) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+    if !migration::events_writable(ctx, inv).await {
+        return Ok(());
+    }
+
+    let guild_id = i64::from(inv.guild_id().unwrap());
+    let store = store::store(ctx).await;
+    let guild_tz = store.guild_timezone(guild_id as u64);
+    let client = api::client(ctx).await;
+    let (existing, occurrence) =
+        match retrieve_event(ctx, inv, &client, guild_id, id, guild_tz).await? {
+            Some(found) => found,
+            None => return Ok(()),
+        };
+
+    if let Some(occurrence) = occurrence {
+        match ask_scope(ctx, inv, &occurrence, "Delete").await? {
+            None => return Ok(()),
+            Some(SeriesScope::ThisOccurrence) => {
+                let series = skip_occurrence(&client, existing, &occurrence, guild_tz).await?;
+                store.forget_occurrence_rsvps(&rsvp::rsvp_key(&occurrence, guild_tz));
+                scheduled::mirror(ctx, &series).await;
+                let _msg = inv
+                    .reply(
+                        ctx,
+                        Reply::corkboard(
+                            "Skipped Occurrence",
+                            vec![event_field(&occurrence, guild_tz)],
+                        ),
+                    )
+                    .await;
+                return Ok(());
+            }
+            Some(SeriesScope::WholeSeries) => {}
         }
-    };
+    }
 
-    let id_map = retrieve_events_id_map(guild_id).await;
-    let real_id_maybe = id_map.get(&id_int).clone();
-    let real_id = match real_id_maybe {
-        Some(i) => i,
-        None => {
-            let _msg = msg
-                .channel_id.say(
-                    &ctx.http,
-                    ":bangbang: Error :bangbang: - Invalid ID! Run the `.events` command to see a list of usable IDs."
-                )
-                .await;
-            return Ok(());
+    println!("Sending Event delete request with ID {:?}", existing.id);
+    let event = client.delete_event(existing.id).await?;
+    store.forget_rsvps(event.id);
+    scheduled::unmirror(ctx, event.id).await;
+
+    let _msg = inv
+        .reply(
+            ctx,
+            Reply::corkboard("Deleted Event", vec![event_field(&event, guild_tz)]),
+        )
+        .await;
+
+    Ok(())
+}
+
+fn event_field(event: &Event, tz: Tz) -> (String, String, bool) {
+    let mut value = format!(
+        "[{}]({}): {}\n**Start:** {}\n**End:** {}",
+        event.title,
+        event.url,
+        event.description,
+        timezone::discord_timestamp(event.start_date),
+        timezone::discord_timestamp(event.end_date)
+    );
+    if let Some(recurrence) = event.recurrence(tz) {
+        value.push_str(&format!("\n**Repeats:** {}", recurrence.describe()));
+    }
+
+    (format!("`{}`", event_handle(event, tz)), value, false)
+}
+
+// Resolves a handle shown by `.events` to the Event, replying with an error if it doesn't
+// match exactly one current event. For a recurring event this also finds the occurrence
+// named after the `@`, or else the next one.
+async fn retrieve_event(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    client: &CorkboardClient,
+    guild_id: i64,
+    handle: &str,
+    tz: Tz,
+) -> Result<Option<(Event, Option<Event>)>, BotError> {
+    let (handle, date) = match split_handle(handle) {
+        Ok(split) => split,
+        Err(error) => {
+            let _msg = inv.say(ctx, error).await;
+            return Ok(None);
         }
     };
 
-    println!("Sending Event delete request with ID {:?}", real_id);
-    let client = reqwest::Client::new();
-    let resp = client
-        .delete(format!("http://localhost:8000/api/v1/event/delete/{}", real_id).as_str())
-        .header(CONTENT_TYPE, "application/json")
-        .send()
-        .await?
-        .json::<HashMap<String, Value>>()
-        .await?;
+    let events = client.list_current_events(guild_id).await?;
+    let ids: Vec<Uuid> = events.iter().map(|event| event.id).collect();
+    let id = match validation::resolve_short_id(ctx, inv, handle, &ids, "events").await {
+        Some(id) => id,
+        None => return Ok(None),
+    };
+    let event = events.into_iter().find(|event| event.id == id).unwrap();
+    if event.recurrence(tz).is_none() {
+        return Ok(Some((event, None)));
+    }
 
-    let title = resp.get("title").unwrap();
-    let url = resp.get("url").unwrap();
-    let description = resp.get("description").unwrap();
-    let start_date = resp.get("start_date").unwrap();
-    let end_date = resp.get("end_date").unwrap();
-
-    let fmt = "%Y-%m-%dT%H:%M:%S%.f";
-    let start_date = NaiveDateTime::parse_from_str(start_date.as_str().unwrap(), fmt)
-        .expect("Unable to parse start_date NaiveDateTime for Event.");
-    let end_date = NaiveDateTime::parse_from_str(end_date.as_str().unwrap(), fmt)
-        .expect("Unable to parse start_date NaiveDateTime for Event.");
-
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Deleted Event (using PST/PDT)")
-                    .image("attachment://cork-board.png")
-                    .field(
-                        format!("{}. ", id),
-                        format!(
-                            "[{}]({}): {}\n**Start:** {}\n**End:** {}",
-                            title,
-                            url,
-                            description,
-                            start_date.format("%m/%d/%Y %-I:%M%p").to_string(),
-                            end_date.format("%m/%d/%Y %-I:%M%p").to_string()
-                        ),
-                        false,
-                    )
-                    .timestamp(Timestamp::now())
-            })
-            .add_file("./resources/cork-board.png")
+    let occurrence = find_occurrence(&event, tz, date);
+    if let (Some(date), None) = (date, &occurrence) {
+        let _msg = inv
+            .say(
+                ctx,
+                format!(
+                    ":bangbang: Error :bangbang: - Event `{}` doesn't happen on {}.",
+                    handle,
+                    date.format("%a %m/%d/%Y")
+                ),
+            )
+            .await;
+        return Ok(None);
+    }
+
+    Ok(Some((event, occurrence)))
+}
+
+// The series' new start and end when one of its occurrences is edited to `start` and
+// `end`. The start moves by as much as the occurrence's does on the clock in the guild's
+// time zone, so a series moved from 7pm to 8pm is at 8pm on both sides of a daylight
+// saving change.
+fn shift_series(
+    series: &Event,
+    occurrence: &Event,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+    tz: Tz,
+) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let moved = timezone::from_utc(tz, start) - timezone::from_utc(tz, occurrence.start_date);
+    let first = timezone::from_utc(tz, series.start_date).checked_add_signed(moved)?;
+    let first = timezone::to_utc(tz, first);
+    Some((first, first.checked_add_signed(end - start)?))
+}
+
+async fn skip_occurrence(
+    client: &CorkboardClient,
+    mut series: Event,
+    occurrence: &Event,
+    tz: Tz,
+) -> Result<Event, BotError> {
+    series.exceptions.push(occurrence_date(occurrence, tz));
+    println!("Sending Event edit request with {:?}", series);
+    client.update_event(&series).await
+}
+
+enum SeriesScope {
+    ThisOccurrence,
+    WholeSeries,
+}
+
+// Asks whoever ran the command whether a change to a recurring event is for one
+// occurrence or the whole series. Nothing is changed if they don't answer in time.
+async fn ask_scope(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    occurrence: &Event,
+    verb: &str,
+) -> serenity::Result<Option<SeriesScope>> {
+    let action_row = CreateActionRow::default()
+        .create_button(|b| {
+            b.style(ButtonStyle::Primary)
+                .custom_id("scope_occurrence")
+                .label("This occurrence")
         })
+        .create_button(|b| {
+            b.style(ButtonStyle::Secondary)
+                .custom_id("scope_series")
+                .label("Whole series")
+        })
+        .clone();
+    let mut components = CreateComponents::default();
+    components.add_action_row(action_row);
+
+    let question = format!(
+        "**{}** repeats. {} only the occurrence on {}, or the whole series?",
+        occurrence.title,
+        verb,
+        timezone::discord_timestamp(occurrence.start_date)
+    );
+    let mut msg = inv
+        .reply(ctx, Reply::text(&question).components(components))
+        .await?;
+
+    let interaction = msg
+        .await_component_interaction(ctx)
+        .author_id(inv.user().id)
+        .timeout(SCOPE_TIMEOUT)
         .await;
+    let (scope, answer) = match interaction.as_ref().map(|i| i.data.custom_id.as_str()) {
+        Some("scope_occurrence") => (Some(SeriesScope::ThisOccurrence), "Only this occurrence."),
+        Some("scope_series") => (Some(SeriesScope::WholeSeries), "The whole series."),
+        _ => (None, "No answer, so nothing was changed."),
+    };
 
-    Ok(())
+    // The buttons are removed so the question can't be answered twice.
+    let content = format!("{}\n{}", question, answer);
+    match interaction {
+        Some(interaction) => {
+            interaction
+                .create_interaction_response(&ctx.http, |r| {
+                    r.kind(InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| {
+                            d.content(content)
+                                .set_components(CreateComponents::default())
+                        })
+                })
+                .await?
+        }
+        None => {
+            let _ = msg
+                .edit(&ctx.http, |m| {
+                    m.content(content)
+                        .set_components(CreateComponents::default())
+                })
+                .await;
+        }
+    }
+
+    Ok(scope)
 }
 
-async fn retrieve_events_id_map(guild_id: i64) -> HashMap<i32, String> {
-    let resp = reqwest::get(format!(
-        "http://localhost:8000/api/v1/event/current/guild/{}",
-        guild_id
-    ))
-    .await
-    .unwrap()
-    .json::<Vec<HashMap<String, Value>>>()
-    .await
-    .unwrap();
-    let mut events: Vec<Event> = Vec::new();
-    for event_map in resp {
-        events.push(Event::to_event(event_map));
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::America::Los_Angeles;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
     }
 
-    let mut result: HashMap<i32, String> = HashMap::new();
-    let mut i = 1;
-    for event in events {
-        result.insert(i, event.id.to_string());
-        i += 1;
+    fn weekly(start: NaiveDateTime, end: NaiveDateTime) -> Event {
+        Event {
+            recurrence: Some("FREQ=WEEKLY".to_string()),
+            ..Event::new(
+                Uuid::nil(),
+                1,
+                "Game night".to_string(),
+                String::new(),
+                String::new(),
+                start,
+                end,
+            )
+        }
     }
 
-    result
+    #[test]
+    fn editing_an_occurrence_moves_the_series_by_as_much() {
+        // Thursdays at 7pm in Los Angeles, from before daylight saving time ends.
+        let series = weekly(at(2024, 10, 4, 2, 0), at(2024, 10, 4, 4, 0));
+        let occurrence =
+            find_occurrence(&series, Los_Angeles, NaiveDate::from_ymd_opt(2024, 11, 7)).unwrap();
+        assert_eq!(occurrence.start_date, at(2024, 11, 8, 3, 0));
+
+        // Moved to 8pm and made three hours long.
+        let shifted = shift_series(
+            &series,
+            &occurrence,
+            at(2024, 11, 8, 4, 0),
+            at(2024, 11, 8, 7, 0),
+            Los_Angeles,
+        );
+        assert_eq!(
+            shifted,
+            Some((at(2024, 10, 4, 3, 0), at(2024, 10, 4, 6, 0)))
+        );
+    }
+
+    #[test]
+    fn unchanged_dates_leave_the_series_where_it_was() {
+        let series = weekly(at(2024, 10, 4, 2, 0), at(2024, 10, 4, 4, 0));
+        let occurrence =
+            find_occurrence(&series, Los_Angeles, NaiveDate::from_ymd_opt(2024, 11, 14)).unwrap();
+        let shifted = shift_series(
+            &series,
+            &occurrence,
+            occurrence.start_date,
+            occurrence.end_date,
+            Los_Angeles,
+        );
+        assert_eq!(shifted, Some((series.start_date, series.end_date)));
+    }
 }
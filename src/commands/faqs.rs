@@ -1,21 +1,21 @@
 extern crate serde;
 extern crate serde_json;
 
-use std::collections::HashMap;
-
-use reqwest::header::CONTENT_TYPE;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use serenity::framework::standard::macros::command;
 use serenity::framework::standard::{Args, CommandResult};
 use serenity::model::channel::Message;
-use serenity::model::Timestamp;
 use serenity::prelude::*;
 use uuid::Uuid;
 
+use crate::api;
+use crate::api::client::CorkboardClient;
+use crate::error::BotError;
 use crate::validation::validation;
 
-#[derive(Serialize, Deserialize, Debug)]
+use super::invocation::{Invocation, Reply};
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Faq {
     pub id: Uuid,
     pub guild_id: i64,
@@ -24,8 +24,7 @@ pub struct Faq {
 }
 
 impl Faq {
-    pub fn new(id: &str, guild_id: i64, question: String, answer: String) -> Self {
-        let id = Uuid::parse_str(id).expect("Bad UUID");
+    pub fn new(id: Uuid, guild_id: i64, question: String, answer: String) -> Self {
         Faq {
             id,
             guild_id,
@@ -33,20 +32,6 @@ impl Faq {
             answer,
         }
     }
-
-    pub fn to_faq(faq_map: HashMap<String, Value>) -> Self {
-        Faq::new(
-            faq_map.get("id").unwrap().as_str().unwrap(),
-            faq_map.get("guild_id").unwrap().as_i64().unwrap(),
-            faq_map
-                .get("question")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .to_string(),
-            faq_map.get("answer").unwrap().as_str().unwrap().to_string(),
-        )
-    }
 }
 
 #[derive(Serialize, Debug)]
@@ -69,22 +54,16 @@ impl NewFaq {
 #[command]
 #[description = "Retrieves all FAQs."]
 async fn faqs(ctx: &Context, msg: &Message) -> CommandResult {
+    run_faqs(ctx, &Invocation::Prefix(msg)).await
+}
+
+pub async fn run_faqs(ctx: &Context, inv: &Invocation<'_>) -> CommandResult {
     println!("Got FAQs command..");
-    let resp = reqwest::get(format!(
-        "http://localhost:8000/api/v1/faq/guild/{}",
-        msg.guild_id.unwrap()
-    ))
-    .await?
-    .json::<Vec<HashMap<String, Value>>>()
-    .await?;
-    let mut faqs: Vec<Faq> = Vec::new();
-    for faq_map in resp {
-        faqs.push(Faq::to_faq(faq_map));
-    }
+    let client = api::client(ctx).await;
+    let faqs = client.list_faqs(i64::from(inv.guild_id().unwrap())).await?;
 
     let mut faq_fields: Vec<(String, String, bool)> = Vec::new();
-    let mut i = 1;
-    if faqs.len() == 0 {
+    if faqs.is_empty() {
         faq_fields.push((
             "FAQs: ".to_string(),
             "No current FAQs found!".to_string(),
@@ -92,23 +71,11 @@ async fn faqs(ctx: &Context, msg: &Message) -> CommandResult {
         ));
     } else {
         for faq in faqs {
-            faq_fields.push((format!("{}. {}", i, faq.question), faq.answer, false));
-            i += 1;
+            faq_fields.push(faq_field(&faq));
         }
     }
 
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("FAQs")
-                    .image("attachment://cork-board.png")
-                    .fields(faq_fields)
-                    .timestamp(Timestamp::now())
-            })
-            .add_file("./resources/cork-board.png")
-        })
-        .await;
+    let _msg = inv.reply(ctx, Reply::corkboard("FAQs", faq_fields)).await;
 
     println!("Finished processing FAQs command!");
     Ok(())
@@ -120,40 +87,37 @@ async fn faqs(ctx: &Context, msg: &Message) -> CommandResult {
 #[usage = "\"Question\" \"Answer\""]
 async fn add_faq(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let arg_names = vec!["Question", "Answer"];
-    if !validation::has_corkboard_role(ctx, msg).await
-        || !validation::has_correct_arg_count(ctx, msg, 2, args.len(), arg_names, "add_faq").await
-    {
+    if !validation::has_correct_arg_count(ctx, msg, 2, args.len(), arg_names, "add_faq").await {
         return Ok(());
     }
 
-    let guild_id = i64::from(msg.guild_id.unwrap());
     let question = args.single_quoted::<String>().unwrap();
     let answer = args.single_quoted::<String>().unwrap();
+    run_add_faq(ctx, &Invocation::Prefix(msg), question, answer).await
+}
+
+pub async fn run_add_faq(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    question: String,
+    answer: String,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let guild_id = i64::from(inv.guild_id().unwrap());
     let new = NewFaq::new(guild_id, question, answer);
 
     println!("Sending new FAQ creation request with {:?}", new);
-    let client = reqwest::Client::new();
-    let resp = client
-        .post("http://localhost:8000/api/v1/faq")
-        .json(&new)
-        .send()
-        .await?
-        .json::<HashMap<String, Value>>()
-        .await?;
-
-    let question = resp.get("question").unwrap();
-    let answer = resp.get("answer").unwrap();
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Created New FAQ")
-                    .image("attachment://cork-board.png")
-                    .field(question.to_string(), answer.to_string(), false)
-                    .timestamp(Timestamp::now())
-            })
-            .add_file("./resources/cork-board.png")
-        })
+    let client = api::client(ctx).await;
+    let faq = client.create_faq(&new).await?;
+
+    let _msg = inv
+        .reply(
+            ctx,
+            Reply::corkboard("Created New FAQ", vec![faq_field(&faq)]),
+        )
         .await;
 
     Ok(())
@@ -165,72 +129,42 @@ async fn add_faq(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult
 #[usage = "FAQ_id \"Question\" \"Answer\""]
 async fn edit_faq(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let arg_names = vec!["FAQ_id", "Question", "Answer"];
-    if !validation::has_corkboard_role(ctx, msg).await
-        || !validation::has_correct_arg_count(ctx, msg, 3, args.len(), arg_names, "edit_faq").await
-    {
+    if !validation::has_correct_arg_count(ctx, msg, 3, args.len(), arg_names, "edit_faq").await {
         return Ok(());
     }
 
-    let guild_id = i64::from(msg.guild_id.unwrap());
     let id = args.current().unwrap().to_string();
     args.advance();
     let question = args.single_quoted::<String>().unwrap();
     let answer = args.single_quoted::<String>().unwrap();
+    run_edit_faq(ctx, &Invocation::Prefix(msg), &id, question, answer).await
+}
 
-    let id_int = match id.parse::<i32>() {
-        Ok(i) => i,
-        _error => {
-            let _msg = msg
-                .channel_id
-                .say(
-                    &ctx.http,
-                    ":bangbang: Error :bangbang: - Unable to parse ID.",
-                )
-                .await;
-            return Ok(());
-        }
-    };
+pub async fn run_edit_faq(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    id: &str,
+    question: String,
+    answer: String,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
 
-    let id_map = retrieve_faqs_id_map(guild_id).await;
-    let real_id_maybe = id_map.get(&id_int).clone();
-    let real_id = match real_id_maybe {
+    let guild_id = i64::from(inv.guild_id().unwrap());
+    let client = api::client(ctx).await;
+    let real_id = match retrieve_faq_id(ctx, inv, &client, guild_id, id).await? {
         Some(i) => i,
-        None => {
-            let _msg = msg
-                .channel_id.say(
-                    &ctx.http,
-                    ":bangbang: Error :bangbang: - Invalid ID! Run the `.faqs` command to see a list of usable IDs."
-                )
-                .await;
-            return Ok(());
-        }
+        None => return Ok(()),
     };
 
-    let new = Faq::new(real_id.as_str(), guild_id, question, answer);
+    let new = Faq::new(real_id, guild_id, question, answer);
 
     println!("Sending FAQ edit request with {:?}", new);
-    let client = reqwest::Client::new();
-    let resp = client
-        .put(format!("http://localhost:8000/api/v1/faq/{}", real_id).as_str())
-        .json(&new)
-        .send()
-        .await?
-        .json::<HashMap<String, Value>>()
-        .await?;
-
-    let question = resp.get("question").unwrap();
-    let answer = resp.get("answer").unwrap();
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Edited FAQ")
-                    .image("attachment://cork-board.png")
-                    .field(question.to_string(), answer.to_string(), false)
-                    .timestamp(Timestamp::now())
-            })
-            .add_file("./resources/cork-board.png")
-        })
+    let faq = client.update_faq(&new).await?;
+
+    let _msg = inv
+        .reply(ctx, Reply::corkboard("Edited FAQ", vec![faq_field(&faq)]))
         .await;
 
     Ok(())
@@ -242,94 +176,55 @@ async fn edit_faq(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult
 #[usage = "FAQ_id"]
 async fn delete_faq(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let arg_names = vec!["FAQ_id"];
-    if !validation::has_corkboard_role(ctx, msg).await
-        || !validation::has_correct_arg_count(ctx, msg, 1, args.len(), arg_names, "delete_faq")
-            .await
-    {
+    if !validation::has_correct_arg_count(ctx, msg, 1, args.len(), arg_names, "delete_faq").await {
         return Ok(());
     }
 
-    let guild_id = i64::from(msg.guild_id.unwrap());
     args.quoted();
     let id = args.current().unwrap().to_string();
-    let id_int = match id.parse::<i32>() {
-        Ok(i) => i,
-        _error => {
-            let _msg = msg
-                .channel_id
-                .say(
-                    &ctx.http,
-                    ":bangbang: Error :bangbang: - Unable to parse ID.",
-                )
-                .await;
-            return Ok(());
-        }
-    };
+    run_delete_faq(ctx, &Invocation::Prefix(msg), &id).await
+}
 
-    let id_map = retrieve_faqs_id_map(guild_id).await;
-    let real_id_maybe = id_map.get(&id_int).clone();
-    let real_id = match real_id_maybe {
+pub async fn run_delete_faq(ctx: &Context, inv: &Invocation<'_>, id: &str) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let guild_id = i64::from(inv.guild_id().unwrap());
+    let client = api::client(ctx).await;
+    let real_id = match retrieve_faq_id(ctx, inv, &client, guild_id, id).await? {
         Some(i) => i,
-        None => {
-            let _msg = msg
-                .channel_id.say(
-                    &ctx.http,
-                    ":bangbang: Error :bangbang: - Invalid ID! Run the `.faqs` command to see a list of usable IDs."
-                )
-                .await;
-            return Ok(());
-        }
+        None => return Ok(()),
     };
 
     println!("Sending FAQ delete request with ID {:?}", real_id);
-    let client = reqwest::Client::new();
-    let resp = client
-        .delete(format!("http://localhost:8000/api/v1/faq/delete/{}", real_id).as_str())
-        .header(CONTENT_TYPE, "application/json")
-        .send()
-        .await?
-        .json::<HashMap<String, Value>>()
-        .await?;
-
-    let question = resp.get("question").unwrap();
-    let answer = resp.get("answer").unwrap();
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Deleted FAQ")
-                    .image("attachment://cork-board.png")
-                    .field(question.to_string(), answer.to_string(), false)
-                    .timestamp(Timestamp::now())
-            })
-            .add_file("./resources/cork-board.png")
-        })
+    let faq = client.delete_faq(real_id).await?;
+
+    let _msg = inv
+        .reply(ctx, Reply::corkboard("Deleted FAQ", vec![faq_field(&faq)]))
         .await;
 
     Ok(())
 }
 
-async fn retrieve_faqs_id_map(guild_id: i64) -> HashMap<i32, String> {
-    let resp = reqwest::get(format!(
-        "http://localhost:8000/api/v1/faq/guild/{}",
-        guild_id
-    ))
-    .await
-    .unwrap()
-    .json::<Vec<HashMap<String, Value>>>()
-    .await
-    .unwrap();
-    let mut faqs: Vec<Faq> = Vec::new();
-    for faq_map in resp {
-        faqs.push(Faq::to_faq(faq_map));
-    }
-
-    let mut result: HashMap<i32, String> = HashMap::new();
-    let mut i = 1;
-    for faq in faqs {
-        result.insert(i, faq.id.to_string());
-        i += 1;
-    }
+fn faq_field(faq: &Faq) -> (String, String, bool) {
+    (
+        format!("`{}` {}", validation::short_id(&faq.id), faq.question),
+        faq.answer.clone(),
+        false,
+    )
+}
 
-    result
+// Resolves the short ID shown by `.faqs` to the FAQ's UUID, replying with an error
+// if it doesn't match exactly one current FAQ.
+async fn retrieve_faq_id(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    client: &CorkboardClient,
+    guild_id: i64,
+    handle: &str,
+) -> Result<Option<Uuid>, BotError> {
+    let faqs = client.list_faqs(guild_id).await?;
+    let ids: Vec<Uuid> = faqs.iter().map(|faq| faq.id).collect();
+    Ok(validation::resolve_short_id(ctx, inv, handle, &ids, "faqs").await)
 }
@@ -0,0 +1,591 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use serenity::framework::standard::CommandResult;
+use serenity::model::application::component::{ActionRowComponent, InputTextStyle};
+use serenity::model::application::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::model::application::interaction::modal::ModalSubmitInteraction;
+use serenity::model::application::interaction::InteractionResponseType;
+use serenity::prelude::*;
+use uuid::Uuid;
+
+use crate::api;
+use crate::dates::{self, DateError};
+use crate::error::BotError;
+use crate::store;
+use crate::timezone;
+use crate::validation::validation;
+
+use super::events::{self, DATE_FORMAT};
+use super::faqs;
+use super::invocation::Invocation;
+use super::pins;
+
+// Discord caps text inputs at 4000 characters; titles are kept short so they fit in embeds.
+static MAX_TITLE_LEN: u64 = 256;
+static MAX_TEXT_LEN: u64 = 4000;
+
+#[derive(Clone, Copy)]
+pub enum FormKind {
+    Pin,
+    Event,
+    Faq,
+}
+
+impl FormKind {
+    pub fn from_command(name: &str) -> Option<Self> {
+        match name {
+            "pin_form" => Some(FormKind::Pin),
+            "event_form" => Some(FormKind::Event),
+            "faq_form" => Some(FormKind::Faq),
+            _ => None,
+        }
+    }
+
+    fn command(&self) -> &'static str {
+        match self {
+            FormKind::Pin => "pin_form",
+            FormKind::Event => "event_form",
+            FormKind::Faq => "faq_form",
+        }
+    }
+
+    fn noun(&self) -> &'static str {
+        match self {
+            FormKind::Pin => "Pin",
+            FormKind::Event => "Event",
+            FormKind::Faq => "FAQ",
+        }
+    }
+
+    fn list_command(&self) -> &'static str {
+        match self {
+            FormKind::Pin => "pins",
+            FormKind::Event => "events",
+            FormKind::Faq => "faqs",
+        }
+    }
+}
+
+struct Field {
+    id: &'static str,
+    label: &'static str,
+    style: InputTextStyle,
+    max_length: u64,
+    placeholder: Option<&'static str>,
+    value: Option<String>,
+    required: bool,
+}
+
+impl Field {
+    fn short(id: &'static str, label: &'static str, value: Option<String>) -> Self {
+        Field {
+            id,
+            label,
+            style: InputTextStyle::Short,
+            max_length: MAX_TITLE_LEN,
+            placeholder: None,
+            value,
+            required: true,
+        }
+    }
+
+    fn paragraph(id: &'static str, label: &'static str, value: Option<String>) -> Self {
+        Field {
+            id,
+            label,
+            style: InputTextStyle::Paragraph,
+            max_length: MAX_TEXT_LEN,
+            placeholder: None,
+            value,
+            required: true,
+        }
+    }
+
+    fn optional(
+        id: &'static str,
+        label: &'static str,
+        placeholder: &'static str,
+        value: Option<String>,
+    ) -> Self {
+        Field {
+            placeholder: Some(placeholder),
+            required: false,
+            ..Field::short(id, label, value)
+        }
+    }
+
+    fn date(id: &'static str, label: &'static str, value: Option<String>) -> Self {
+        Field {
+            placeholder: Some("tomorrow 8pm, 12/1/2022 9:00AM, 2022-12-01 21:00"),
+            ..Field::short(id, label, value)
+        }
+    }
+}
+
+// Opens the add/edit form for a pin, event or FAQ. With an `id` option the form is
+// prefilled from the existing item and submitting it edits that item.
+pub async fn open(ctx: &Context, command: &ApplicationCommandInteraction, kind: FormKind) {
+    println!("Got {} command..", kind.command());
+    match build_form(ctx, command, kind).await {
+        Ok(Ok((custom_id, fields))) => {
+            let title = if custom_id.contains(':') {
+                format!("Edit {}", kind.noun())
+            } else {
+                format!("Add {}", kind.noun())
+            };
+            if let Err(why) = command
+                .create_interaction_response(&ctx.http, |r| {
+                    r.kind(InteractionResponseType::Modal)
+                        .interaction_response_data(|d| {
+                            d.custom_id(custom_id).title(title).components(|c| {
+                                for field in fields {
+                                    c.create_action_row(|row| {
+                                        row.create_input_text(|t| {
+                                            t.custom_id(field.id)
+                                                .label(field.label)
+                                                .style(field.style)
+                                                .max_length(field.max_length)
+                                                .required(field.required);
+                                            if let Some(placeholder) = field.placeholder {
+                                                t.placeholder(placeholder);
+                                            }
+                                            if let Some(value) = field.value {
+                                                t.value(value);
+                                            }
+                                            t
+                                        })
+                                    });
+                                }
+                                c
+                            })
+                        })
+                })
+                .await
+            {
+                println!("Unable to open {} form: {:?}", kind.noun(), why);
+            }
+        }
+        Ok(Err(error)) => refuse(ctx, command, error).await,
+        Err(why) => {
+            why.log(kind.command());
+            refuse(ctx, command, why.user_message()).await;
+        }
+    }
+}
+
+// The modal's custom ID carries the kind of item and, when editing, its UUID, so the
+// submission can be routed without keeping any state between the two interactions.
+async fn build_form(
+    ctx: &Context,
+    command: &ApplicationCommandInteraction,
+    kind: FormKind,
+) -> Result<Result<(String, Vec<Field>), String>, BotError> {
+    let guild_id = command.guild_id.unwrap();
+    if validation::user_has_corkboard_role(ctx, guild_id, &command.user).await != Some(true) {
+        return Ok(Err(validation::CORKBOARD_ROLE_ERROR.to_string()));
+    }
+
+    let handle = command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == "id")
+        .and_then(|option| match &option.resolved {
+            Some(CommandDataOptionValue::String(value)) => Some(value.clone()),
+            _ => None,
+        });
+
+    let store = store::store(ctx).await;
+    let tz = store.timezone_for(guild_id.into(), command.user.id.into());
+    let guild_tz = store.guild_timezone(guild_id.into());
+    let guild_id = i64::from(guild_id);
+    let client = api::client(ctx).await;
+    let form = match (kind, handle) {
+        (FormKind::Pin, None) => (kind.command().to_string(), pin_fields(None)),
+        (FormKind::Event, None) => (
+            kind.command().to_string(),
+            event_fields(None, None, None, None, None),
+        ),
+        (FormKind::Faq, None) => (kind.command().to_string(), faq_fields(None, None)),
+        (FormKind::Pin, Some(handle)) => {
+            let pins = client.list_pins(guild_id).await?;
+            let ids: Vec<Uuid> = pins.iter().map(|pin| pin.id).collect();
+            let id = match validation::lookup_short_id(&handle, &ids, kind.list_command()) {
+                Ok(id) => id,
+                Err(error) => return Ok(Err(error)),
+            };
+            let pin = pins.into_iter().find(|pin| pin.id == id).unwrap();
+            (format!("{}:{}", kind.command(), id), pin_fields(Some(pin)))
+        }
+        (FormKind::Event, Some(handle)) => {
+            let (handle, date) = match events::split_handle(&handle) {
+                Ok(split) => split,
+                Err(error) => return Ok(Err(error)),
+            };
+            let events = client.list_current_events(guild_id).await?;
+            let ids: Vec<Uuid> = events.iter().map(|event| event.id).collect();
+            let id = match validation::lookup_short_id(handle, &ids, kind.list_command()) {
+                Ok(id) => id,
+                Err(error) => return Ok(Err(error)),
+            };
+            let series = events.into_iter().find(|event| event.id == id).unwrap();
+            // A recurring event is edited through one of its occurrences, which the
+            // submission names the same way `.events` does.
+            let (event, target) = match series.recurrence(guild_tz) {
+                Some(_) => match events::find_occurrence(&series, guild_tz, date) {
+                    Some(occurrence) => {
+                        let date = events::occurrence_date(&occurrence, guild_tz);
+                        (occurrence, format!("{}@{}", id, date.format("%Y-%m-%d")))
+                    }
+                    None => (series, id.to_string()),
+                },
+                None => (series, id.to_string()),
+            };
+            (
+                format!("{}:{}", kind.command(), target),
+                event_fields(
+                    Some(event.title),
+                    Some(event.url),
+                    Some(event.description),
+                    Some(local_date(tz, event.start_date)),
+                    Some(local_date(tz, event.end_date)),
+                ),
+            )
+        }
+        (FormKind::Faq, Some(handle)) => {
+            let faqs = client.list_faqs(guild_id).await?;
+            let ids: Vec<Uuid> = faqs.iter().map(|faq| faq.id).collect();
+            let id = match validation::lookup_short_id(&handle, &ids, kind.list_command()) {
+                Ok(id) => id,
+                Err(error) => return Ok(Err(error)),
+            };
+            let faq = faqs.into_iter().find(|faq| faq.id == id).unwrap();
+            (
+                format!("{}:{}", kind.command(), id),
+                faq_fields(Some(faq.question), Some(faq.answer)),
+            )
+        }
+    };
+
+    Ok(Ok(form))
+}
+
+fn pin_fields(pin: Option<pins::Pin>) -> Vec<Field> {
+    let (title, url, description, category, tags) = match pin {
+        Some(pin) => {
+            let tags: Vec<String> = pin.tags.iter().map(|tag| format!("#{}", tag)).collect();
+            (
+                Some(pin.title),
+                Some(pin.url),
+                Some(pin.description),
+                pin.category,
+                Some(tags.join(" ")),
+            )
+        }
+        None => (None, None, None, None, None),
+    };
+    vec![
+        Field::short("title", "Title", title),
+        Field::short("url", "URL", url),
+        Field::paragraph("description", "Description", description),
+        Field::optional("category", "Category", "e.g. Guides", category),
+        Field::optional("tags", "Tags", "e.g. #raids #events", tags),
+    ]
+}
+
+fn event_fields(
+    title: Option<String>,
+    url: Option<String>,
+    description: Option<String>,
+    start_date: Option<String>,
+    end_date: Option<String>,
+) -> Vec<Field> {
+    vec![
+        Field::short("title", "Title", title),
+        Field::short("url", "URL", url),
+        Field::paragraph("description", "Description", description),
+        Field::date("start_date", "Start (your time zone)", start_date),
+        Field::date("end_date", "End, or a duration like 2h", end_date),
+    ]
+}
+
+fn local_date(tz: Tz, utc: NaiveDateTime) -> String {
+    timezone::from_utc(tz, utc).format(DATE_FORMAT).to_string()
+}
+
+fn faq_fields(question: Option<String>, answer: Option<String>) -> Vec<Field> {
+    vec![
+        Field::short("question", "Question", question),
+        Field::paragraph("answer", "Answer", answer),
+    ]
+}
+
+async fn refuse(ctx: &Context, command: &ApplicationCommandInteraction, error: String) {
+    let _ = command
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| d.content(error).ephemeral(true))
+        })
+        .await;
+}
+
+// Handles a submitted form: every field is checked, and only when all of them are valid
+// is the item created or edited through the same code as the add/edit commands.
+pub async fn submit(ctx: &Context, modal: &ModalSubmitInteraction) {
+    let (command, id) = match modal.data.custom_id.split_once(':') {
+        Some((command, id)) => (command, Some(id.to_string())),
+        None => (modal.data.custom_id.as_str(), None),
+    };
+    let kind = match FormKind::from_command(command) {
+        Some(kind) => kind,
+        None => return,
+    };
+    println!("Got {} submission..", kind.command());
+
+    if let Err(why) = modal
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await
+    {
+        println!("Unable to acknowledge {} form: {:?}", kind.noun(), why);
+        return;
+    }
+
+    let inv = Invocation::Modal(modal);
+    if let Err(why) = save(ctx, &inv, kind, id, field_values(modal)).await {
+        match why.downcast_ref::<BotError>() {
+            Some(bot_error) => bot_error.report(ctx, &inv, kind.command()).await,
+            None => println!("Command '{}' returned error {:?}", kind.command(), why),
+        }
+    }
+}
+
+fn field_values(modal: &ModalSubmitInteraction) -> HashMap<String, String> {
+    modal
+        .data
+        .components
+        .iter()
+        .flat_map(|row| row.components.iter())
+        .filter_map(|component| match component {
+            ActionRowComponent::InputText(input) => {
+                Some((input.custom_id.clone(), input.value.trim().to_string()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+async fn save(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    kind: FormKind,
+    id: Option<String>,
+    mut values: HashMap<String, String>,
+) -> CommandResult {
+    let tz = store::store(ctx)
+        .await
+        .timezone_for(inv.guild_id().unwrap().into(), inv.user().id.into());
+    let errors = field_errors(kind, &values, tz);
+    if !errors.is_empty() {
+        let _msg = inv
+            .say(
+                ctx,
+                format!(
+                    ":bangbang: Error :bangbang: - The {} wasn't saved:\n{}",
+                    kind.noun(),
+                    errors.join("\n")
+                ),
+            )
+            .await;
+        return Ok(());
+    }
+
+    let mut take = |field: &str| values.remove(field).unwrap_or_default();
+    match (kind, id) {
+        (FormKind::Pin, None) => {
+            pins::run_add_pin(
+                ctx,
+                inv,
+                take("title"),
+                take("url"),
+                take("description"),
+                &take("category"),
+                &take("tags"),
+                "",
+            )
+            .await
+        }
+        // The form is filled in with the pin's category and tags, so emptying them
+        // clears them.
+        (FormKind::Pin, Some(id)) => {
+            pins::run_edit_pin(
+                ctx,
+                inv,
+                &id,
+                take("title"),
+                take("url"),
+                take("description"),
+                Some(&take("category")),
+                Some(&take("tags")),
+            )
+            .await
+        }
+        (FormKind::Event, None) => {
+            events::run_add_event(
+                ctx,
+                inv,
+                take("title"),
+                take("url"),
+                take("description"),
+                &take("start_date"),
+                &take("end_date"),
+            )
+            .await
+        }
+        (FormKind::Event, Some(id)) => {
+            events::run_edit_event(
+                ctx,
+                inv,
+                &id,
+                take("title"),
+                take("url"),
+                take("description"),
+                &take("start_date"),
+                &take("end_date"),
+            )
+            .await
+        }
+        (FormKind::Faq, None) => {
+            faqs::run_add_faq(ctx, inv, take("question"), take("answer")).await
+        }
+        (FormKind::Faq, Some(id)) => {
+            faqs::run_edit_faq(ctx, inv, &id, take("question"), take("answer")).await
+        }
+    }
+}
+
+// One line per invalid field, naming the field and what's wrong with it.
+fn field_errors(kind: FormKind, values: &HashMap<String, String>, tz: Tz) -> Vec<String> {
+    let value = |field: &str| values.get(field).map(String::as_str).unwrap_or("");
+    let mut errors = Vec::new();
+
+    let required: &[(&str, &str)] = match kind {
+        FormKind::Pin => &[
+            ("title", "Title"),
+            ("url", "URL"),
+            ("description", "Description"),
+        ],
+        FormKind::Event => &[
+            ("title", "Title"),
+            ("url", "URL"),
+            ("description", "Description"),
+            ("start_date", "Start Date"),
+            ("end_date", "End Date"),
+        ],
+        FormKind::Faq => &[("question", "Question"), ("answer", "Answer")],
+    };
+    for (field, label) in required {
+        if value(field).is_empty() {
+            errors.push(format!("- **{}** can't be empty.", label));
+        }
+    }
+
+    if !matches!(kind, FormKind::Faq)
+        && !value("url").is_empty()
+        && !validation::is_web_url(value("url"))
+    {
+        errors.push(format!(
+            "- **URL** `{}` isn't a valid http:// or https:// link.",
+            value("url")
+        ));
+    }
+
+    if let FormKind::Event = kind {
+        let now = Utc::now().naive_utc();
+        // The accepted formats are listed once if either date couldn't be read.
+        let mut unrecognized = false;
+        let start = match dates::parse_start(value("start_date"), tz, now) {
+            Ok(start) => Some(start),
+            Err(error) => {
+                unrecognized |= matches!(error, DateError::Unrecognized(_));
+                errors.push(format!(
+                    "- **Start Date** `{}` isn't a date I understand.",
+                    value("start_date")
+                ));
+                None
+            }
+        };
+        if let Some(start) = start {
+            match dates::parse_end(value("end_date"), start, tz, now) {
+                Ok(end) if end < start => {
+                    errors.push("- **End Date** is before the start date.".to_string())
+                }
+                Ok(_) => {}
+                Err(DateError::Unrecognized(_)) => {
+                    unrecognized = true;
+                    errors.push(format!(
+                        "- **End Date** `{}` isn't a date or duration I understand.",
+                        value("end_date")
+                    ))
+                }
+                Err(DateError::OutOfRange(_)) => errors.push(format!(
+                    "- **End Date** `{}` ends too far after the start.",
+                    value("end_date")
+                )),
+            }
+        }
+        if unrecognized {
+            errors.push(dates::ACCEPTED_FORMATS.to_string());
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event_errors(start_date: &str, end_date: &str) -> Vec<String> {
+        let values: HashMap<String, String> = [
+            ("title", "Raid night"),
+            ("url", "https://example.com"),
+            ("description", "Bring potions"),
+            ("start_date", start_date),
+            ("end_date", end_date),
+        ]
+        .into_iter()
+        .map(|(field, value)| (field.to_string(), value.to_string()))
+        .collect();
+        field_errors(FormKind::Event, &values, chrono_tz::UTC)
+    }
+
+    #[test]
+    fn unreadable_dates_are_named_and_the_formats_listed_once() {
+        let errors = event_errors("someday", "2h");
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].starts_with("- **Start Date** `someday`"));
+        assert_eq!(errors[1], dates::ACCEPTED_FORMATS);
+
+        let errors = event_errors("tomorrow 8pm", "later");
+        assert!(errors[0].starts_with("- **End Date** `later`"));
+        assert_eq!(errors.last().unwrap(), dates::ACCEPTED_FORMATS);
+    }
+
+    #[test]
+    fn dates_that_are_read_but_wrong_dont_list_the_formats() {
+        assert_eq!(
+            event_errors("tomorrow 8pm", "9999999999h"),
+            vec!["- **End Date** `9999999999h` ends too far after the start."]
+        );
+        assert_eq!(
+            event_errors("tomorrow 8pm", "tomorrow 7pm"),
+            vec!["- **End Date** is before the start date."]
+        );
+        assert!(event_errors("tomorrow 8pm", "2h").is_empty());
+    }
+}
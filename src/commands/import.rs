@@ -0,0 +1,270 @@
+use std::time::Duration;
+
+use chrono_tz::Tz;
+use serenity::builder::{CreateActionRow, CreateComponents, CreateEmbed};
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::application::interaction::InteractionResponseType;
+use serenity::model::channel::{Attachment, Message};
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+
+use crate::api;
+use crate::ics;
+use crate::migration;
+use crate::recurrence::Recurrence;
+use crate::scheduled;
+use crate::store;
+use crate::timezone;
+use crate::validation::validation;
+
+use super::events::NewEvent;
+use super::invocation::{Invocation, Reply};
+
+// Calendar exports are small; anything bigger is almost certainly the wrong file.
+static MAX_FILE_SIZE: u64 = 1024 * 1024;
+static CONFIRM_TIMEOUT: Duration = Duration::from_secs(120);
+// Discord allows at most 25 fields in an embed, one is kept for the "and N more" line.
+static MAX_PREVIEWED_EVENTS: usize = 24;
+// Keeps the preview message well under Discord's 2000 character limit.
+static MAX_SHOWN_NOTES: usize = 10;
+
+#[command]
+#[allowed_roles("corkboard")]
+#[description = "Import Events from an attached iCalendar (`.ics`) file, such as an export from Google Calendar or Outlook. Shows what will be created and asks for confirmation first. Times without a time zone are read in yours (see `.timezone`), and events that are already over are left out."]
+#[usage = "(attach an .ics file)"]
+async fn import_events(ctx: &Context, msg: &Message, _args: Args) -> CommandResult {
+    let inv = Invocation::Prefix(msg);
+    match msg.attachments.first() {
+        Some(attachment) => run_import_events(ctx, &inv, attachment).await,
+        None => {
+            let _msg = inv
+                .say(
+                    ctx,
+                    ":bangbang: Error :bangbang: - Attach an `.ics` file to the message to import its events.",
+                )
+                .await;
+            Ok(())
+        }
+    }
+}
+
+pub async fn run_import_events(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    attachment: &Attachment,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+    if !migration::events_writable(ctx, inv).await {
+        return Ok(());
+    }
+
+    if !attachment.filename.to_lowercase().ends_with(".ics") {
+        let _msg = inv
+            .say(
+                ctx,
+                format!(
+                    ":bangbang: Error :bangbang: - `{}` isn't an iCalendar file. Attach a file ending in `.ics`.",
+                    attachment.filename
+                ),
+            )
+            .await;
+        return Ok(());
+    }
+    if attachment.size > MAX_FILE_SIZE {
+        let _msg = inv
+            .say(
+                ctx,
+                format!(
+                    ":bangbang: Error :bangbang: - `{}` is too big to import; the limit is 1 MB.",
+                    attachment.filename
+                ),
+            )
+            .await;
+        return Ok(());
+    }
+
+    let guild_id = inv.guild_id().unwrap();
+    let store = store::store(ctx).await;
+    let tz = store.timezone_for(guild_id.into(), inv.user().id.into());
+    let contents = attachment.download().await?;
+    let import = ics::parse_events(&String::from_utf8_lossy(&contents), i64::from(guild_id), tz);
+
+    let notes = notes(&import.notes);
+    if import.events.is_empty() {
+        let _msg = inv
+            .say(
+                ctx,
+                format!(
+                    "No upcoming events were found in `{}`.{}",
+                    attachment.filename, notes
+                ),
+            )
+            .await;
+        return Ok(());
+    }
+
+    let guild_tz = store.guild_timezone(guild_id.into());
+    let question = format!(
+        "Create these {} events from `{}`?{}",
+        import.events.len(),
+        attachment.filename,
+        notes
+    );
+    let mut msg = inv
+        .reply(
+            ctx,
+            Reply::embed(preview(&import.events, guild_tz))
+                .content(&question)
+                .components(confirm_buttons()),
+        )
+        .await?;
+
+    let interaction = msg
+        .await_component_interaction(ctx)
+        .author_id(inv.user().id)
+        .timeout(CONFIRM_TIMEOUT)
+        .await;
+    let confirmed = interaction
+        .as_ref()
+        .is_some_and(|i| i.data.custom_id == "import_confirm");
+    let answer = match (&interaction, confirmed) {
+        (_, true) => "Creating the events...",
+        (Some(_), false) => "Cancelled, nothing was imported.",
+        (None, false) => "No answer, so nothing was imported.",
+    };
+
+    // The buttons are removed so the import can't be started twice.
+    let content = format!("{}\n{}", question, answer);
+    match interaction {
+        Some(interaction) => {
+            interaction
+                .create_interaction_response(&ctx.http, |r| {
+                    r.kind(InteractionResponseType::UpdateMessage)
+                        .interaction_response_data(|d| {
+                            d.content(content)
+                                .set_components(CreateComponents::default())
+                        })
+                })
+                .await?
+        }
+        None => {
+            let _ = msg
+                .edit(&ctx.http, |m| {
+                    m.content(content)
+                        .set_components(CreateComponents::default())
+                })
+                .await;
+        }
+    }
+    if !confirmed {
+        return Ok(());
+    }
+
+    // One at a time, so a failure part way through still leaves a clear count of what
+    // was created.
+    let client = api::client(ctx).await;
+    let mut created = 0;
+    let mut failed = Vec::new();
+    for new in &import.events {
+        println!("Sending imported Event creation request with {:?}", new);
+        match client.create_event(new).await {
+            Ok(event) => {
+                scheduled::mirror(ctx, &event).await;
+                created += 1;
+            }
+            Err(why) => {
+                why.log("import_events");
+                failed.push(format!("`{}`", new.title));
+            }
+        }
+    }
+
+    let summary = if failed.is_empty() {
+        format!(":white_check_mark: Imported {} events.", created)
+    } else {
+        format!(
+            ":warning: Imported {} of {} events. These could not be created: {}",
+            created,
+            import.events.len(),
+            failed.join(", ")
+        )
+    };
+    let _msg = inv.say(ctx, summary).await;
+
+    Ok(())
+}
+
+fn preview(events: &[NewEvent], tz: Tz) -> CreateEmbed {
+    let mut fields: Vec<(String, String, bool)> = events
+        .iter()
+        .take(MAX_PREVIEWED_EVENTS)
+        .map(|event| {
+            let mut value = format!(
+                "**Start:** {}\n**End:** {}",
+                timezone::discord_timestamp(event.start_date),
+                timezone::discord_timestamp(event.end_date)
+            );
+            if let Some(rule) = &event.recurrence {
+                if let Ok(recurrence) = Recurrence::parse(rule, tz) {
+                    value.push_str(&format!("\n**Repeats:** {}", recurrence.describe()));
+                }
+            }
+            if !event.url.is_empty() {
+                value.push_str(&format!("\n{}", event.url));
+            }
+            (event.title.clone(), value, false)
+        })
+        .collect();
+    if events.len() > MAX_PREVIEWED_EVENTS {
+        fields.push((
+            format!("...and {} more", events.len() - MAX_PREVIEWED_EVENTS),
+            "They'll be created too.".to_string(),
+            false,
+        ));
+    }
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Events to Import")
+        .fields(fields)
+        .timestamp(Timestamp::now());
+    embed
+}
+
+fn confirm_buttons() -> CreateComponents {
+    let action_row = CreateActionRow::default()
+        .create_button(|b| {
+            b.style(ButtonStyle::Success)
+                .custom_id("import_confirm")
+                .label("Create events")
+        })
+        .create_button(|b| {
+            b.style(ButtonStyle::Secondary)
+                .custom_id("import_cancel")
+                .label("Cancel")
+        })
+        .clone();
+    let mut components = CreateComponents::default();
+    components.add_action_row(action_row);
+    components
+}
+
+// The parser's notes about skipped events, as lines to append to a reply.
+fn notes(notes: &[String]) -> String {
+    let mut lines: String = notes
+        .iter()
+        .take(MAX_SHOWN_NOTES)
+        .map(|note| format!("\n- {}", note))
+        .collect();
+    if notes.len() > MAX_SHOWN_NOTES {
+        lines.push_str(&format!(
+            "\n- ...and {} more skipped.",
+            notes.len() - MAX_SHOWN_NOTES
+        ));
+    }
+    lines
+}
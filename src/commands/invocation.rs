@@ -0,0 +1,144 @@
+use serenity::builder::{CreateComponents, CreateEmbed};
+use serenity::model::application::interaction::application_command::ApplicationCommandInteraction;
+use serenity::model::application::interaction::modal::ModalSubmitInteraction;
+use serenity::model::channel::{AttachmentType, Message};
+use serenity::model::id::GuildId;
+use serenity::model::user::User;
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+
+// Where a command came from. Prefix commands reply in the channel of the triggering
+// message, slash commands and submitted forms reply through the interaction's followup
+// messages. The command logic itself only ever talks to this type, so every entry point
+// shares it.
+pub enum Invocation<'a> {
+    Prefix(&'a Message),
+    Slash(&'a ApplicationCommandInteraction),
+    Modal(&'a ModalSubmitInteraction),
+}
+
+impl<'a> Invocation<'a> {
+    pub fn guild_id(&self) -> Option<GuildId> {
+        match self {
+            Invocation::Prefix(msg) => msg.guild_id,
+            Invocation::Slash(command) => command.guild_id,
+            Invocation::Modal(modal) => modal.guild_id,
+        }
+    }
+
+    pub fn user(&self) -> &User {
+        match self {
+            Invocation::Prefix(msg) => &msg.author,
+            Invocation::Slash(command) => &command.user,
+            Invocation::Modal(modal) => &modal.user,
+        }
+    }
+
+    pub async fn say(&self, ctx: &Context, content: impl ToString) -> serenity::Result<Message> {
+        self.reply(ctx, Reply::text(content)).await
+    }
+
+    pub async fn reply(&self, ctx: &Context, reply: Reply) -> serenity::Result<Message> {
+        match self {
+            Invocation::Prefix(msg) => {
+                msg.channel_id
+                    .send_message(&ctx.http, |m| {
+                        if let Some(content) = reply.content {
+                            m.content(content);
+                        }
+                        if let Some(embed) = reply.embed {
+                            m.set_embed(embed);
+                        }
+                        if let Some(components) = reply.components {
+                            m.set_components(components);
+                        }
+                        m.add_files(reply.files)
+                    })
+                    .await
+            }
+            Invocation::Slash(command) => {
+                command
+                    .create_followup_message(&ctx.http, |m| {
+                        if let Some(content) = reply.content {
+                            m.content(content);
+                        }
+                        if let Some(embed) = reply.embed {
+                            m.add_embed(embed);
+                        }
+                        if let Some(components) = reply.components {
+                            m.set_components(components);
+                        }
+                        m.add_files(reply.files)
+                    })
+                    .await
+            }
+            Invocation::Modal(modal) => {
+                modal
+                    .create_followup_message(&ctx.http, |m| {
+                        if let Some(content) = reply.content {
+                            m.content(content);
+                        }
+                        if let Some(embed) = reply.embed {
+                            m.add_embed(embed);
+                        }
+                        if let Some(components) = reply.components {
+                            m.set_components(components);
+                        }
+                        m.add_files(reply.files)
+                    })
+                    .await
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Reply {
+    pub content: Option<String>,
+    pub embed: Option<CreateEmbed>,
+    pub files: Vec<AttachmentType<'static>>,
+    pub components: Option<CreateComponents>,
+}
+
+impl Reply {
+    pub fn text(content: impl ToString) -> Self {
+        Reply {
+            content: Some(content.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn embed(embed: CreateEmbed) -> Self {
+        Reply {
+            embed: Some(embed),
+            ..Default::default()
+        }
+    }
+
+    // The cork board banner embed used by the pins, events and FAQs commands.
+    pub fn corkboard(title: &str, fields: Vec<(String, String, bool)>) -> Self {
+        let mut embed = CreateEmbed::default();
+        embed
+            .title(title)
+            .image("attachment://cork-board.png")
+            .fields(fields)
+            .timestamp(Timestamp::now());
+
+        Reply::embed(embed).file("./resources/cork-board.png")
+    }
+
+    pub fn content(mut self, content: impl ToString) -> Self {
+        self.content = Some(content.to_string());
+        self
+    }
+
+    pub fn file(mut self, file: impl Into<AttachmentType<'static>>) -> Self {
+        self.files.push(file.into());
+        self
+    }
+
+    pub fn components(mut self, components: CreateComponents) -> Self {
+        self.components = Some(components);
+        self
+    }
+}
@@ -1,31 +1,34 @@
 extern crate serde;
 extern crate serde_json;
 
-use std::collections::HashMap;
 use std::error::Error;
 
-use serde_json::Value;
 use serenity::framework::standard::macros::command;
 use serenity::framework::standard::CommandResult;
 use serenity::model::channel::Message;
 use serenity::model::Timestamp;
 use serenity::prelude::*;
 
-use crate::commands::events::Event;
-use crate::commands::faqs::Faq;
-use crate::commands::pins::Pin;
+use crate::api;
+use crate::api::client::CorkboardClient;
+use crate::store;
+use crate::timezone;
+
+use super::pins;
 
 #[command]
 #[description = "Retrieves all events, pins, and faqs."]
 async fn list(ctx: &Context, msg: &Message) -> CommandResult {
     println!("Got list command..");
 
+    let client = api::client(ctx).await;
+    let guild_id = i64::from(msg.guild_id.unwrap());
     let mut all_fields = Vec::new();
-    all_fields.push(_get_events(msg).await?);
+    all_fields.push(_get_events(&client, guild_id).await?);
     println!("Got events..");
-    all_fields.push(_get_pins(msg).await?);
+    all_fields.push(_get_pins(ctx, &client, guild_id).await?);
     println!("Got pins..");
-    all_fields.push(_get_faqs(msg).await?);
+    all_fields.push(_get_faqs(&client, guild_id).await?);
     println!("Got faqs..");
 
     let _msg = msg
@@ -45,20 +48,15 @@ async fn list(ctx: &Context, msg: &Message) -> CommandResult {
     Ok(())
 }
 
-async fn _get_pins(msg: &Message) -> Result<(String, String, bool), Box<dyn Error + Send + Sync>> {
-    let resp = reqwest::get(format!(
-        "http://localhost:8000/api/v1/pin/guild/{}",
-        msg.guild_id.unwrap()
-    ))
-    .await?
-    .json::<Vec<HashMap<String, Value>>>()
-    .await?;
-    let mut pins: Vec<Pin> = Vec::new();
-    for pin_map in resp {
-        pins.push(Pin::to_pin(pin_map));
-    }
+async fn _get_pins(
+    ctx: &Context,
+    client: &CorkboardClient,
+    guild_id: i64,
+) -> Result<(String, String, bool), Box<dyn Error + Send + Sync>> {
+    let pins = pins::ordered_pins(ctx, client, guild_id).await?;
+    let featured = store::store(ctx).await.guild(guild_id as u64).featured_pins;
 
-    if pins.len() == 0 {
+    if pins.is_empty() {
         return Ok((
             "Pins: ".to_string(),
             "No current pins found!".to_string(),
@@ -68,30 +66,30 @@ async fn _get_pins(msg: &Message) -> Result<(String, String, bool), Box<dyn Erro
 
     let mut pin_descriptions = String::new();
     for pin in pins {
-        pin_descriptions
-            .push_str(format!("[{}]({}): {}\n", pin.title, pin.url, pin.description).as_str())
+        let star = if featured.contains(&pin.id) {
+            ":star: "
+        } else {
+            ""
+        };
+        pin_descriptions.push_str(
+            format!(
+                "{}[{}]({}): {}\n",
+                star, pin.title, pin.url, pin.description
+            )
+            .as_str(),
+        )
     }
 
     Ok(("Pins:".to_string(), pin_descriptions, false))
 }
 
 async fn _get_events(
-    msg: &Message,
+    client: &CorkboardClient,
+    guild_id: i64,
 ) -> Result<(String, String, bool), Box<dyn Error + Send + Sync>> {
-    let resp = reqwest::get(format!(
-        "http://localhost:8000/api/v1/event/current/guild/{}",
-        msg.guild_id.unwrap()
-    ))
-    .await?
-    .json::<Vec<HashMap<String, Value>>>()
-    .await?;
-
-    let mut events: Vec<Event> = Vec::new();
-    for event_map in resp {
-        events.push(Event::to_event(event_map));
-    }
+    let events = client.list_current_events(guild_id).await?;
 
-    if events.len() == 0 {
+    if events.is_empty() {
         return Ok((
             "Events: ".to_string(),
             "No current events found!".to_string(),
@@ -107,34 +105,23 @@ async fn _get_events(
                 event.title,
                 event.url,
                 event.description,
-                event.start_date.format("%m/%d/%Y %-I:%M%p").to_string(),
-                event.end_date.format("%m/%d/%Y %-I:%M%p").to_string()
+                timezone::discord_timestamp(event.start_date),
+                timezone::discord_timestamp(event.end_date)
             )
             .as_str(),
         );
     }
 
-    Ok((
-        "Events (using PST/PDT):".to_string(),
-        event_descriptions,
-        false,
-    ))
+    Ok(("Events:".to_string(), event_descriptions, false))
 }
 
-async fn _get_faqs(msg: &Message) -> Result<(String, String, bool), Box<dyn Error + Send + Sync>> {
-    let resp = reqwest::get(format!(
-        "http://localhost:8000/api/v1/faq/guild/{}",
-        msg.guild_id.unwrap()
-    ))
-    .await?
-    .json::<Vec<HashMap<String, Value>>>()
-    .await?;
-    let mut faqs: Vec<Faq> = Vec::new();
-    for faq_map in resp {
-        faqs.push(Faq::to_faq(faq_map));
-    }
+async fn _get_faqs(
+    client: &CorkboardClient,
+    guild_id: i64,
+) -> Result<(String, String, bool), Box<dyn Error + Send + Sync>> {
+    let faqs = client.list_faqs(guild_id).await?;
 
-    if faqs.len() == 0 {
+    if faqs.is_empty() {
         return Ok((
             "FAQs: ".to_string(),
             "No current FAQs found!".to_string(),
@@ -1,8 +1,7 @@
-use std::collections::HashMap;
 use std::io::Cursor;
 use std::time::Duration;
 
-use serenity::builder::{CreateActionRow, CreateComponents};
+use serenity::builder::{CreateActionRow, CreateComponents, CreateEmbed};
 use serenity::framework::standard::macros::command;
 use serenity::framework::standard::CommandResult;
 use serenity::model::application::component::ButtonStyle;
@@ -21,12 +20,13 @@ use imageproc::drawing::draw_text_mut;
 use rand::Rng;
 use rusttype::{point, Font, PositionedGlyph, Scale};
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use uuid::Uuid;
 
+use super::invocation::{Invocation, Reply};
 use super::luckymon;
+use crate::api;
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct LuckymonHistory {
     pub id: Uuid,
     pub user_id: i64,
@@ -37,78 +37,31 @@ pub struct LuckymonHistory {
     pub traded: bool,
 }
 
-impl LuckymonHistory {
-    pub fn new(
-        id: &str,
-        user_id: i64,
-        date_obtained: &str,
-        pokemon_id: i64,
-        shiny: bool,
-        pokemon_name: String,
-        traded: bool,
-    ) -> Self {
-        let id = Uuid::parse_str(id).expect("Bad UUID");
-
-        let fmt = "%Y-%m-%d";
-        let date_obtained = NaiveDate::parse_from_str(date_obtained, fmt)
-            .expect("Unable to parse date_obtained NaiveDate for LuckymonHistory.");
-
-        LuckymonHistory {
-            id,
-            user_id,
-            date_obtained,
-            pokemon_id,
-            shiny,
-            pokemon_name,
-            traded,
-        }
-    }
-
-    pub fn to_hist(hist_map: HashMap<String, Value>) -> Self {
-        LuckymonHistory::new(
-            hist_map.get("id").unwrap().as_str().unwrap(),
-            hist_map.get("user_id").unwrap().as_i64().unwrap(),
-            hist_map.get("date_obtained").unwrap().as_str().unwrap(),
-            hist_map.get("pokemon_id").unwrap().as_i64().unwrap(),
-            hist_map.get("shiny").unwrap().as_bool().unwrap(),
-            hist_map
-                .get("pokemon_name")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .to_string(),
-            hist_map.get("traded").unwrap().as_bool().unwrap(),
-        )
-    }
-}
-
 #[command]
 #[description = "Retrieves Luckymon History for a User."]
 async fn luckydex(ctx: &Context, msg: &Message) -> CommandResult {
+    run_luckydex(ctx, &Invocation::Prefix(msg)).await
+}
+
+pub async fn run_luckydex(ctx: &Context, inv: &Invocation<'_>) -> CommandResult {
     println!("Got luckydex command..");
-    let resp = reqwest::get(format!(
-        "http://localhost:8000/api/v1/luckymon-history/user-id/{}",
-        i64::from(msg.author.id)
-    ))
-    .await?
-    .json::<Vec<HashMap<String, Value>>>()
-    .await?;
-    let mut hists: Vec<LuckymonHistory> = Vec::new();
-    for hist_map in resp {
-        let hist = LuckymonHistory::to_hist(hist_map);
-        if !hist.traded {
-            hists.push(hist);
-        }
-    }
+    let owner = inv.user();
+    let client = api::client(ctx).await;
+    let hists: Vec<LuckymonHistory> = client
+        .get_luckymon_history(i64::from(owner.id))
+        .await?
+        .into_iter()
+        .filter(|hist| !hist.traded)
+        .collect();
 
     let items_per_page = 25;
     let total_pages = (hists.len() as f64 / items_per_page as f64).ceil() as usize;
     let mut current_page = 0;
 
-    let mut message = create_embed_page(ctx, msg, &hists, items_per_page, current_page).await?;
+    let mut message = create_embed_page(ctx, inv, &hists, items_per_page, current_page).await?;
 
     while let Some(interaction) = message
-        .await_component_interaction(&ctx)
+        .await_component_interaction(ctx)
         .timeout(Duration::from_secs(120))
         .await
     {
@@ -119,7 +72,7 @@ async fn luckydex(ctx: &Context, msg: &Message) -> CommandResult {
             })
             .await?;
 
-        if interaction.user.id != msg.author.id {
+        if interaction.user.id != owner.id {
             interaction
                 .edit_original_interaction_response(&ctx.http, |r| {
                     r.set_embed(message.embeds[0].clone().into())
@@ -142,7 +95,7 @@ async fn luckydex(ctx: &Context, msg: &Message) -> CommandResult {
             &hists,
             items_per_page,
             current_page,
-            &msg,
+            owner,
         )
         .await?;
 
@@ -160,7 +113,7 @@ async fn luckydex(ctx: &Context, msg: &Message) -> CommandResult {
 #[allow(deprecated)]
 async fn create_embed_page(
     ctx: &Context,
-    msg: &Message,
+    inv: &Invocation<'_>,
     data: &[LuckymonHistory],
     items_per_page: usize,
     current_page: usize,
@@ -209,32 +162,30 @@ async fn create_embed_page(
             .expect("Error encoding image");
     }
 
-    let image_url = send_dummy_message(&ctx, &buffer).await;
+    let image_url = send_dummy_message(ctx, &buffer).await;
+
+    let owner = inv.user();
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Luckydex")
+        .image("attachment://image.png")
+        .color(Colour::from_rgb(0, 255, 255))
+        .footer(|f| {
+            f.text(format!(
+                "{}: Page {} of {}",
+                &owner.name,
+                current_page + 1,
+                total_pages
+            ));
+            if let Some(avatar_url) = &owner.avatar_url() {
+                f.icon_url(avatar_url);
+            }
+            f
+        });
+    embed.image(image_url);
+    embed.timestamp(Timestamp::now());
 
-    msg.channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Luckydex")
-                    .image("attachment://image.png")
-                    .color(Colour::from_rgb(0, 255, 255))
-                    .footer(|f| {
-                        f.text(format!(
-                            "{}: Page {} of {}",
-                            &msg.author.name,
-                            current_page + 1,
-                            total_pages
-                        ));
-                        if let Some(avatar_url) = &msg.author.avatar_url() {
-                            f.icon_url(avatar_url);
-                        }
-                        f
-                    });
-                e.image(image_url);
-                e.timestamp(Timestamp::now());
-                e
-            });
-            m.set_components(components.clone())
-        })
+    inv.reply(ctx, Reply::embed(embed).components(components))
         .await
 }
 
@@ -245,7 +196,7 @@ async fn update_embed_page(
     data: &[LuckymonHistory],
     items_per_page: usize,
     current_page: usize,
-    original_owner: &Message,
+    owner: &User,
 ) -> serenity::Result<Message> {
     let start_index = current_page * items_per_page;
     let end_index = usize::min(start_index + items_per_page, data.len());
@@ -258,8 +209,8 @@ async fn update_embed_page(
         total_pages = 1;
     }
 
-    let author_name = &original_owner.author.name.clone();
-    let avatar_url = &original_owner.author.avatar_url();
+    let author_name = &owner.name;
+    let avatar_url = &owner.avatar_url();
 
     let action_row = CreateActionRow::default()
         .create_button(|b| {
@@ -294,7 +245,7 @@ async fn update_embed_page(
             .expect("Error encoding image");
     }
 
-    let image_url = send_dummy_message(&ctx, &buffer).await;
+    let image_url = send_dummy_message(ctx, &buffer).await;
 
     msg.channel_id
         .edit_message(&ctx.http, msg.id, |m| {
@@ -400,7 +351,6 @@ fn create_page_image(data: &[LuckymonHistory]) -> ImageBuffer<Rgba<u8>, Vec<u8>>
                 .to_rgba8();
 
                 pokemon_name = format!("✧˖° Shiny {} °˖✧", pokemon_name);
-            } else {
             }
 
             let x: i64 = (((col * (sprite_dimensions + sprite_spacing)) + sprite_dimensions)
@@ -430,21 +380,21 @@ fn create_page_image(data: &[LuckymonHistory]) -> ImageBuffer<Rgba<u8>, Vec<u8>>
                     &font,
                     text.as_str(),
                 );
-                text_spacing = text_spacing + 15;
+                text_spacing += 15;
             }
         }
     }
 
-    return img;
+    img
 }
 
-fn get_font<'a>() -> Font<'a> {
+pub fn get_font<'a>() -> Font<'a> {
     let font_data: &[u8] = include_bytes!("../../resources/fonts/DejaVuSans.ttf");
-    return Font::try_from_bytes(font_data).unwrap();
+    Font::try_from_bytes(font_data).unwrap()
 }
 
 // Ultimately used to center the text that is written over the generated page image
-fn text_width(font: &Font, scale: Scale, text: &str) -> f32 {
+pub fn text_width(font: &Font, scale: Scale, text: &str) -> f32 {
     let v_metrics = font.v_metrics(scale);
     let glyphs: Vec<PositionedGlyph<'_>> = font
         .layout(text, scale, point(0.0, v_metrics.ascent))
@@ -452,7 +402,7 @@ fn text_width(font: &Font, scale: Scale, text: &str) -> f32 {
     let width = glyphs
         .iter()
         .rev()
-        .map(|g| g.position().x as f32 + g.unpositioned().h_metrics().advance_width)
+        .map(|g| g.position().x + g.unpositioned().h_metrics().advance_width)
         .next()
         .unwrap_or(0.0);
     width
@@ -1,5 +1,4 @@
 use std::collections::hash_map::DefaultHasher;
-use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
 use std::hash::{Hash, Hasher};
@@ -11,7 +10,7 @@ use rustemon::client::RustemonClient;
 use rustemon::model::pokemon::Pokemon;
 use rustemon::pokemon::pokemon;
 use serde::Serialize;
-use serde_json::Value;
+use serenity::builder::CreateEmbed;
 use serenity::framework::standard::macros::command;
 use serenity::framework::standard::CommandResult;
 use serenity::futures::StreamExt;
@@ -19,6 +18,10 @@ use serenity::model::channel::Message;
 use serenity::model::Timestamp;
 use serenity::prelude::*;
 
+use crate::api;
+
+use super::invocation::{Invocation, Reply};
+
 extern crate reqwest;
 extern crate tokio;
 
@@ -65,7 +68,7 @@ fn capitalize(name: &str) -> String {
     let (n1, n2) = name.split_at(1);
     let n_upper = n1.to_uppercase();
     let new_name = n_upper + n2;
-    return new_name.to_string();
+    new_name.to_string()
 }
 
 fn capitalize_hyphenated(name: &str, separator: &str) -> String {
@@ -73,25 +76,25 @@ fn capitalize_hyphenated(name: &str, separator: &str) -> String {
     let (n1, eh) = name.split_at(hyphen_index);
     let (_uh, n2) = eh.split_at(1);
     let new_name = capitalize(n1) + separator + &capitalize(n2);
-    return new_name.to_string();
+    new_name.to_string()
 }
 
 fn has_hyphen(name: &str) -> bool {
-    return name.contains("-");
+    name.contains("-")
 }
 
 fn is_nidoran(name: &str) -> bool {
-    return name.contains("idoran");
+    name.contains("idoran")
 }
 
 fn is_paradox(name: &str) -> bool {
-    return name.starts_with("iron-")
+    name.starts_with("iron-")
         || name.starts_with("scream-")
         || name.starts_with("slither-")
         || name.starts_with("brute-")
         || name.starts_with("great-")
         || name.starts_with("flutter-")
-        || name.starts_with("sandy-");
+        || name.starts_with("sandy-")
 }
 
 pub fn format_for_display(name: &str) -> String {
@@ -132,7 +135,7 @@ pub fn format_for_display(name: &str) -> String {
     }
 
     // fall through when no hyphen and default just to capitalizing the string once at the start;
-    return capitalize(name).to_string();
+    capitalize(name).to_string()
 }
 
 fn format_for_bulba(name: &str) -> String {
@@ -167,14 +170,18 @@ fn format_for_bulba(name: &str) -> String {
             return capitalize_hyphenated(name, "_");
         }
     }
-    return capitalize(name).to_string();
+    capitalize(name).to_string()
 }
 
 #[command]
 #[description = "Lucky pokemon of the day!"]
 async fn luckymon(ctx: &Context, msg: &Message) -> CommandResult {
+    run_luckymon(ctx, &Invocation::Prefix(msg)).await
+}
+
+pub async fn run_luckymon(ctx: &Context, inv: &Invocation<'_>) -> CommandResult {
     println!("Got luckymon command..");
-    let user_id = msg.author.id;
+    let user_id = inv.user().id;
     let today = Timestamp::now().date_naive();
 
     let one_in_x_shiny_chance = 400; // 1/400 chance to get a shiny
@@ -204,21 +211,12 @@ async fn luckymon(ctx: &Context, msg: &Message) -> CommandResult {
 
     let regular_name = lucky_pokemon.species.name;
     let display_name = format_for_display(&regular_name);
-    let mut final_name = String::from(display_name.clone());
+    let mut final_name = display_name.clone();
     let link_name = format_for_bulba(&regular_name);
     let regular_sprite = lucky_pokemon.sprites.front_default.unwrap();
 
     let mut sprite = regular_sprite;
 
-    let new = NewLuckymonHistory::new(
-        i64::from(user_id),
-        today,
-        daily_pair.0,
-        daily_pair.1,
-        &display_name,
-        false,
-    );
-
     if daily_pair.1 {
         if let Some(shiny_sprite) = lucky_pokemon.sprites.front_shiny {
             final_name = format!("✨ Shiny {} ✨", final_name);
@@ -230,38 +228,45 @@ async fn luckymon(ctx: &Context, msg: &Message) -> CommandResult {
         }
     }
 
+    let new = NewLuckymonHistory::new(
+        i64::from(user_id),
+        today,
+        daily_pair.0,
+        daily_pair.1,
+        &display_name,
+        false,
+    );
+
     println!(
         "Sending new LuckymonHistory creation request with {:?}",
         new
     );
-    let client = reqwest::Client::new();
-    let _resp = client
-        .post("http://localhost:8000/api/v1/luckymon-history")
-        .json(&new)
-        .send()
-        .await?
-        .json::<HashMap<String, Value>>()
-        .await?;
-
-    let author_name = &msg.author.name.clone();
-    let avatar_url = &msg.author.avatar_url();
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Your lucky Pokémon of the day is:")
-                    .image(sprite)
-                    .fields(vec!((format!("{}", &final_name), format!("[Bulbapedia Page](https://bulbapedia.bulbagarden.net/wiki/{}_(Pok%C3%A9mon))", link_name).to_string(), false)))
-                    .footer(|f| {
-                        f.text(format!("{} - Resets 4PM PDT (12AM UTC)", author_name));
-                        if let Some(avatar_url) = avatar_url {
-                            f.icon_url(avatar_url);
-                        } 
-                        f
-                    })
-            })
-        })
-        .await;
+    let client = api::client(ctx).await;
+    let _hist = client.create_luckymon_history(&new, false).await?;
+
+    let author_name = &inv.user().name;
+    let avatar_url = &inv.user().avatar_url();
+    let mut embed = CreateEmbed::default();
+    embed
+        .title("Your lucky Pokémon of the day is:")
+        .image(sprite)
+        .fields(vec![(
+            final_name.to_string(),
+            format!(
+                "[Bulbapedia Page](https://bulbapedia.bulbagarden.net/wiki/{}_(Pok%C3%A9mon))",
+                link_name
+            )
+            .to_string(),
+            false,
+        )])
+        .footer(|f| {
+            f.text(format!("{} - Resets 4PM PDT (12AM UTC)", author_name));
+            if let Some(avatar_url) = avatar_url {
+                f.icon_url(avatar_url);
+            }
+            f
+        });
+    let _msg = inv.reply(ctx, Reply::embed(embed)).await;
 
     println!("Finished processing luckymon command!");
     Ok(())
@@ -1,11 +1,7 @@
-use chrono::NaiveDate;
 use image::codecs::png::PngEncoder;
 use image::{imageops, ImageBuffer, ImageEncoder, Rgba};
 use rand::Rng;
-use reqwest::header::CONTENT_TYPE;
-use serde::{Deserialize, Serialize};
-use serde_json::Value;
-use serenity::builder::{CreateActionRow, CreateButton, CreateEmbed};
+use serenity::builder::{CreateActionRow, CreateButton, CreateComponents, CreateEmbed};
 use serenity::framework::standard::macros::command;
 use serenity::framework::standard::{Args, CommandError, CommandResult};
 use serenity::model::channel::Message;
@@ -14,102 +10,18 @@ use serenity::model::prelude::component::ButtonStyle;
 use serenity::model::prelude::{AttachmentType, InteractionResponseType};
 use serenity::prelude::*;
 use serenity::utils::parse_username;
-use std::collections::HashMap;
 use std::io::Cursor;
 use std::time::Duration;
 use uuid::Uuid;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct LuckymonHistory {
-    pub id: Uuid,
-    pub user_id: i64,
-    pub date_obtained: NaiveDate,
-    pub pokemon_id: i64,
-    pub shiny: bool,
-    pub pokemon_name: String,
-    pub traded: bool,
-}
-
-impl LuckymonHistory {
-    pub fn new(
-        id: &str,
-        user_id: i64,
-        date_obtained: &str,
-        pokemon_id: i64,
-        shiny: bool,
-        pokemon_name: String,
-        traded: bool,
-    ) -> Self {
-        let id = Uuid::parse_str(id).expect("Bad UUID");
-
-        let fmt = "%Y-%m-%d";
-        let date_obtained = NaiveDate::parse_from_str(date_obtained, fmt)
-            .expect("Unable to parse date_obtained NaiveDate for LuckymonHistory.");
-
-        LuckymonHistory {
-            id,
-            user_id,
-            date_obtained,
-            pokemon_id,
-            shiny,
-            pokemon_name,
-            traded,
-        }
-    }
-
-    pub fn to_hist(hist_map: HashMap<String, Value>) -> Self {
-        LuckymonHistory::new(
-            hist_map.get("id").unwrap().as_str().unwrap(),
-            hist_map.get("user_id").unwrap().as_i64().unwrap(),
-            hist_map.get("date_obtained").unwrap().as_str().unwrap(),
-            hist_map.get("pokemon_id").unwrap().as_i64().unwrap(),
-            hist_map.get("shiny").unwrap().as_bool().unwrap(),
-            hist_map
-                .get("pokemon_name")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .to_string(),
-            hist_map.get("traded").unwrap().as_bool().unwrap(),
-        )
-    }
-}
-
-#[derive(Serialize, Debug)]
-pub struct NewLuckymonHistory {
-    pub user_id: i64,
-    pub date_obtained: NaiveDate,
-    pub pokemon_id: i64,
-    pub shiny: bool,
-    pub pokemon_name: String,
-    pub traded: bool,
-}
-
-impl NewLuckymonHistory {
-    pub fn new(
-        user_id: i64,
-        date_obtained: NaiveDate,
-        pokemon_id: i64,
-        shiny: bool,
-        pokemon_name: &String,
-        traded: bool,
-    ) -> Self {
-        NewLuckymonHistory {
-            user_id,
-            date_obtained,
-            pokemon_id,
-            shiny,
-            pokemon_name: pokemon_name.to_string(),
-            traded,
-        }
-    }
-}
+use super::invocation::{Invocation, Reply};
+use super::luckydex::LuckymonHistory;
+use super::luckymon::NewLuckymonHistory;
+use crate::api;
 
 #[command]
 #[description = "Trade Your Luckymon With Other Users."]
 async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    println!("Got luckytrade command..");
-
     let caller = &msg.author;
 
     // Error handling: check if we have the correct number of arguments
@@ -144,44 +56,62 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
         }
     };
 
+    let caller_luckymon = args.single::<String>()?;
+    let callee_luckymon = args.single::<String>()?;
+    run_luckytrade(
+        ctx,
+        &Invocation::Prefix(msg),
+        callee_id,
+        caller_luckymon,
+        callee_luckymon,
+    )
+    .await
+}
+
+pub async fn run_luckytrade(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    callee_id: UserId,
+    caller_luckymon: String,
+    callee_luckymon: String,
+) -> CommandResult {
+    println!("Got luckytrade command..");
+
+    let caller = inv.user();
+
     if caller.id == callee_id {
-        msg.channel_id
-            .say(
-                &ctx.http,
-                format!("{} Error: You can't trade yourself, silly!", caller),
-            )
-            .await?;
+        inv.say(
+            ctx,
+            format!("{} Error: You can't trade yourself, silly!", caller),
+        )
+        .await?;
         return Err(CommandError::from("Tried trading themselves."));
     }
 
-    // Extract and validate the next 2 luckymon trade arguments
-    let caller_luckymon = args.single::<String>()?;
+    // Validate the 2 luckymon trade arguments
     if !validate_trade_arg(&caller_luckymon) {
-        msg.channel_id
-            .say(
-                &ctx.http,
-                format!(
-                    "{} Error: Invalid format for the first trade argument.",
-                    caller
-                ),
-            )
-            .await?;
+        inv.say(
+            ctx,
+            format!(
+                "{} Error: Invalid format for the first trade argument.",
+                caller
+            ),
+        )
+        .await?;
         return Err(CommandError::from(
             "Invalid format for the first trade argument.",
         ));
     }
 
-    let callee_luckymon = args.single::<String>()?;
     if !validate_trade_arg(&callee_luckymon) {
-        msg.channel_id
-            .say(
-                &ctx.http,
-                format!(
-                    "{} Error: Invalid format for the second trade argument.",
-                    caller
-                ),
-            )
-            .await?;
+        inv.say(
+            ctx,
+            format!(
+                "{} Error: Invalid format for the second trade argument.",
+                caller
+            ),
+        )
+        .await?;
         return Err(CommandError::from(
             "Invalid format for the second trade argument.",
         ));
@@ -190,45 +120,29 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
     let caller_na = caller_luckymon.eq_ignore_ascii_case("n/a");
     let callee_na = callee_luckymon.eq_ignore_ascii_case("n/a");
     if caller_na && callee_na {
-        msg.channel_id
-            .say(
-                &ctx.http,
-                format!("{} Error: Both luckymon can't be 'N/A'.", caller),
-            )
-            .await?;
+        inv.say(
+            ctx,
+            format!("{} Error: Both luckymon can't be 'N/A'.", caller),
+        )
+        .await?;
         return Err(CommandError::from("Invalid arguments provided."));
     }
 
     // Check to see if each user has the requested pokemon
-    let resp = reqwest::get(format!(
-        "http://localhost:8000/api/v1/luckymon-history/user-id/{}",
-        i64::from(msg.author.id)
-    ))
-    .await?
-    .json::<Vec<HashMap<String, Value>>>()
-    .await?;
-    let mut caller_hists: Vec<LuckymonHistory> = Vec::new();
-    for hist_map in resp {
-        let hist = LuckymonHistory::to_hist(hist_map);
-        if !hist.traded {
-            caller_hists.push(hist);
-        }
-    }
-
-    let resp = reqwest::get(format!(
-        "http://localhost:8000/api/v1/luckymon-history/user-id/{}",
-        i64::from(callee_id)
-    ))
-    .await?
-    .json::<Vec<HashMap<String, Value>>>()
-    .await?;
-    let mut callee_hists: Vec<LuckymonHistory> = Vec::new();
-    for hist_map in resp {
-        let hist = LuckymonHistory::to_hist(hist_map);
-        if !hist.traded {
-            callee_hists.push(hist);
-        }
-    }
+    let client = api::client(ctx).await;
+    let caller_hists: Vec<LuckymonHistory> = client
+        .get_luckymon_history(i64::from(caller.id))
+        .await?
+        .into_iter()
+        .filter(|hist| !hist.traded)
+        .collect();
+
+    let callee_hists: Vec<LuckymonHistory> = client
+        .get_luckymon_history(i64::from(callee_id))
+        .await?
+        .into_iter()
+        .filter(|hist| !hist.traded)
+        .collect();
 
     let caller_shiny = caller_luckymon.ends_with("s");
     let callee_shiny = callee_luckymon.ends_with("s");
@@ -245,7 +159,7 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
         if caller_shiny {
             let digits: String = caller_luckymon
                 .chars()
-                .take_while(|c| c.is_digit(10))
+                .take_while(|c| c.is_ascii_digit())
                 .collect();
             caller_luckymon_id = digits.parse::<i64>().unwrap();
         } else {
@@ -256,20 +170,19 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
             .into_iter()
             .find(|h| h.pokemon_id == caller_luckymon_id);
 
-        if caller_luckymon_hist.is_none() {
-            msg.channel_id
-                .say(
-                    &ctx.http,
-                    format!(
-                        "{} Error: You don't have a luckymon with ID {}!",
-                        caller, caller_luckymon
-                    ),
-                )
-                .await?;
-            return Err(CommandError::from("Caller doesn't have this luckymon."));
+        if let Some(hist) = caller_luckymon_hist {
+            caller_hist_id = hist.id;
+            hist_data.push(Some(hist));
         } else {
-            caller_hist_id = caller_luckymon_hist.as_ref().unwrap().id;
-            hist_data.push(caller_luckymon_hist);
+            inv.say(
+                ctx,
+                format!(
+                    "{} Error: You don't have a luckymon with ID {}!",
+                    caller, caller_luckymon
+                ),
+            )
+            .await?;
+            return Err(CommandError::from("Caller doesn't have this luckymon."));
         }
     }
 
@@ -280,7 +193,7 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
         if callee_shiny {
             let digits: String = callee_luckymon
                 .chars()
-                .take_while(|c| c.is_digit(10))
+                .take_while(|c| c.is_ascii_digit())
                 .collect();
             callee_luckymon_id = digits.parse::<i64>().unwrap();
         } else {
@@ -291,22 +204,21 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
             .into_iter()
             .find(|h| h.pokemon_id == callee_luckymon_id);
 
-        if callee_luckymon_hist.is_none() {
-            msg.channel_id
-                .say(
-                    &ctx.http,
-                    format!(
-                        "{} Error: {} doesn't have a luckymon with ID {}!",
-                        caller,
-                        callee_id.mention(),
-                        callee_luckymon
-                    ),
-                )
-                .await?;
-            return Err(CommandError::from("Callee doesn't have this luckymon."));
+        if let Some(hist) = callee_luckymon_hist {
+            callee_hist_id = hist.id;
+            hist_data.push(Some(hist));
         } else {
-            callee_hist_id = callee_luckymon_hist.as_ref().unwrap().id;
-            hist_data.push(callee_luckymon_hist.clone());
+            inv.say(
+                ctx,
+                format!(
+                    "{} Error: {} doesn't have a luckymon with ID {}!",
+                    caller,
+                    callee_id.mention(),
+                    callee_luckymon
+                ),
+            )
+            .await?;
+            return Err(CommandError::from("Callee doesn't have this luckymon."));
         }
     }
 
@@ -325,10 +237,10 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
             .expect("Error encoding image");
     }
 
-    let files = vec![AttachmentType::Bytes {
+    let file = AttachmentType::Bytes {
         data: buffer.into(),
         filename: "image.png".to_string(),
-    }];
+    };
 
     // Build the embedded message with images
     let embed = (*CreateEmbed::default()
@@ -375,22 +287,17 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
     .clone();
 
     // Send the embedded message with buttons
-    let msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.files(files)
-                .embed(|e| {
-                    *e = embed;
-                    e.image(format!("attachment://image.png"));
-                    e
-                })
-                .components(|c| c.add_action_row(action_row))
-        })
+    let mut embed = embed;
+    embed.image("attachment://image.png".to_string());
+    let mut components = CreateComponents::default();
+    components.add_action_row(action_row);
+    let msg = inv
+        .reply(ctx, Reply::embed(embed).file(file).components(components))
         .await
         .unwrap();
 
     while let Some(interaction) = msg
-        .await_component_interaction(&ctx)
+        .await_component_interaction(ctx)
         .timeout(Duration::from_secs(1200))
         .await
     {
@@ -410,30 +317,16 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
             // when the trade request was first created. This is to prevent duping!
             let mut caller_hist = None;
             if !caller_na {
-                let resp = reqwest::get(format!(
-                    "http://localhost:8000/api/v1/luckymon-history/{}",
-                    caller_hist_id
-                ))
-                .await?
-                .json::<HashMap<String, Value>>()
-                .await?;
-                caller_hist = Some(LuckymonHistory::to_hist(resp));
+                caller_hist = Some(client.get_luckymon_history_entry(caller_hist_id).await?);
             }
 
             let mut callee_hist = None;
             if !callee_na {
-                let resp = reqwest::get(format!(
-                    "http://localhost:8000/api/v1/luckymon-history/{}",
-                    callee_hist_id
-                ))
-                .await?
-                .json::<HashMap<String, Value>>()
-                .await?;
-                callee_hist = Some(LuckymonHistory::to_hist(resp));
+                callee_hist = Some(client.get_luckymon_history_entry(callee_hist_id).await?);
             }
 
-            if (!caller_hist.is_none() && !caller_na && caller_hist.as_ref().unwrap().traded)
-                || (!callee_hist.is_none() && callee_na && callee_hist.as_ref().unwrap().traded)
+            if (caller_hist.is_some() && !caller_na && caller_hist.as_ref().unwrap().traded)
+                || (callee_hist.is_some() && callee_na && callee_hist.as_ref().unwrap().traded)
             {
                 interaction
                     .edit_original_interaction_response(&ctx.http, |r| {
@@ -442,7 +335,7 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
                                 .description(
                                     "Luckymon data is outdated! Please create a new trade request.",
                                 )
-                                .image(format!("attachment://image.png"))
+                                .image("attachment://image.png".to_string())
                         })
                     })
                     .await?;
@@ -466,26 +359,10 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
                     "Sending new LuckymonHistory creation request via trade with {:?}",
                     new_caller_luckymon
                 );
-                let client = reqwest::Client::new();
-                let _resp = client
-                    .post("http://localhost:8000/api/v1/luckymon-history?trade=true")
-                    .json(&new_caller_luckymon)
-                    .send()
-                    .await?
-                    .json::<HashMap<String, Value>>()
-                    .await?;
-
-                let client = reqwest::Client::new();
-                let _resp = client
-                    .put(format!(
-                        "http://localhost:8000/api/v1/luckymon-history/traded/{}",
-                        callee_hist_id
-                    ))
-                    .header(CONTENT_TYPE, "application/json")
-                    .send()
-                    .await?
-                    .json::<HashMap<String, Value>>()
+                client
+                    .create_luckymon_history(&new_caller_luckymon, true)
                     .await?;
+                client.mark_traded(callee_hist_id).await?;
             }
 
             if !caller_na {
@@ -504,26 +381,10 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
                     "Sending new LuckymonHistory creation request via trade with {:?}",
                     new_callee_luckymon
                 );
-                let client = reqwest::Client::new();
-                let _resp = client
-                    .post("http://localhost:8000/api/v1/luckymon-history?trade=true")
-                    .json(&new_callee_luckymon)
-                    .send()
-                    .await?
-                    .json::<HashMap<String, Value>>()
-                    .await?;
-
-                let client = reqwest::Client::new();
-                let _resp = client
-                    .put(format!(
-                        "http://localhost:8000/api/v1/luckymon-history/traded/{}",
-                        caller_hist_id
-                    ))
-                    .header(CONTENT_TYPE, "application/json")
-                    .send()
-                    .await?
-                    .json::<HashMap<String, Value>>()
+                client
+                    .create_luckymon_history(&new_callee_luckymon, true)
                     .await?;
+                client.mark_traded(caller_hist_id).await?;
             }
 
             interaction
@@ -535,7 +396,7 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
                                 interaction.user.id.mention(),
                                 caller.mention()
                             ))
-                            .image(format!("attachment://image.png"))
+                            .image("attachment://image.png".to_string())
                     })
                 })
                 .await?;
@@ -552,7 +413,7 @@ async fn luckytrade(ctx: &Context, msg: &Message, mut args: Args) -> CommandResu
                                 "{} has cancelled the trade request. 😢",
                                 interaction.user.id.mention()
                             ))
-                            .image(format!("attachment://image.png"))
+                            .image("attachment://image.png".to_string())
                     })
                 })
                 .await?;
@@ -595,7 +456,7 @@ fn create_page_image(data: Vec<Option<LuckymonHistory>>) -> ImageBuffer<Rgba<u8>
 
     imageops::overlay(&mut img, &background, 0, 0);
 
-    let pokemon_data_1_maybe: &Option<LuckymonHistory> = data.get(0).unwrap();
+    let pokemon_data_1_maybe: &Option<LuckymonHistory> = data.first().unwrap();
     let pokemon_data_2_maybe: &Option<LuckymonHistory> = data.get(1).unwrap();
 
     let mut pokemon_sprite_1;
@@ -650,19 +511,16 @@ fn create_page_image(data: Vec<Option<LuckymonHistory>>) -> ImageBuffer<Rgba<u8>
         .unwrap()
         .to_rgba8();
 
-    let mut x: i64 = (((0 * (sprite_dimensions + sprite_spacing)) + sprite_dimensions)
-        - x_spacing_buffer)
-        .into();
+    let mut x: i64 = (sprite_dimensions - x_spacing_buffer).into();
     let y: i64 = y_spacing_buffer.into();
     imageops::overlay(&mut img, &pokemon_sprite_1, x, y);
 
-    x = (((1 * (sprite_dimensions + sprite_spacing)) + sprite_dimensions) - x_spacing_buffer)
-        .into();
+    x = (((sprite_dimensions + sprite_spacing) + sprite_dimensions) - x_spacing_buffer).into();
     imageops::overlay(&mut img, &trade_sprite, x, y);
 
     x = (((2 * (sprite_dimensions + sprite_spacing)) + sprite_dimensions) - x_spacing_buffer)
         .into();
     imageops::overlay(&mut img, &pokemon_sprite_2, x, y);
 
-    return img;
+    img
 }
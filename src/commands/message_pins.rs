@@ -0,0 +1,233 @@
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::{Message, Reaction, ReactionType};
+use serenity::model::id::GuildId;
+use serenity::model::user::User;
+use serenity::prelude::*;
+
+use crate::api;
+use crate::error::BotError;
+use crate::store;
+use crate::validation::validation;
+
+use super::invocation::Invocation;
+use super::pins::{self, NewPin, Pin};
+
+// Name of the message context-menu command, as shown in Discord's Apps menu.
+pub static CONTEXT_MENU_NAME: &str = "Pin to Corkboard";
+static MAX_TITLE_LEN: usize = 100;
+static MAX_EXCERPT_LEN: usize = 300;
+
+#[command]
+#[allowed_roles("corkboard")]
+#[description = "Shows or sets the emoji that pins a message to the corkboard when someone with the `corkboard` role reacts with it. Use `off` to stop pinning by reaction."]
+#[usage = "[emoji|off]"]
+async fn pin_reaction(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    run_pin_reaction(ctx, &Invocation::Prefix(msg), args.current()).await
+}
+
+pub async fn run_pin_reaction(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    emoji: Option<&str>,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let store = store::store(ctx).await;
+    let guild_id = u64::from(inv.guild_id().unwrap());
+    let reply = match emoji.map(str::trim) {
+        None => match store.guild(guild_id).pin_reaction {
+            Some(emoji) => format!(
+                ":pushpin: Reacting with {} pins a message to the corkboard.",
+                emoji
+            ),
+            None => ":pushpin: Pinning by reaction is off. Turn it on with `.pin_reaction 📌`."
+                .to_string(),
+        },
+        Some(emoji) if emoji.eq_ignore_ascii_case("off") => {
+            store.update_guild(guild_id, |settings| settings.pin_reaction = None);
+            ":pushpin: Pinning by reaction is off.".to_string()
+        }
+        Some(emoji) => match parse_emoji(emoji) {
+            Some(emoji) => {
+                let emoji = emoji.to_string();
+                store.update_guild(guild_id, |settings| {
+                    settings.pin_reaction = Some(emoji.clone())
+                });
+                format!(
+                    ":pushpin: Reacting with {} now pins a message to the corkboard.",
+                    emoji
+                )
+            }
+            None => format!(
+                ":bangbang: Error :bangbang: - `{}` isn't an emoji. Use a standard emoji like 📌 or one of this server's own.",
+                emoji
+            ),
+        },
+    };
+    let _msg = inv.say(ctx, reply).await;
+
+    Ok(())
+}
+
+// The "Pin to Corkboard" entry in a message's Apps menu.
+pub async fn run_pin_message(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    message: &Message,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let reply = match pin_message(ctx, inv.guild_id().unwrap(), message).await? {
+        Some((pin, true)) => confirmation(&pin, inv.user()),
+        Some((pin, false)) => format!(
+            "This message is already pinned as `{}`.",
+            validation::short_id(&pin.id)
+        ),
+        None => "This message is being pinned already.".to_string(),
+    };
+    let _msg = inv.say(ctx, reply).await;
+
+    Ok(())
+}
+
+// Pins the message when someone with the `corkboard` role reacts with the guild's pin
+// emoji. Anyone else's reaction is left alone, and so is reacting to a message that's
+// already pinned, so a pile of reactions only makes one pin.
+pub async fn on_reaction(ctx: &Context, reaction: &Reaction) {
+    let guild_id = match reaction.guild_id {
+        Some(guild_id) => guild_id,
+        None => return,
+    };
+    let configured = match store::store(ctx).await.guild(guild_id.into()).pin_reaction {
+        Some(emoji) => emoji,
+        None => return,
+    };
+    if !is_emoji(&configured, &reaction.emoji) {
+        return;
+    }
+
+    let user = match reaction.user(&ctx).await {
+        Ok(user) if !user.bot => user,
+        _ => return,
+    };
+    if validation::user_has_corkboard_role(ctx, guild_id, &user).await != Some(true) {
+        return;
+    }
+
+    println!("Got pin reaction from {}..", user.name);
+    let message = match reaction.message(&ctx.http).await {
+        Ok(message) => message,
+        Err(why) => {
+            println!("Unable to fetch reacted message: {:?}", why);
+            return;
+        }
+    };
+    match pin_message(ctx, guild_id, &message).await {
+        Ok(Some((pin, true))) => {
+            if let Err(why) = message.reply(&ctx.http, confirmation(&pin, &user)).await {
+                println!("Unable to confirm pin: {:?}", why);
+            }
+        }
+        Ok(_) => {}
+        Err(why) => why.log("pin_reaction"),
+    }
+}
+
+// Creates a pin pointing at the message, or returns the existing one if the message is
+// already pinned. The flag says whether the pin is new. Returns None if the message is
+// being pinned by another reaction or command at the same time.
+async fn pin_message(
+    ctx: &Context,
+    guild_id: GuildId,
+    message: &Message,
+) -> Result<Option<(Pin, bool)>, BotError> {
+    let store = store::store(ctx).await;
+    let _claim = match store.claim_pinning(message.id.0) {
+        Some(claim) => claim,
+        None => return Ok(None),
+    };
+
+    let client = api::client(ctx).await;
+    let guild = i64::from(guild_id);
+    // Messages fetched over HTTP don't carry their guild, so the link is built from ours.
+    let url = message.id.link(message.channel_id, Some(guild_id));
+    if let Some(pin) = client
+        .list_pins(guild)
+        .await?
+        .into_iter()
+        .find(|pin| pin.url == url)
+    {
+        return Ok(Some((pin, false)));
+    }
+
+    let (title, description) = summarize(message);
+    let new = NewPin::new(guild, title, url, description, None, Vec::new());
+    println!("Sending new Pin creation request with {:?}", new);
+    let pin = client.create_pin(&new).await?;
+    Ok(Some((pin, true)))
+}
+
+// The title is the message's first line and the description an excerpt of the rest.
+// Messages without text fall back to their embed or attachment, then to the author.
+fn summarize(message: &Message) -> (String, String) {
+    let mut lines = message
+        .content
+        .lines()
+        .map(|line| line.trim_start_matches(['#', '>', ' ']).trim())
+        .filter(|line| !line.is_empty());
+    let title = lines
+        .next()
+        .map(str::to_string)
+        .or_else(|| message.embeds.iter().find_map(|embed| embed.title.clone()))
+        .or_else(|| message.attachments.first().map(|a| a.filename.clone()))
+        .unwrap_or_else(|| format!("Message from {}", message.author.name));
+
+    let rest: Vec<&str> = lines.collect();
+    let excerpt = if rest.is_empty() {
+        format!(
+            "Posted by {} in <#{}>",
+            message.author.name, message.channel_id
+        )
+    } else {
+        rest.join(" ")
+    };
+
+    (
+        pins::truncate(&title, MAX_TITLE_LEN),
+        pins::truncate(&excerpt, MAX_EXCERPT_LEN),
+    )
+}
+
+fn confirmation(pin: &Pin, user: &User) -> String {
+    format!(
+        ":pushpin: {} pinned this to the corkboard as `{}`: **{}**",
+        user.name,
+        validation::short_id(&pin.id),
+        pin.title
+    )
+}
+
+fn parse_emoji(input: &str) -> Option<ReactionType> {
+    // Anything that isn't a custom emoji parses as unicode, so plain words are ruled out here.
+    if input.is_empty() || input.is_ascii() && !input.starts_with('<') {
+        return None;
+    }
+    ReactionType::try_from(input).ok()
+}
+
+// Custom emoji are compared by ID, since they can be renamed. Unicode emoji may or may
+// not come with the emoji variation selector.
+fn is_emoji(configured: &str, emoji: &ReactionType) -> bool {
+    match (ReactionType::try_from(configured), emoji) {
+        (Ok(ReactionType::Custom { id: a, .. }), ReactionType::Custom { id: b, .. }) => a == *b,
+        (Ok(ReactionType::Unicode(a)), ReactionType::Unicode(b)) => {
+            a.trim_end_matches('\u{fe0f}') == b.trim_end_matches('\u{fe0f}')
+        }
+        _ => false,
+    }
+}
@@ -1,7 +1,19 @@
+pub mod autocomplete;
+pub mod calendar;
 pub mod events;
 pub mod faqs;
+pub mod forms;
+pub mod import;
+pub mod invocation;
 pub mod list;
 pub mod luckydex;
 pub mod luckymon;
 pub mod luckytrade;
+pub mod message_pins;
 pub mod pins;
+pub mod refresh;
+pub mod reminders;
+pub mod rsvp;
+pub mod scheduled;
+pub mod slash;
+pub mod timezone;
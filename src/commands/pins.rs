@@ -3,52 +3,85 @@ extern crate serde_json;
 
 use std::collections::HashMap;
 
-use reqwest::header::CONTENT_TYPE;
+use chrono::{NaiveDateTime, Utc};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
-use serde_json::Value;
 use serenity::framework::standard::macros::command;
 use serenity::framework::standard::{Args, CommandResult};
 use serenity::model::channel::Message;
-use serenity::model::Timestamp;
 use serenity::prelude::*;
 use uuid::Uuid;
 
+use crate::api;
+use crate::api::client::CorkboardClient;
+use crate::dates;
+use crate::error::BotError;
+use crate::links;
+use crate::store::{self, GuildSettings};
+use crate::timezone;
 use crate::validation::validation;
 
-#[derive(Serialize, Deserialize, Debug)]
+use super::invocation::{Invocation, Reply};
+
+// Featured pins are listed first under this heading, and the rest by category. Pins
+// without a category are listed last.
+static FEATURED: &str = ":star: Featured";
+static UNCATEGORIZED: &str = "Uncategorized";
+// `.pins archive` lists the archived pins instead.
+pub static ARCHIVE: &str = "archive";
+// Discord caps an embed at 25 fields, 1024 characters per field value and 6000 in
+// total. One field and some room are kept back for the "...and N more" line.
+static MAX_PIN_FIELDS: usize = 24;
+static MAX_FIELD_LEN: usize = 1024;
+static MAX_EMBED_LEN: usize = 5500;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Pin {
     pub id: Uuid,
     pub guild_id: i64,
     pub title: String,
     pub url: String,
     pub description: String,
+    // Always sent, so clearing them in an edit reaches the server.
+    #[serde(default)]
+    pub category: Option<String>,
+    // Lowercase and without the leading `#`.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // When the archiver takes the pin out of `.pins`, in UTC.
+    #[serde(default)]
+    pub expires_at: Option<NaiveDateTime>,
+    // Archived pins stay on the server so they can be restored, and are only listed
+    // by `.pins archive`.
+    #[serde(default)]
+    pub archived_at: Option<NaiveDateTime>,
 }
 
 impl Pin {
-    pub fn new(id: &str, guild_id: i64, title: String, url: String, description: String) -> Self {
-        let id = Uuid::parse_str(id).expect("Bad UUID");
+    pub fn new(
+        id: Uuid,
+        guild_id: i64,
+        title: String,
+        url: String,
+        description: String,
+        category: Option<String>,
+        tags: Vec<String>,
+    ) -> Self {
         Pin {
             id,
             guild_id,
             title,
             url,
             description,
+            category,
+            tags,
+            expires_at: None,
+            archived_at: None,
         }
     }
 
-    pub fn to_pin(pin_map: HashMap<String, Value>) -> Self {
-        Pin::new(
-            pin_map.get("id").unwrap().as_str().unwrap(),
-            pin_map.get("guild_id").unwrap().as_i64().unwrap(),
-            pin_map.get("title").unwrap().as_str().unwrap().to_string(),
-            pin_map.get("url").unwrap().as_str().unwrap().to_string(),
-            pin_map
-                .get("description")
-                .unwrap()
-                .as_str()
-                .unwrap()
-                .to_string(),
-        )
+    pub fn is_archived(&self) -> bool {
+        self.archived_at.is_some()
     }
 }
 
@@ -58,117 +91,293 @@ pub struct NewPin {
     pub title: String,
     pub url: String,
     pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<NaiveDateTime>,
 }
 
 impl NewPin {
-    pub fn new(guild_id: i64, title: String, url: String, description: String) -> Self {
+    pub fn new(
+        guild_id: i64,
+        title: String,
+        url: String,
+        description: String,
+        category: Option<String>,
+        tags: Vec<String>,
+    ) -> Self {
         NewPin {
             guild_id,
             title,
             url,
             description,
+            category,
+            tags,
+            expires_at: None,
+        }
+    }
+}
+
+// What `.pins` lists: everything grouped by category, the archive, one category, or
+// one tag.
+pub enum PinFilter {
+    All,
+    Archive,
+    Category(String),
+    Tag(String),
+}
+
+impl PinFilter {
+    // `archive` is the archive, so a category with that name is given as
+    // `category:archive`. Any category can be given that way.
+    pub fn parse(input: &str) -> Self {
+        let input = input.trim();
+        if let Some(category) = strip_category_prefix(input) {
+            return match category.trim() {
+                "" => PinFilter::All,
+                category => PinFilter::Category(category.to_string()),
+            };
+        }
+        match input.strip_prefix('#') {
+            _ if input.is_empty() => PinFilter::All,
+            _ if input.eq_ignore_ascii_case(ARCHIVE) => PinFilter::Archive,
+            Some(tag) => PinFilter::Tag(tag.to_lowercase()),
+            None => PinFilter::Category(input.to_string()),
+        }
+    }
+
+    fn matches(&self, pin: &Pin) -> bool {
+        match self {
+            PinFilter::All => true,
+            PinFilter::Archive => pin.is_archived(),
+            PinFilter::Category(category) => pin
+                .category
+                .as_ref()
+                .is_some_and(|c| c.eq_ignore_ascii_case(category)),
+            PinFilter::Tag(tag) => pin.tags.contains(tag),
         }
     }
+
+    fn title(&self) -> String {
+        match self {
+            PinFilter::All => "Pins".to_string(),
+            PinFilter::Archive => "Archived Pins".to_string(),
+            PinFilter::Category(category) => format!("Pins in {}", category),
+            PinFilter::Tag(tag) => format!("Pins tagged #{}", tag),
+        }
+    }
+}
+
+fn strip_category_prefix(input: &str) -> Option<&str> {
+    let prefix = "category:";
+    match input.get(..prefix.len()) {
+        Some(start) if start.eq_ignore_ascii_case(prefix) => Some(&input[prefix.len()..]),
+        _ => None,
+    }
+}
+
+// How to filter `.pins` by `category`, which for a category named `archive` needs the
+// `category:` prefix.
+pub fn category_filter(category: &str) -> String {
+    if category.eq_ignore_ascii_case(ARCHIVE) {
+        format!("category:{}", category)
+    } else {
+        category.to_string()
+    }
 }
 
 #[command]
-#[description = "Retrieves all pins."]
-async fn pins(ctx: &Context, msg: &Message) -> CommandResult {
+#[description = "Retrieves all pins, grouped by category. Give a category or a `#tag` to only list those pins, or `archive` to list pins that have expired. A category named `archive` is given as `category:archive`."]
+#[usage = "[category|#tag|archive]"]
+async fn pins(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    run_pins(ctx, &Invocation::Prefix(msg), args.rest()).await
+}
+
+pub async fn run_pins(ctx: &Context, inv: &Invocation<'_>, filter: &str) -> CommandResult {
+    let filter = PinFilter::parse(filter);
+    if let PinFilter::Archive = filter {
+        return run_archived_pins(ctx, inv).await;
+    }
+
     println!("Got pins command..");
-    let resp = reqwest::get(format!(
-        "http://localhost:8000/api/v1/pin/guild/{}",
-        msg.guild_id.unwrap()
-    ))
-    .await?
-    .json::<Vec<HashMap<String, Value>>>()
-    .await?;
-    let mut pins: Vec<Pin> = Vec::new();
-    for pin_map in resp {
-        pins.push(Pin::to_pin(pin_map));
-    }
-
-    let mut pin_fields: Vec<(String, String, bool)> = Vec::new();
-    let mut i = 1;
-    if pins.len() == 0 {
-        pin_fields.push((
+    let guild_id = inv.guild_id().unwrap();
+    let client = api::client(ctx).await;
+    let pins = ordered_pins(ctx, &client, i64::from(guild_id)).await?;
+
+    // Numbered by place in the full list, which is what `.move_pin` takes.
+    let listed: Vec<(usize, &Pin)> = pins
+        .iter()
+        .enumerate()
+        .map(|(index, pin)| (index + 1, pin))
+        .filter(|(_, pin)| filter.matches(pin))
+        .collect();
+    let pin_fields = if pins.is_empty() {
+        vec![(
             "Pins: ".to_string(),
             "No current pins found!".to_string(),
             false,
-        ));
+        )]
+    } else if listed.is_empty() {
+        let mut known: Vec<String> = categories(&pins)
+            .iter()
+            .map(|category| category_filter(category))
+            .collect();
+        known.extend(tags(&pins).iter().map(|tag| format!("#{}", tag)));
+        let hint = if known.is_empty() {
+            "No pins have a category or tags yet.".to_string()
+        } else {
+            truncate(&format!("Try one of: {}", known.join(", ")), MAX_FIELD_LEN)
+        };
+        vec![("No matching pins found!".to_string(), hint, false)]
     } else {
-        for pin in pins {
-            pin_fields.push((
-                format!("{}.", i),
-                format!("[{}]({}): {}", pin.title, pin.url, pin.description),
-                false,
-            ));
-            i += 1;
-        }
-    }
+        let store = store::store(ctx).await;
+        let featured = store.guild(guild_id.into()).featured_pins;
+        let dead = links::dead_links(&store, &pins);
+        grouped_fields(&listed, &featured, &dead)
+    };
 
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Pins")
-                    .image("attachment://cork-board.png")
-                    .fields(pin_fields)
-                    .timestamp(Timestamp::now())
-            })
-            .add_file("./resources/cork-board.png")
-        })
+    let _msg = inv
+        .reply(ctx, Reply::corkboard(&filter.title(), pin_fields))
         .await;
 
     println!("Finished processing pins command!");
     Ok(())
 }
 
+// Most recently archived first.
+async fn run_archived_pins(ctx: &Context, inv: &Invocation<'_>) -> CommandResult {
+    println!("Got pins archive command..");
+    let guild_id = i64::from(inv.guild_id().unwrap());
+    let mut pins = api::client(ctx).await.list_archived_pins(guild_id).await?;
+    pins.sort_by_key(|pin| std::cmp::Reverse(pin.archived_at));
+
+    let pin_fields = if pins.is_empty() {
+        vec![(
+            "Archived Pins: ".to_string(),
+            "No archived pins found!".to_string(),
+            false,
+        )]
+    } else {
+        let lines = pins
+            .iter()
+            .map(|pin| {
+                format!(
+                    "`{}` {} *(archived {})*",
+                    validation::short_id(&pin.id),
+                    pin_line(pin, false, None),
+                    timezone::discord_relative(pin.archived_at.unwrap())
+                )
+            })
+            .collect();
+        fill_fields(
+            vec![("Archived".to_string(), lines)],
+            "Only the most recently archived pins are shown.",
+        )
+    };
+
+    let _msg = inv
+        .reply(ctx, Reply::corkboard("Archived Pins", pin_fields))
+        .await;
+
+    Ok(())
+}
+
 #[command]
 #[allowed_roles("corkboard")]
-#[description = "Add a Pin."]
-#[usage = "title url description"]
+#[description = "Add a Pin. Start with the URL instead of a title to have the title and description read from the page. To have the pin archived on a certain date, use `.expire_pin` afterwards."]
+#[usage = "title url description [category] [#tag ...] | url [category] [#tag ...]"]
 async fn add_pin(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let arg_names = vec!["Title", "URL", "Description"];
-    if !validation::has_corkboard_role(ctx, msg).await
-        || !validation::has_correct_arg_count(ctx, msg, 3, args.len(), arg_names, "add_pin").await
-    {
+    let first = args.single_quoted::<String>().unwrap_or_default();
+    if validation::is_web_url(&first) {
+        let (category, tags) = category_and_tags(args);
+        return run_add_pin(
+            ctx,
+            &Invocation::Prefix(msg),
+            String::new(),
+            first,
+            String::new(),
+            category.as_deref().unwrap_or(""),
+            tags.as_deref().unwrap_or(""),
+            "",
+        )
+        .await;
+    }
+    if args.len() < 3 {
+        let arg_names = vec!["Title", "URL", "Description"];
+        validation::has_correct_arg_count(ctx, msg, 3, args.len(), arg_names, "add_pin").await;
         return Ok(());
     }
 
-    let guild_id = i64::from(msg.guild_id.unwrap());
-    let title = args.single_quoted::<String>().unwrap();
+    let title = first;
     let url = args.single_quoted::<String>().unwrap();
     let description = args.single_quoted::<String>().unwrap();
-    let new = NewPin::new(guild_id, title, url, description);
+    let (category, tags) = category_and_tags(args);
+    run_add_pin(
+        ctx,
+        &Invocation::Prefix(msg),
+        title,
+        url,
+        description,
+        category.as_deref().unwrap_or(""),
+        tags.as_deref().unwrap_or(""),
+        "",
+    )
+    .await
+}
+
+// A title or description left empty is read from the page the URL points at. An empty
+// expiry means the pin is kept until it's deleted.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_add_pin(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    title: String,
+    url: String,
+    description: String,
+    category: &str,
+    tags: &str,
+    expires: &str,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+    if !validation::has_web_url(ctx, inv, &url).await {
+        return Ok(());
+    }
+    let expires_at = match parse_expiry(expires, user_timezone(ctx, inv).await) {
+        Ok(expires_at) => expires_at,
+        Err(error) => {
+            let _msg = inv.say(ctx, error).await;
+            return Ok(());
+        }
+    };
+    let (title, description) = match links::complete(ctx, inv, &url, title, description).await {
+        Some(completed) => completed,
+        None => return Ok(()),
+    };
+
+    let guild_id = i64::from(inv.guild_id().unwrap());
+    let mut new = NewPin::new(
+        guild_id,
+        title,
+        url,
+        description,
+        parse_category(category),
+        parse_tags(tags),
+    );
+    new.expires_at = expires_at;
 
     println!("Sending new Pin creation request with {:?}", new);
-    let client = reqwest::Client::new();
-    let resp = client
-        .post("http://localhost:8000/api/v1/pin")
-        .json(&new)
-        .send()
-        .await?
-        .json::<HashMap<String, Value>>()
-        .await?;
-
-    let title = resp.get("title").unwrap();
-    let url = resp.get("url").unwrap();
-    let description = resp.get("description").unwrap();
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Created New Pin")
-                    .image("attachment://cork-board.png")
-                    .field(
-                        "1. ",
-                        format!("[{}]({}): {}", title, url, description),
-                        false,
-                    )
-                    .timestamp(Timestamp::now())
-            })
-            .add_file("./resources/cork-board.png")
-        })
+    let client = api::client(ctx).await;
+    let pin = client.create_pin(&new).await?;
+
+    let _msg = inv
+        .reply(
+            ctx,
+            Reply::corkboard("Created New Pin", vec![pin_field(&pin)]),
+        )
         .await;
 
     Ok(())
@@ -177,81 +386,78 @@ async fn add_pin(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult
 #[command]
 #[allowed_roles("corkboard")]
 #[description = "Edit a Pin."]
-#[usage = "pin_id title url description"]
+#[usage = "pin_id title url description [category] [#tag ...]"]
 async fn edit_pin(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
-    let arg_names = vec!["Pin_id", "Title", "URL", "Description"];
-    if !validation::has_corkboard_role(ctx, msg).await
-        || !validation::has_correct_arg_count(ctx, msg, 4, args.len(), arg_names, "edit_pin").await
-    {
+    if args.len() < 4 {
+        let arg_names = vec!["Pin_id", "Title", "URL", "Description"];
+        validation::has_correct_arg_count(ctx, msg, 4, args.len(), arg_names, "edit_pin").await;
         return Ok(());
     }
 
-    let guild_id = i64::from(msg.guild_id.unwrap());
     let id = args.current().unwrap().to_string();
     args.advance();
     let title = args.single_quoted::<String>().unwrap();
     let url = args.single_quoted::<String>().unwrap();
     let description = args.single_quoted::<String>().unwrap();
+    let (category, tags) = category_and_tags(args);
+    run_edit_pin(
+        ctx,
+        &Invocation::Prefix(msg),
+        &id,
+        title,
+        url,
+        description,
+        category.as_deref(),
+        tags.as_deref(),
+    )
+    .await
+}
 
-    let id_int = match id.parse::<i32>() {
-        Ok(i) => i,
-        _error => {
-            let _msg = msg
-                .channel_id
-                .say(
-                    &ctx.http,
-                    ":bangbang: Error :bangbang: - Unable to parse ID.",
-                )
-                .await;
-            return Ok(());
-        }
-    };
+// A category or tags left out (`None`) keep the pin's current ones.
+#[allow(clippy::too_many_arguments)]
+pub async fn run_edit_pin(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    id: &str,
+    title: String,
+    url: String,
+    description: String,
+    category: Option<&str>,
+    tags: Option<&str>,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+    if !validation::has_web_url(ctx, inv, &url).await {
+        return Ok(());
+    }
 
-    let id_map = retrieve_pins_id_map(guild_id).await;
-    let real_id_maybe = id_map.get(&id_int).clone();
-    let real_id = match real_id_maybe {
-        Some(i) => i,
-        None => {
-            let _msg = msg
-                .channel_id.say(
-                    &ctx.http,
-                    ":bangbang: Error :bangbang: - Invalid ID! Run the `.pins` command to see a list of usable IDs."
-                )
-                .await;
-            return Ok(());
-        }
+    let guild_id = i64::from(inv.guild_id().unwrap());
+    let client = api::client(ctx).await;
+    let current = match retrieve_pin(ctx, inv, &client, guild_id, id).await? {
+        Some(pin) => pin,
+        None => return Ok(()),
     };
 
-    let new = Pin::new(real_id.as_str(), guild_id, title, url, description);
+    let mut new = Pin::new(
+        current.id,
+        guild_id,
+        title,
+        url,
+        description,
+        category.map_or(current.category, parse_category),
+        tags.map_or(current.tags, parse_tags),
+    );
+    new.expires_at = current.expires_at;
 
     println!("Sending Pin edit request with {:?}", new);
-    let client = reqwest::Client::new();
-    let resp = client
-        .put(format!("http://localhost:8000/api/v1/pin/{}", real_id).as_str())
-        .json(&new)
-        .send()
-        .await?
-        .json::<HashMap<String, Value>>()
-        .await?;
-
-    let title = resp.get("title").unwrap();
-    let url = resp.get("url").unwrap();
-    let description = resp.get("description").unwrap();
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Edited New Pin")
-                    .image("attachment://cork-board.png")
-                    .field(
-                        format!("{}. ", id),
-                        format!("[{}]({}): {}", title, url, description),
-                        false,
-                    )
-                    .timestamp(Timestamp::now())
-            })
-            .add_file("./resources/cork-board.png")
-        })
+    let pin = client.update_pin(&new).await?;
+
+    let _msg = inv
+        .reply(
+            ctx,
+            Reply::corkboard("Edited New Pin", vec![pin_field(&pin)]),
+        )
         .await;
 
     Ok(())
@@ -263,99 +469,775 @@ async fn edit_pin(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult
 #[usage = "pin_id"]
 async fn delete_pin(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
     let arg_names = vec!["Pin_id"];
-    if !validation::has_corkboard_role(ctx, msg).await
-        || !validation::has_correct_arg_count(ctx, msg, 1, args.len(), arg_names, "delete_pin")
-            .await
-    {
+    if !validation::has_correct_arg_count(ctx, msg, 1, args.len(), arg_names, "delete_pin").await {
         return Ok(());
     }
 
-    let guild_id = i64::from(msg.guild_id.unwrap());
     args.quoted();
     let id = args.current().unwrap().to_string();
-    let id_int = match id.parse::<i32>() {
-        Ok(i) => i,
-        _error => {
-            let _msg = msg
-                .channel_id
+    run_delete_pin(ctx, &Invocation::Prefix(msg), &id).await
+}
+
+pub async fn run_delete_pin(ctx: &Context, inv: &Invocation<'_>, id: &str) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let guild_id = i64::from(inv.guild_id().unwrap());
+    let client = api::client(ctx).await;
+    let real_id = match retrieve_pin(ctx, inv, &client, guild_id, id).await? {
+        Some(pin) => pin.id,
+        None => return Ok(()),
+    };
+
+    println!("Sending Pin delete request with ID {:?}", real_id);
+    let pin = client.delete_pin(real_id).await?;
+    // Deleted pins leave nothing behind in the arranged order or the featured pins.
+    store::store(ctx)
+        .await
+        .update_guild(guild_id as u64, |settings| {
+            settings.pin_order.retain(|id| *id != real_id);
+            settings.featured_pins.retain(|id| *id != real_id);
+        });
+
+    let _msg = inv
+        .reply(
+            ctx,
+            Reply::corkboard("Edited New Pin", vec![pin_field(&pin)]),
+        )
+        .await;
+
+    Ok(())
+}
+
+#[command]
+#[allowed_roles("corkboard")]
+#[description = "Move a Pin to another place in `.pins`, where 1 is the top. The pin can be given by its ID or by its current place. Featured pins are always listed first."]
+#[usage = "pin_id|place new_place"]
+async fn move_pin(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let arg_names = vec!["Pin_id", "New_place"];
+    if !validation::has_correct_arg_count(ctx, msg, 2, args.len(), arg_names, "move_pin").await {
+        return Ok(());
+    }
+
+    let inv = Invocation::Prefix(msg);
+    let handle = args.single::<String>().unwrap();
+    let place = args.single::<String>().unwrap();
+    match place.parse::<usize>() {
+        Ok(place) if place > 0 => run_move_pin(ctx, &inv, &handle, place).await,
+        _ => {
+            let _msg = inv
                 .say(
-                    &ctx.http,
-                    ":bangbang: Error :bangbang: - Unable to parse ID.",
+                    ctx,
+                    format!(
+                        ":bangbang: Error :bangbang: - `{}` isn't a place in the list. Use a number like 1 for the top.",
+                        place
+                    ),
                 )
                 .await;
-            return Ok(());
+            Ok(())
         }
+    }
+}
+
+pub async fn run_move_pin(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    handle: &str,
+    place: usize,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let guild_id = inv.guild_id().unwrap();
+    let client = api::client(ctx).await;
+    let mut pins = ordered_pins(ctx, &client, i64::from(guild_id)).await?;
+    let index = match find_pin_or_place(ctx, inv, &pins, handle).await {
+        Some(index) => index,
+        None => return Ok(()),
     };
 
-    let id_map = retrieve_pins_id_map(guild_id).await;
-    let real_id_maybe = id_map.get(&id_int).clone();
-    let real_id = match real_id_maybe {
-        Some(i) => i,
-        None => {
-            let _msg = msg
-                .channel_id.say(
-                    &ctx.http,
-                    ":bangbang: Error :bangbang: - Invalid ID! Run the `.pin` command to see a list of usable IDs."
-                )
-                .await;
+    let pin = pins.remove(index);
+    let (id, title) = (pin.id, pin.title.clone());
+    pins.insert((place - 1).min(pins.len()), pin);
+    let store = store::store(ctx).await;
+    store.update_guild(guild_id.into(), |settings| {
+        settings.pin_order = pins.iter().map(|pin| pin.id).collect()
+    });
+
+    // Featured pins stay ahead of the rest, so the pin may not land exactly where asked.
+    sort_pins(&mut pins, &store.guild(guild_id.into()));
+    let place = pins.iter().position(|pin| pin.id == id).unwrap() + 1;
+    let _msg = inv
+        .say(
+            ctx,
+            format!(
+                ":pushpin: Moved **{}** to place {} in `.pins`.",
+                title, place
+            ),
+        )
+        .await;
+
+    Ok(())
+}
+
+#[command]
+#[allowed_roles("corkboard")]
+#[description = "Feature a Pin, so it's listed first in `.pins` and highlighted. Add `off` to stop featuring it."]
+#[usage = "pin_id [off]"]
+async fn feature_pin(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    if args.is_empty() {
+        let arg_names = vec!["Pin_id"];
+        validation::has_correct_arg_count(ctx, msg, 1, 0, arg_names, "feature_pin").await;
+        return Ok(());
+    }
+
+    let handle = args.single::<String>().unwrap();
+    let off = args
+        .current()
+        .is_some_and(|arg| arg.eq_ignore_ascii_case("off"));
+    run_feature_pin(ctx, &Invocation::Prefix(msg), &handle, !off).await
+}
+
+pub async fn run_feature_pin(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    handle: &str,
+    featured: bool,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let guild_id = inv.guild_id().unwrap();
+    let client = api::client(ctx).await;
+    let pins = ordered_pins(ctx, &client, i64::from(guild_id)).await?;
+    let pin = match find_pin(ctx, inv, &pins, handle).await {
+        Some(index) => &pins[index],
+        None => return Ok(()),
+    };
+
+    // Pins that have since been deleted are dropped from the list along the way.
+    store::store(ctx)
+        .await
+        .update_guild(guild_id.into(), |settings| {
+            settings
+                .featured_pins
+                .retain(|id| *id != pin.id && pins.iter().any(|pin| pin.id == *id));
+            if featured {
+                settings.featured_pins.push(pin.id);
+            }
+        });
+
+    let reply = if featured {
+        format!(
+            ":star: **{}** is now featured at the top of `.pins`.",
+            pin.title
+        )
+    } else {
+        format!(":pushpin: **{}** is no longer featured.", pin.title)
+    };
+    let _msg = inv.say(ctx, reply).await;
+
+    Ok(())
+}
+
+#[command]
+#[allowed_roles("corkboard")]
+#[description = "Set the date a Pin expires. Expired pins are moved to the archive (see `.pins archive`) rather than deleted, and can be brought back with `.restore_pin`. The date is read in your time zone (see `.timezone`). Use `never` to keep the pin until it's deleted."]
+#[usage = "pin_id date|never"]
+async fn expire_pin(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let arg_names = vec!["Pin_id", "Date"];
+    if !validation::has_correct_arg_count(ctx, msg, 2, args.len(), arg_names, "expire_pin").await {
+        return Ok(());
+    }
+
+    let handle = args.single::<String>().unwrap();
+    run_expire_pin(ctx, &Invocation::Prefix(msg), &handle, args.rest()).await
+}
+
+pub async fn run_expire_pin(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    handle: &str,
+    expires: &str,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+    let expires_at = match parse_expiry(expires, user_timezone(ctx, inv).await) {
+        Ok(expires_at) => expires_at,
+        Err(error) => {
+            let _msg = inv.say(ctx, error).await;
             return Ok(());
         }
     };
 
-    println!("Sending Pin delete request with ID {:?}", real_id);
-    let client = reqwest::Client::new();
-    let resp = client
-        .delete(format!("http://localhost:8000/api/v1/pin/delete/{}", real_id).as_str())
-        .header(CONTENT_TYPE, "application/json")
-        .send()
-        .await?
-        .json::<HashMap<String, Value>>()
-        .await?;
-
-    let title = resp.get("title").unwrap();
-    let url = resp.get("url").unwrap();
-    let description = resp.get("description").unwrap();
-    let _msg = msg
-        .channel_id
-        .send_message(&ctx.http, |m| {
-            m.embed(|e| {
-                e.title("Edited New Pin")
-                    .image("attachment://cork-board.png")
-                    .field(
-                        "1. ",
-                        format!("[{}]({}): {}", title, url, description),
-                        false,
-                    )
-                    .timestamp(Timestamp::now())
-            })
-            .add_file("./resources/cork-board.png")
-        })
+    let guild_id = i64::from(inv.guild_id().unwrap());
+    let client = api::client(ctx).await;
+    let mut pins = ordered_pins(ctx, &client, guild_id).await?;
+    let mut pin = match find_pin(ctx, inv, &pins, handle).await {
+        Some(index) => pins.swap_remove(index),
+        None => return Ok(()),
+    };
+
+    pin.expires_at = expires_at;
+    println!("Sending Pin edit request with {:?}", pin);
+    let pin = client.update_pin(&pin).await?;
+
+    let reply = match pin.expires_at {
+        Some(expires_at) => format!(
+            ":hourglass: **{}** will be archived {}.",
+            pin.title,
+            timezone::discord_timestamp(expires_at)
+        ),
+        None => format!(":pushpin: **{}** no longer expires.", pin.title),
+    };
+    let _msg = inv.say(ctx, reply).await;
+
+    Ok(())
+}
+
+#[command]
+#[allowed_roles("corkboard")]
+#[description = "Bring an archived Pin back to `.pins`. Its expiry is cleared, so set a new one with `.expire_pin` if needed."]
+#[usage = "pin_id"]
+async fn restore_pin(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let arg_names = vec!["Pin_id"];
+    if !validation::has_correct_arg_count(ctx, msg, 1, args.len(), arg_names, "restore_pin").await {
+        return Ok(());
+    }
+
+    args.quoted();
+    let id = args.current().unwrap().to_string();
+    run_restore_pin(ctx, &Invocation::Prefix(msg), &id).await
+}
+
+pub async fn run_restore_pin(ctx: &Context, inv: &Invocation<'_>, id: &str) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let guild_id = i64::from(inv.guild_id().unwrap());
+    let client = api::client(ctx).await;
+    let archived = client.list_archived_pins(guild_id).await?;
+    let ids: Vec<Uuid> = archived.iter().map(|pin| pin.id).collect();
+    let mut pin = match validation::resolve_short_id(ctx, inv, id, &ids, "pins archive").await {
+        Some(id) => archived.into_iter().find(|pin| pin.id == id).unwrap(),
+        None => return Ok(()),
+    };
+
+    // Otherwise the archiver would take it straight back out.
+    pin.expires_at = None;
+    pin.archived_at = None;
+    println!("Sending Pin restore request with {:?}", pin);
+    let pin = client.update_pin(&pin).await?;
+
+    let _msg = inv
+        .reply(ctx, Reply::corkboard("Restored Pin", vec![pin_field(&pin)]))
         .await;
 
     Ok(())
 }
 
-async fn retrieve_pins_id_map(guild_id: i64) -> HashMap<i32, String> {
-    let resp = reqwest::get(format!(
-        "http://localhost:8000/api/v1/pin/guild/{}",
-        guild_id
-    ))
-    .await
-    .unwrap()
-    .json::<Vec<HashMap<String, Value>>>()
-    .await
-    .unwrap();
-    let mut pins: Vec<Pin> = Vec::new();
-    for pin_map in resp {
-        pins.push(Pin::to_pin(pin_map));
+// The guild's pins in the order they're listed: featured pins first, then the rest.
+// Each group follows the order arranged with `.move_pin`, and pins added since come
+// last, in the order the server returns them.
+pub async fn ordered_pins(
+    ctx: &Context,
+    client: &CorkboardClient,
+    guild_id: i64,
+) -> Result<Vec<Pin>, BotError> {
+    let mut pins = client.list_pins(guild_id).await?;
+    let settings = store::store(ctx).await.guild(guild_id as u64);
+    sort_pins(&mut pins, &settings);
+    Ok(pins)
+}
+
+fn sort_pins(pins: &mut [Pin], settings: &GuildSettings) {
+    pins.sort_by_key(|pin| {
+        (
+            !settings.featured_pins.contains(&pin.id),
+            settings
+                .pin_order
+                .iter()
+                .position(|id| *id == pin.id)
+                .unwrap_or(usize::MAX),
+        )
+    });
+}
+
+// Finds a pin by the short ID shown in `.pins`, returning its index in `pins`.
+async fn find_pin(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    pins: &[Pin],
+    handle: &str,
+) -> Option<usize> {
+    let ids: Vec<Uuid> = pins.iter().map(|pin| pin.id).collect();
+    let id = validation::resolve_short_id(ctx, inv, handle, &ids, "pins").await?;
+    pins.iter().position(|pin| pin.id == id)
+}
+
+// Like `find_pin`, but also takes the place a pin is numbered with in `.pins`. Short
+// IDs are never shorter than four characters, so a shorter number is a place.
+async fn find_pin_or_place(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    pins: &[Pin],
+    handle: &str,
+) -> Option<usize> {
+    match handle.parse::<usize>() {
+        Ok(place) if handle.len() < validation::MIN_SHORT_ID_LEN => {
+            if (1..=pins.len()).contains(&place) {
+                return Some(place - 1);
+            }
+            let _msg = inv
+                .say(
+                    ctx,
+                    format!(
+                        ":bangbang: Error :bangbang: - There's no pin at place {}; `.pins` lists {}.",
+                        place,
+                        pins.len()
+                    ),
+                )
+                .await;
+            None
+        }
+        _ => find_pin(ctx, inv, pins, handle).await,
+    }
+}
+
+fn pin_field(pin: &Pin) -> (String, String, bool) {
+    let mut value = pin_line(pin, false, None);
+    if let Some(category) = &pin.category {
+        value.push_str(&format!("\n**Category:** {}", category));
+    }
+    (format!("`{}`", validation::short_id(&pin.id)), value, false)
+}
+
+// Featured pins have their title in bold. `dead` is why the link checker thinks the
+// pin's link no longer works.
+fn pin_line(pin: &Pin, featured: bool, dead: Option<&String>) -> String {
+    let mut line = if featured {
+        format!("**[{}]({})**: {}", pin.title, pin.url, pin.description)
+    } else {
+        format!("[{}]({}): {}", pin.title, pin.url, pin.description)
+    };
+    if !pin.tags.is_empty() {
+        let tags: Vec<String> = pin.tags.iter().map(|tag| format!("`#{}`", tag)).collect();
+        line.push_str(&format!(" {}", tags.join(" ")));
+    }
+    if let (Some(expires_at), false) = (pin.expires_at, pin.is_archived()) {
+        line.push_str(&format!(
+            " :hourglass: *Expires {}*",
+            timezone::discord_relative(expires_at)
+        ));
+    }
+    if let Some(reason) = dead {
+        line.push_str(&format!(" :warning: *Link looks dead ({})*", reason));
+    }
+    line
+}
+
+// One field for the featured pins, then one per category, named categories first in
+// alphabetical order. Each pin is numbered by its place in the full list.
+fn grouped_fields(
+    pins: &[(usize, &Pin)],
+    featured: &[Uuid],
+    dead: &HashMap<Uuid, String>,
+) -> Vec<(String, String, bool)> {
+    let mut groups: Vec<(String, Vec<String>)> = Vec::new();
+    for (place, pin) in pins {
+        let is_featured = featured.contains(&pin.id);
+        let name = if is_featured {
+            FEATURED
+        } else {
+            pin.category.as_deref().unwrap_or(UNCATEGORIZED)
+        };
+        let line = format!(
+            "**{}.** `{}` {}",
+            place,
+            validation::short_id(&pin.id),
+            pin_line(pin, is_featured, dead.get(&pin.id))
+        );
+        match groups
+            .iter_mut()
+            .find(|(group, _)| group.eq_ignore_ascii_case(name))
+        {
+            Some((_, lines)) => lines.push(line),
+            None => groups.push((name.to_string(), vec![line])),
+        }
+    }
+    groups.sort_by_key(|(name, _)| (name != FEATURED, name == UNCATEGORIZED, name.to_lowercase()));
+
+    fill_fields(
+        groups,
+        "List fewer at a time with `.pins <category>` or `.pins #tag`.",
+    )
+}
+
+// Lays out groups of lines as embed fields, one or more per group. A group with more
+// lines than fit in one field carries on in the next, and whatever doesn't fit in the
+// embed at all is counted at the end, along with the `overflow` hint.
+fn fill_fields(groups: Vec<(String, Vec<String>)>, overflow: &str) -> Vec<(String, String, bool)> {
+    let mut fields: Vec<(String, String, bool)> = Vec::new();
+    let mut length = 0;
+    let mut hidden = 0;
+    for (name, lines) in groups {
+        let mut value = String::new();
+        let mut heading = name.clone();
+        for line in lines {
+            let line = truncate(&line, MAX_FIELD_LEN);
+            if fields.len() == MAX_PIN_FIELDS
+                || length + heading.len() + value.len() + line.len() > MAX_EMBED_LEN
+            {
+                hidden += 1;
+                continue;
+            }
+            if value.len() + line.len() + 1 > MAX_FIELD_LEN {
+                length += heading.len() + value.len();
+                fields.push((heading, value, false));
+                heading = format!("{} (cont.)", name);
+                value = String::new();
+                if fields.len() == MAX_PIN_FIELDS {
+                    hidden += 1;
+                    continue;
+                }
+            }
+            if !value.is_empty() {
+                value.push('\n');
+            }
+            value.push_str(&line);
+        }
+        if !value.is_empty() {
+            length += heading.len() + value.len();
+            fields.push((heading, value, false));
+        }
     }
 
-    let mut result: HashMap<i32, String> = HashMap::new();
-    let mut i = 1;
-    for pin in pins {
-        result.insert(i, pin.id.to_string());
-        i += 1;
+    if hidden > 0 {
+        fields.push((
+            format!("...and {} more", hidden),
+            overflow.to_string(),
+            false,
+        ));
     }
+    fields
+}
 
-    result
+// The distinct categories in use, in alphabetical order.
+pub fn categories(pins: &[Pin]) -> Vec<String> {
+    let mut categories: Vec<String> = Vec::new();
+    for category in pins.iter().filter_map(|pin| pin.category.as_ref()) {
+        if !categories.iter().any(|c| c.eq_ignore_ascii_case(category)) {
+            categories.push(category.clone());
+        }
+    }
+    categories.sort_by_key(|category| category.to_lowercase());
+    categories
+}
+
+// The distinct tags in use, in alphabetical order.
+pub fn tags(pins: &[Pin]) -> Vec<String> {
+    let mut tags: Vec<String> = pins.iter().flat_map(|pin| pin.tags.clone()).collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+// Empty or `none` means no category.
+fn parse_category(input: &str) -> Option<String> {
+    let category = input.trim();
+    if category.is_empty() || category.eq_ignore_ascii_case("none") {
+        None
+    } else {
+        Some(category.to_string())
+    }
+}
+
+// Tags may be separated by spaces or commas, with or without a leading `#`, and
+// `none` means no tags.
+fn parse_tags(input: &str) -> Vec<String> {
+    if input.trim().eq_ignore_ascii_case("none") {
+        return Vec::new();
+    }
+    let mut tags: Vec<String> = Vec::new();
+    for tag in input.split(|c: char| c.is_whitespace() || c == ',') {
+        let tag = tag.trim_start_matches('#').to_lowercase();
+        if !tag.is_empty() && !tags.contains(&tag) {
+            tags.push(tag);
+        }
+    }
+    tags
+}
+
+// Empty or `never` means the pin doesn't expire. Dates are read in `tz`, like event
+// dates, and have to be in the future.
+fn parse_expiry(input: &str, tz: Tz) -> Result<Option<NaiveDateTime>, String> {
+    let input = input.trim();
+    if input.is_empty() || input.eq_ignore_ascii_case("never") {
+        return Ok(None);
+    }
+    let now = Utc::now().naive_utc();
+    let expires_at = dates::parse_start(input, tz, now)?;
+    if expires_at <= now {
+        return Err(format!(
+            ":bangbang: Error :bangbang: - `{}` has already passed. Give a date in the future, or `never`.",
+            input
+        ));
+    }
+    Ok(Some(expires_at))
+}
+
+async fn user_timezone(ctx: &Context, inv: &Invocation<'_>) -> Tz {
+    store::store(ctx)
+        .await
+        .timezone_for(inv.guild_id().unwrap().into(), inv.user().id.into())
+}
+
+// The optional arguments after a prefix command's description: a category, unless it
+// starts with `#`, followed by any number of tags.
+fn category_and_tags(mut args: Args) -> (Option<String>, Option<String>) {
+    let mut category = None;
+    let mut tags: Vec<String> = Vec::new();
+    while let Ok(arg) = args.single_quoted::<String>() {
+        if category.is_none() && tags.is_empty() && !arg.starts_with('#') {
+            category = Some(arg);
+        } else {
+            tags.push(arg);
+        }
+    }
+    let tags = if tags.is_empty() {
+        None
+    } else {
+        Some(tags.join(" "))
+    };
+    (category, tags)
+}
+
+pub fn truncate(text: &str, max_len: usize) -> String {
+    if text.len() <= max_len {
+        return text.to_string();
+    }
+
+    let mut end = max_len - 3;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}...", &text[..end])
+}
+
+// Resolves the short ID shown by `.pins` to the Pin, replying with an error if it
+// doesn't match exactly one current pin.
+async fn retrieve_pin(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    client: &CorkboardClient,
+    guild_id: i64,
+    handle: &str,
+) -> Result<Option<Pin>, BotError> {
+    let pins = client.list_pins(guild_id).await?;
+    let ids: Vec<Uuid> = pins.iter().map(|pin| pin.id).collect();
+    let id = validation::resolve_short_id(ctx, inv, handle, &ids, "pins").await;
+    Ok(id.and_then(|id| pins.into_iter().find(|pin| pin.id == id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pin(n: u128, category: Option<&str>, tags: &[&str]) -> Pin {
+        Pin::new(
+            Uuid::from_u128(n),
+            1,
+            format!("Pin {}", n),
+            format!("https://example.com/{}", n),
+            String::new(),
+            category.map(str::to_string),
+            tags.iter().map(|tag| tag.to_string()).collect(),
+        )
+    }
+
+    fn numbered(pins: &[Pin]) -> Vec<(usize, &Pin)> {
+        pins.iter()
+            .enumerate()
+            .map(|(index, pin)| (index + 1, pin))
+            .collect()
+    }
+
+    #[test]
+    fn parses_filters() {
+        assert!(matches!(PinFilter::parse(""), PinFilter::All));
+        assert!(matches!(PinFilter::parse("  "), PinFilter::All));
+        assert!(matches!(PinFilter::parse("#Raids"), PinFilter::Tag(tag) if tag == "raids"));
+        assert!(matches!(
+            PinFilter::parse(" Raid Guides "),
+            PinFilter::Category(category) if category == "Raid Guides"
+        ));
+    }
+
+    #[test]
+    fn archive_is_the_archive_unless_given_as_a_category() {
+        assert!(matches!(PinFilter::parse(" Archive "), PinFilter::Archive));
+        assert!(matches!(
+            PinFilter::parse("category:archive"),
+            PinFilter::Category(category) if category == "archive"
+        ));
+        assert!(matches!(
+            PinFilter::parse("Category: Raid Guides"),
+            PinFilter::Category(category) if category == "Raid Guides"
+        ));
+        assert!(matches!(PinFilter::parse("category:"), PinFilter::All));
+        assert!(matches!(PinFilter::parse("#archive"), PinFilter::Tag(tag) if tag == "archive"));
+        assert_eq!(category_filter("Archive"), "category:Archive");
+        assert_eq!(category_filter("Guides"), "Guides");
+    }
+
+    #[test]
+    fn filters_match_categories_in_any_case_and_whole_tags() {
+        let guide = pin(1, Some("Guides"), &["raids"]);
+        assert!(PinFilter::parse("guides").matches(&guide));
+        assert!(PinFilter::parse("#RAIDS").matches(&guide));
+        assert!(!PinFilter::parse("#raid").matches(&guide));
+        assert!(!PinFilter::parse("Events").matches(&guide));
+        assert!(!PinFilter::parse("Guides").matches(&pin(2, None, &[])));
+    }
+
+    #[test]
+    fn parses_tags() {
+        assert_eq!(parse_tags("#raids, PvE #raids"), vec!["raids", "pve"]);
+        assert_eq!(parse_tags("raids,,pve"), vec!["raids", "pve"]);
+        assert!(parse_tags("none").is_empty());
+        assert!(parse_tags("NONE").is_empty());
+        assert!(parse_tags("  ").is_empty());
+    }
+
+    #[test]
+    fn parses_categories() {
+        assert_eq!(parse_category(" Guides "), Some("Guides".to_string()));
+        assert_eq!(parse_category("none"), None);
+        assert_eq!(parse_category(""), None);
+    }
+
+    #[test]
+    fn lists_categories_and_tags_once_in_alphabetical_order() {
+        let pins = vec![
+            pin(1, Some("raids"), &["pve", "raids"]),
+            pin(2, Some("Events"), &["pve"]),
+            pin(3, Some("Raids"), &[]),
+            pin(4, None, &["art"]),
+        ];
+        assert_eq!(categories(&pins), vec!["Events", "raids"]);
+        assert_eq!(tags(&pins), vec!["art", "pve", "raids"]);
+    }
+
+    #[test]
+    fn groups_pins_by_category_with_uncategorized_last() {
+        let pins = [
+            pin(1, None, &[]),
+            pin(2, Some("raids"), &[]),
+            pin(3, Some("Events"), &[]),
+            pin(4, Some("Raids"), &[]),
+        ];
+        let listed = numbered(&pins);
+        let fields = grouped_fields(&listed, &[], &HashMap::new());
+        let names: Vec<&str> = fields.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["Events", "raids", UNCATEGORIZED]);
+        assert_eq!(fields[1].1.lines().count(), 2);
+    }
+
+    #[test]
+    fn counts_the_pins_that_dont_fit() {
+        let pins: Vec<Pin> = (0..200)
+            .map(|n| pin(n, Some(&format!("Category {}", n % 30)), &[]))
+            .collect();
+        let listed = numbered(&pins);
+        let fields = grouped_fields(&listed, &[], &HashMap::new());
+        assert!(fields.len() <= MAX_PIN_FIELDS + 1);
+        let shown: usize = fields[..fields.len() - 1]
+            .iter()
+            .map(|(_, value, _)| value.lines().count())
+            .sum();
+        let (last, _, _) = fields.last().unwrap();
+        assert_eq!(last, &format!("...and {} more", 200 - shown));
+    }
+
+    #[test]
+    fn flags_dead_links() {
+        let pins = [pin(1, None, &["raids"]), pin(2, None, &[])];
+        let listed = numbered(&pins);
+        let dead = HashMap::from([(pins[1].id, "HTTP 404".to_string())]);
+        let fields = grouped_fields(&listed, &[], &dead);
+        let lines: Vec<&str> = fields[0].1.lines().collect();
+        assert!(lines[0].ends_with("`#raids`"));
+        assert!(lines[1].ends_with(":warning: *Link looks dead (HTTP 404)*"));
+    }
+
+    #[test]
+    fn numbers_pins_and_lists_featured_pins_first() {
+        let pins = [
+            pin(1, Some("Raids"), &[]),
+            pin(2, None, &[]),
+            pin(3, Some("Raids"), &[]),
+        ];
+        let listed: Vec<(usize, &Pin)> = numbered(&pins).into_iter().skip(1).collect();
+        let fields = grouped_fields(&listed, &[pins[2].id], &HashMap::new());
+        let names: Vec<&str> = fields.iter().map(|(name, _, _)| name.as_str()).collect();
+        assert_eq!(names, vec![FEATURED, UNCATEGORIZED]);
+        assert!(fields[0]
+            .1
+            .starts_with(&format!("**3.** `{}` ", validation::short_id(&pins[2].id))));
+        assert!(fields[0].1.contains("**[Pin 3](https://example.com/3)**"));
+        assert!(fields[1].1.starts_with("**2.** `"));
+    }
+
+    #[test]
+    fn sorts_featured_pins_first_then_by_the_arranged_order() {
+        let mut pins: Vec<Pin> = (1..=5).map(|n| pin(n, None, &[])).collect();
+        let id = |n: u128| Uuid::from_u128(n);
+        let settings = GuildSettings {
+            pin_order: vec![id(4), id(2), id(5), id(1)],
+            featured_pins: vec![id(5), id(3)],
+            ..Default::default()
+        };
+        sort_pins(&mut pins, &settings);
+        let order: Vec<Uuid> = pins.iter().map(|pin| pin.id).collect();
+        // Pin 3 was never moved, so it comes last among the featured pins.
+        assert_eq!(order, vec![id(5), id(3), id(4), id(2), id(1)]);
+    }
+
+    #[test]
+    fn parses_expiry_dates() {
+        let tz = chrono_tz::UTC;
+        assert_eq!(parse_expiry("", tz), Ok(None));
+        assert_eq!(parse_expiry(" Never ", tz), Ok(None));
+        let expires_at = parse_expiry("in 3 days", tz).unwrap().unwrap();
+        let from_now = expires_at - Utc::now().naive_utc();
+        assert!(from_now > chrono::Duration::days(2) && from_now <= chrono::Duration::days(3));
+        assert!(parse_expiry("2020-01-01", tz).is_err());
+        assert!(parse_expiry("someday", tz).is_err());
+    }
+
+    #[test]
+    fn only_listed_pins_show_their_expiry() {
+        let mut expiring = pin(1, None, &[]);
+        expiring.expires_at = Some(Utc::now().naive_utc() + chrono::Duration::days(1));
+        assert!(pin_line(&expiring, false, None).contains(":hourglass: *Expires <t:"));
+        expiring.archived_at = Some(Utc::now().naive_utc());
+        assert!(!pin_line(&expiring, false, None).contains(":hourglass:"));
+    }
+
+    #[test]
+    fn overflow_carries_the_given_hint() {
+        let lines: Vec<String> = (0..100).map(|n| "x".repeat(n % 50 + 500)).collect();
+        let fields = fill_fields(vec![("Archived".to_string(), lines)], "Hint.");
+        let (name, value, _) = fields.last().unwrap();
+        assert!(name.starts_with("...and "));
+        assert_eq!(value, "Hint.");
+        assert!(fields[..fields.len() - 1]
+            .iter()
+            .all(|(name, _, _)| name.starts_with("Archived")));
+    }
 }
@@ -0,0 +1,35 @@
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::CommandResult;
+use serenity::model::channel::Message;
+use serenity::prelude::*;
+
+use crate::api;
+use crate::validation::validation;
+
+use super::invocation::Invocation;
+
+#[command]
+#[allowed_roles("corkboard")]
+#[description = "Force the bot to reload pins, events and FAQs from the Corkboard Server."]
+async fn refresh(ctx: &Context, msg: &Message) -> CommandResult {
+    run_refresh(ctx, &Invocation::Prefix(msg)).await
+}
+
+pub async fn run_refresh(ctx: &Context, inv: &Invocation<'_>) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let guild_id = i64::from(inv.guild_id().unwrap());
+    api::client(ctx).await.invalidate_guild(guild_id);
+    println!("Cleared cached corkboard data for guild {}", guild_id);
+
+    let _msg = inv
+        .say(
+            ctx,
+            ":arrows_counterclockwise: Cleared cached pins, events and FAQs. The next lookup will come straight from the Corkboard Server.",
+        )
+        .await;
+
+    Ok(())
+}
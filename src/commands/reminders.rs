@@ -0,0 +1,149 @@
+use chrono::Duration;
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::Message;
+use serenity::model::id::ChannelId;
+use serenity::prelude::*;
+use serenity::utils::parse_channel;
+
+use crate::dates;
+use crate::reminders;
+use crate::store;
+use crate::validation::validation;
+
+use super::invocation::Invocation;
+
+pub enum ReminderChange {
+    Show,
+    Off,
+    Set {
+        channel_id: Option<ChannelId>,
+        offsets: Option<Vec<String>>,
+    },
+}
+
+#[command]
+#[allowed_roles("corkboard")]
+#[description = "Shows or sets where and when event reminders are posted. Offsets are times before the start like `24h`, `1h`, `30m`, or `0` for the start itself. Use `off` to stop reminders."]
+#[usage = "[#channel] [offsets...] | off"]
+async fn reminders(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let change = if args.is_empty() {
+        ReminderChange::Show
+    } else if args.current() == Some("off") {
+        ReminderChange::Off
+    } else {
+        let channel_id = args.current().and_then(parse_channel).map(ChannelId);
+        if channel_id.is_some() {
+            args.advance();
+        }
+        let offsets: Vec<String> = args.iter::<String>().filter_map(Result::ok).collect();
+        ReminderChange::Set {
+            channel_id,
+            offsets: Some(offsets).filter(|offsets| !offsets.is_empty()),
+        }
+    };
+
+    run_reminders(ctx, &Invocation::Prefix(msg), change).await
+}
+
+pub async fn run_reminders(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    change: ReminderChange,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let store = store::store(ctx).await;
+    let guild_id = u64::from(inv.guild_id().unwrap());
+    match change {
+        ReminderChange::Show => {}
+        ReminderChange::Off => {
+            store.update_guild(guild_id, |settings| settings.reminder_channel = None);
+        }
+        ReminderChange::Set {
+            channel_id,
+            offsets,
+        } => {
+            let offsets = match offsets.map(|offsets| parse_offsets(&offsets)) {
+                Some(Err(error)) => {
+                    let _msg = inv.say(ctx, error).await;
+                    return Ok(());
+                }
+                Some(Ok(offsets)) => Some(offsets),
+                None => None,
+            };
+            if channel_id.is_none() && store.guild(guild_id).reminder_channel.is_none() {
+                let _msg = inv
+                    .say(
+                        ctx,
+                        ":bangbang: Error :bangbang: - Pick a channel for reminders first, e.g. `.reminders #events 24h 1h 0`.",
+                    )
+                    .await;
+                return Ok(());
+            }
+
+            store.update_guild(guild_id, |settings| {
+                if let Some(channel_id) = channel_id {
+                    settings.reminder_channel = Some(channel_id.0);
+                }
+                if let Some(offsets) = offsets {
+                    settings.reminder_offsets = Some(offsets);
+                }
+            });
+        }
+    }
+
+    let settings = store.guild(guild_id);
+    let reply = match settings.reminder_channel {
+        Some(channel_id) => {
+            let offsets: Vec<String> = reminders::offsets(&settings)
+                .into_iter()
+                .map(reminders::describe_offset)
+                .collect();
+            format!(
+                ":alarm_clock: Event reminders are posted in <#{}>: {}.",
+                channel_id,
+                offsets.join(", ")
+            )
+        }
+        None => ":alarm_clock: Event reminders are off.".to_string(),
+    };
+
+    let _msg = inv.say(ctx, reply).await;
+    Ok(())
+}
+
+// Offsets are durations before the start; `0` or `start` means at the start itself.
+fn parse_offsets(values: &[String]) -> Result<Vec<i64>, String> {
+    let mut offsets = Vec::new();
+    for value in values {
+        let offset = match value.to_lowercase().as_str() {
+            "0" | "start" => 0,
+            other => match dates::parse_duration(other) {
+                Some(duration) if duration <= Duration::days(reminders::MAX_OFFSET_DAYS) => {
+                    duration.num_minutes()
+                }
+                Some(_) => {
+                    return Err(format!(
+                        ":bangbang: Error :bangbang: - `{}` is too early for a reminder. Reminders can be posted at most {} days before an event.",
+                        value,
+                        reminders::MAX_OFFSET_DAYS
+                    ))
+                }
+                None => {
+                    return Err(format!(
+                        ":bangbang: Error :bangbang: - `{}` is not a reminder offset. Use durations like `24h`, `1h`, `30m`, or `0` for the start.",
+                        value
+                    ))
+                }
+            },
+        };
+        offsets.push(offset);
+    }
+
+    offsets.sort_unstable_by(|a, b| b.cmp(a));
+    offsets.dedup();
+    Ok(offsets)
+}
@@ -0,0 +1,363 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serenity::builder::{CreateActionRow, CreateComponents, CreateEmbed};
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::application::component::ButtonStyle;
+use serenity::model::application::interaction::message_component::MessageComponentInteraction;
+use serenity::model::application::interaction::InteractionResponseType;
+use serenity::model::channel::Message;
+use serenity::prelude::*;
+use serenity::utils::Colour;
+use uuid::Uuid;
+
+use crate::api;
+use crate::error::BotError;
+use crate::store;
+use crate::timezone;
+use crate::validation::validation;
+
+use super::events::{self, Event};
+use super::invocation::{Invocation, Reply};
+
+// Discord caps a message at 2000 characters; the attendee list stops short of that.
+static MAX_ATTENDEE_LIST_LEN: usize = 1900;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum RsvpStatus {
+    Going,
+    Maybe,
+    NotGoing,
+}
+
+impl RsvpStatus {
+    const ALL: [RsvpStatus; 3] = [RsvpStatus::Going, RsvpStatus::Maybe, RsvpStatus::NotGoing];
+
+    fn key(self) -> &'static str {
+        match self {
+            RsvpStatus::Going => "going",
+            RsvpStatus::Maybe => "maybe",
+            RsvpStatus::NotGoing => "not_going",
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            RsvpStatus::Going => "Going",
+            RsvpStatus::Maybe => "Maybe",
+            RsvpStatus::NotGoing => "Not going",
+        }
+    }
+
+    fn emoji(self) -> &'static str {
+        match self {
+            RsvpStatus::Going => "✅",
+            RsvpStatus::Maybe => "❔",
+            RsvpStatus::NotGoing => "❌",
+        }
+    }
+
+    fn from_key(key: &str) -> Option<Self> {
+        RsvpStatus::ALL
+            .into_iter()
+            .find(|status| status.key() == key)
+    }
+}
+
+#[command]
+#[description = "Shows an Event with buttons to RSVP and see who's going."]
+#[usage = "event_id"]
+async fn event(ctx: &Context, msg: &Message, mut args: Args) -> CommandResult {
+    let arg_names = vec!["Event_id"];
+    if !validation::has_correct_arg_count(ctx, msg, 1, args.len(), arg_names, "event").await {
+        return Ok(());
+    }
+
+    args.quoted();
+    let id = args.current().unwrap().to_string();
+    run_event(ctx, &Invocation::Prefix(msg), &id).await
+}
+
+pub async fn run_event(ctx: &Context, inv: &Invocation<'_>, id: &str) -> CommandResult {
+    println!("Got event command..");
+    let (handle, date) = match events::split_handle(id) {
+        Ok(split) => split,
+        Err(error) => {
+            let _msg = inv.say(ctx, error).await;
+            return Ok(());
+        }
+    };
+
+    let guild_id = inv.guild_id().unwrap();
+    let client = api::client(ctx).await;
+    let events = client.list_current_events(i64::from(guild_id)).await?;
+    let ids: Vec<Uuid> = events.iter().map(|event| event.id).collect();
+    let real_id = match validation::resolve_short_id(ctx, inv, handle, &ids, "events").await {
+        Some(i) => i,
+        None => return Ok(()),
+    };
+    let series = events.iter().find(|event| event.id == real_id).unwrap();
+
+    // A recurring event is shown as the occurrence asked for, or else the next one.
+    let store = store::store(ctx).await;
+    let tz = store.guild_timezone(guild_id.into());
+    let event = &events::find_occurrence(series, tz, date).unwrap_or_else(|| series.clone());
+    let rsvps = store.rsvps(&rsvp_key(event, tz));
+    let _msg = inv
+        .reply(
+            ctx,
+            Reply::embed(event_card(event, &rsvps)).components(rsvp_buttons(event, tz)),
+        )
+        .await;
+
+    println!("Finished processing event command!");
+    Ok(())
+}
+
+// Where an event's RSVPs are kept. Each occurrence of a recurring event takes its own,
+// so they're keyed by the series ID and the occurrence date in the guild's time zone.
+pub fn rsvp_key(event: &Event, tz: Tz) -> String {
+    if event.recurrence.is_some() {
+        format!(
+            "{}@{}",
+            event.id,
+            events::occurrence_date(event, tz).format("%Y-%m-%d")
+        )
+    } else {
+        event.id.to_string()
+    }
+}
+
+// A single event with its RSVP counts, shown by `.event` and by reminders.
+pub fn event_card(event: &Event, rsvps: &HashMap<u64, RsvpStatus>) -> CreateEmbed {
+    let counts: Vec<String> = RsvpStatus::ALL
+        .iter()
+        .map(|status| {
+            let count = rsvps.values().filter(|s| *s == status).count();
+            format!("{} {}: {}", status.emoji(), status.label(), count)
+        })
+        .collect();
+
+    let mut embed = CreateEmbed::default();
+    // Discord rejects the whole message if an embed URL isn't a real link.
+    if validation::is_web_url(&event.url) {
+        embed.url(&event.url);
+    }
+    embed
+        .title(&event.title)
+        .description(&event.description)
+        .field("Start", timezone::discord_timestamp(event.start_date), true)
+        .field("End", timezone::discord_timestamp(event.end_date), true)
+        .field("RSVPs", counts.join("  "), false)
+        .footer(|f| f.text(format!("Event {}", validation::short_id(&event.id))))
+        .colour(Colour::GOLD);
+    embed
+}
+
+// The buttons carry the event's RSVP key, so they keep working after the bot restarts
+// and each occurrence of a series gets its own.
+pub fn rsvp_buttons(event: &Event, tz: Tz) -> CreateComponents {
+    let key = rsvp_key(event, tz);
+    let mut action_row = CreateActionRow::default();
+    for status in RsvpStatus::ALL {
+        let style = match status {
+            RsvpStatus::Going => ButtonStyle::Success,
+            RsvpStatus::Maybe => ButtonStyle::Secondary,
+            RsvpStatus::NotGoing => ButtonStyle::Danger,
+        };
+        action_row.create_button(|b| {
+            b.style(style)
+                .custom_id(format!("rsvp:{}:{}", status.key(), key))
+                .label(status.label())
+        });
+    }
+    action_row.create_button(|b| {
+        b.style(ButtonStyle::Primary)
+            .custom_id(format!("rsvp:list:{}", key))
+            .label("Attendees")
+    });
+
+    let mut components = CreateComponents::default();
+    components.add_action_row(action_row);
+    components
+}
+
+// Handles a click on one of the RSVP buttons. Unlike the Luckydex and trade buttons these
+// aren't collected by the command that posted them, since they have to keep working for as
+// long as the event is listed.
+pub async fn respond(ctx: &Context, component: &MessageComponentInteraction) {
+    let (action, event_id, date) = match parse_custom_id(&component.data.custom_id) {
+        Some(parsed) => parsed,
+        None => return,
+    };
+
+    let result = match action {
+        "list" => show_attendees(ctx, component, event_id, date).await,
+        _ => match RsvpStatus::from_key(action) {
+            Some(status) => record_rsvp(ctx, component, event_id, date, status).await,
+            None => Ok(()),
+        },
+    };
+
+    if let Err(why) = result {
+        println!(
+            "Unable to handle RSVP button {}: {:?}",
+            component.data.custom_id, why
+        );
+    }
+}
+
+// Buttons posted before RSVPs were kept per occurrence carry no date, and act on the
+// next occurrence.
+fn parse_custom_id(custom_id: &str) -> Option<(&str, Uuid, Option<NaiveDate>)> {
+    let rest = custom_id.strip_prefix("rsvp:")?;
+    let (action, handle) = rest.split_once(':')?;
+    let (id, date) = match handle.split_once('@') {
+        Some((id, date)) => (id, Some(NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?)),
+        None => (handle, None),
+    };
+    Some((action, Uuid::parse_str(id).ok()?, date))
+}
+
+// Clicking the status you already have takes your RSVP back.
+async fn record_rsvp(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    event_id: Uuid,
+    date: Option<NaiveDate>,
+    status: RsvpStatus,
+) -> CommandResult {
+    let (event, tz) = match current_event(ctx, component, event_id, date).await? {
+        Some(found) => found,
+        None => {
+            return ephemeral(
+                ctx,
+                component,
+                "This event has ended or was removed, so it no longer takes RSVPs.",
+            )
+            .await
+        }
+    };
+
+    let store = store::store(ctx).await;
+    let user_id = u64::from(component.user.id);
+    let key = rsvp_key(&event, tz);
+    let current = store.rsvps(&key).get(&user_id).copied();
+    let new_status = Some(status).filter(|status| Some(*status) != current);
+    store.set_rsvp(&key, user_id, new_status);
+
+    let rsvps = store.rsvps(&key);
+    component
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::UpdateMessage)
+                .interaction_response_data(|d| {
+                    d.set_embed(event_card(&event, &rsvps))
+                        .set_components(rsvp_buttons(&event, tz))
+                })
+        })
+        .await?;
+
+    Ok(())
+}
+
+async fn show_attendees(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    event_id: Uuid,
+    date: Option<NaiveDate>,
+) -> CommandResult {
+    // An occurrence that's over still shows who went.
+    let key = match date {
+        Some(date) => format!("{}@{}", event_id, date.format("%Y-%m-%d")),
+        None => match current_event(ctx, component, event_id, None).await? {
+            Some((event, tz)) => rsvp_key(&event, tz),
+            None => event_id.to_string(),
+        },
+    };
+    let rsvps = store::store(ctx).await.rsvps(&key);
+    let mut content = String::new();
+    for status in RsvpStatus::ALL {
+        let mut users: Vec<u64> = rsvps
+            .iter()
+            .filter(|(_, s)| **s == status)
+            .map(|(user_id, _)| *user_id)
+            .collect();
+        users.sort_unstable();
+        let mentions: Vec<String> = users
+            .iter()
+            .map(|user_id| format!("<@{}>", user_id))
+            .collect();
+        let line = format!(
+            "{} **{}** ({}): {}\n",
+            status.emoji(),
+            status.label(),
+            users.len(),
+            if mentions.is_empty() {
+                "nobody yet".to_string()
+            } else {
+                mentions.join(", ")
+            }
+        );
+        content.push_str(&line);
+    }
+
+    if content.len() > MAX_ATTENDEE_LIST_LEN {
+        let cut = (0..=MAX_ATTENDEE_LIST_LEN)
+            .rev()
+            .find(|i| content.is_char_boundary(*i))
+            .unwrap_or(0);
+        content.truncate(cut);
+        content.push('…');
+    }
+
+    ephemeral(ctx, component, content).await
+}
+
+// The occurrence on `date`, or the next one, as long as it hasn't ended.
+async fn current_event(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    event_id: Uuid,
+    date: Option<NaiveDate>,
+) -> Result<Option<(Event, Tz)>, BotError> {
+    let guild_id = match component.guild_id {
+        Some(guild_id) => i64::from(guild_id),
+        None => return Ok(None),
+    };
+    let events = api::client(ctx).await.list_current_events(guild_id).await?;
+    let tz = store::store(ctx).await.guild_timezone(guild_id as u64);
+    let now = Utc::now().naive_utc();
+    Ok(events
+        .into_iter()
+        .find(|event| event.id == event_id)
+        .and_then(|event| match event.recurrence {
+            Some(_) => events::find_occurrence(&event, tz, date),
+            None => Some(event),
+        })
+        .filter(|event| event.end_date >= now)
+        .map(|event| (event, tz)))
+}
+
+// Only the clicking user sees the reply, and the mentions in it never ping anyone.
+async fn ephemeral(
+    ctx: &Context,
+    component: &MessageComponentInteraction,
+    content: impl ToString,
+) -> CommandResult {
+    component
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::ChannelMessageWithSource)
+                .interaction_response_data(|d| {
+                    d.content(content)
+                        .ephemeral(true)
+                        .allowed_mentions(|m| m.empty_parse())
+                })
+        })
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::Message;
+use serenity::prelude::*;
+
+use crate::scheduled;
+use crate::store::{self, SyncMode};
+use crate::validation::validation;
+
+use super::invocation::Invocation;
+
+#[command]
+#[allowed_roles("corkboard")]
+#[description = "Shows or sets how Events are synced with Discord's scheduled events: `mirror` copies them to Discord, `two_way` also brings in scheduled events made in Discord, and `off` stops syncing. The bot needs the Manage Events permission."]
+#[usage = "[off|mirror|two_way]"]
+async fn scheduled_events(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    run_scheduled_events(ctx, &Invocation::Prefix(msg), args.current()).await
+}
+
+pub async fn run_scheduled_events(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    mode: Option<&str>,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let store = store::store(ctx).await;
+    let guild_id = u64::from(inv.guild_id().unwrap());
+    let mode = match mode.map(|mode| SyncMode::from_key(&mode.to_lowercase())) {
+        None => {
+            let _msg = inv
+                .say(ctx, describe(store.guild(guild_id).scheduled_events))
+                .await;
+            return Ok(());
+        }
+        Some(None) => {
+            let _msg = inv
+                .say(
+                    ctx,
+                    ":bangbang: Error :bangbang: - Pick one of `off`, `mirror` or `two_way`.",
+                )
+                .await;
+            return Ok(());
+        }
+        Some(Some(mode)) => mode,
+    };
+
+    store.update_guild(guild_id, |settings| settings.scheduled_events = mode);
+    let mut reply = describe(mode);
+    // Catches up on events added while sync was off, and on Discord's side for two-way.
+    if mode != SyncMode::Off {
+        let (mirrored, imported) = scheduled::sync_guild(ctx, guild_id).await?;
+        reply.push_str(&format!(
+            "\nCopied {} events to Discord and brought in {} scheduled events from Discord.",
+            mirrored, imported
+        ));
+    }
+    let _msg = inv.say(ctx, reply).await;
+
+    Ok(())
+}
+
+fn describe(mode: SyncMode) -> String {
+    let summary = match mode {
+        SyncMode::Off => "Events aren't synced with Discord's scheduled events.",
+        SyncMode::Mirror => "Events are copied to Discord's scheduled events.",
+        SyncMode::TwoWay => "Events are copied to Discord's scheduled events, and scheduled events made or changed in Discord are brought back as Events.",
+    };
+    format!(":calendar_spiral: Sync: `{}`. {}", mode.key(), summary)
+}
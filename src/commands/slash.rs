@@ -0,0 +1,713 @@
+use serenity::builder::{
+    CreateApplicationCommand, CreateApplicationCommandOption, CreateApplicationCommands,
+};
+use serenity::framework::standard::CommandResult;
+use serenity::model::application::command::{Command, CommandOptionType, CommandType};
+use serenity::model::application::interaction::application_command::{
+    ApplicationCommandInteraction, CommandDataOptionValue,
+};
+use serenity::model::application::interaction::InteractionResponseType;
+use serenity::model::channel::Attachment;
+use serenity::model::id::{ChannelId, UserId};
+use serenity::prelude::*;
+
+use crate::error::BotError;
+use crate::store;
+
+use super::events::EventView;
+use super::forms::{self, FormKind};
+use super::import;
+use super::invocation::Invocation;
+use super::reminders::{self, ReminderChange};
+use super::rsvp;
+use super::{
+    calendar, events, faqs, luckydex, luckymon, luckytrade, message_pins, pins, refresh, scheduled,
+    timezone,
+};
+
+static DATE_HINT: &str = "e.g. 12/1/2022 9:00AM, 2022-12-01 21:00 or tomorrow 8pm";
+static END_DATE_HINT: &str = "A date like the start, or a duration such as 2h";
+static REPEAT_HINT: &str = "e.g. weekly, every 2 weeks on mon, wed, monthly until 12/31/2024";
+
+// Registers every command as a global slash command. Discord replaces the whole set on
+// each call, so this is safe to run on every startup.
+pub async fn register(ctx: &Context) {
+    let result = Command::set_global_application_commands(&ctx.http, |commands| {
+        add_general_commands(commands);
+        add_admin_commands(commands);
+        commands
+    })
+    .await;
+
+    match result {
+        Ok(commands) => println!("Registered {} slash commands.", commands.len()),
+        Err(why) => println!("Unable to register slash commands: {:?}", why),
+    }
+}
+
+fn add_general_commands(commands: &mut CreateApplicationCommands) {
+    commands
+        .create_application_command(|c| {
+            guild_command(c, "pins", "Retrieves all pins, grouped by category.").create_option(
+                |o| {
+                    o.name("filter")
+                        .description(
+                            "Only list pins in this category or with a #tag, or archive for expired pins",
+                        )
+                        .kind(CommandOptionType::String)
+                        .set_autocomplete(true)
+                },
+            )
+        })
+        .create_application_command(|c| {
+            guild_command(
+                c,
+                "events",
+                "Retrieves events, shown in your own local time.",
+            )
+            .create_option(|o| {
+                o.name("view")
+                    .description("Which events to list")
+                    .kind(CommandOptionType::String)
+                    .add_string_choice("Upcoming", "upcoming")
+                    .add_string_choice("Past", "past")
+                    .add_string_choice("All", "all")
+            })
+            .create_option(|o| {
+                o.name("month")
+                    .description("Only this month, e.g. december, dec 2024 or 2024-12")
+                    .kind(CommandOptionType::String)
+            })
+            .create_option(|o| {
+                o.name("ics")
+                    .description("Attach the events as an iCalendar file instead")
+                    .kind(CommandOptionType::Boolean)
+            })
+        })
+        .create_application_command(|c| {
+            guild_command(
+                c,
+                "calendar",
+                "Draws a month of events, with a link to subscribe from a calendar app.",
+            )
+            .create_option(|o| {
+                o.name("month")
+                    .description("Month to draw, e.g. december, dec 2024 or 2024-12")
+                    .kind(CommandOptionType::String)
+            })
+            .create_option(|o| {
+                o.name("reset")
+                    .description("Replace the link so the old one stops working (admins only)")
+                    .kind(CommandOptionType::Boolean)
+            })
+        })
+        .create_application_command(|c| {
+            guild_command(
+                c,
+                "event",
+                "Shows an Event with buttons to RSVP and see who's going.",
+            )
+            .create_option(|o| id_option(o, "Event ID shown by /events"))
+        })
+        .create_application_command(|c| guild_command(c, "faqs", "Retrieves all FAQs."))
+        .create_application_command(|c| guild_command(c, "luckymon", "Lucky pokemon of the day!"))
+        .create_application_command(|c| {
+            guild_command(c, "luckydex", "Retrieves Luckymon History for a User.")
+        })
+        .create_application_command(|c| {
+            guild_command(c, "luckytrade", "Trade Your Luckymon With Other Users.")
+                .create_option(|o| {
+                    o.name("user")
+                        .description("User you wish to trade with")
+                        .kind(CommandOptionType::User)
+                        .required(true)
+                })
+                .create_option(|o| {
+                    string_option(
+                        o,
+                        "offer",
+                        "The Pokémon you wish to trade: #, #s for shiny, or n/a",
+                    )
+                    .set_autocomplete(true)
+                })
+                .create_option(|o| {
+                    string_option(
+                        o,
+                        "request",
+                        "The Pokémon you wish to receive: #, #s for shiny, or n/a",
+                    )
+                    .set_autocomplete(true)
+                })
+        })
+        .create_application_command(|c| {
+            guild_command(
+                c,
+                "timezone",
+                "Shows your time zone, or sets it. Use `reset` to go back to the server default.",
+            )
+            .create_option(|o| zone_option(o).required(false))
+        });
+}
+
+fn add_admin_commands(commands: &mut CreateApplicationCommands) {
+    commands
+        .create_application_command(|c| {
+            guild_command(c, "add_pin", "Add a Pin.")
+                .create_option(|o| string_option(o, "url", "Link the pin points at"))
+                .create_option(|o| {
+                    string_option(
+                        o,
+                        "title",
+                        "Title of the pin, read from the link if left out",
+                    )
+                    .required(false)
+                })
+                .create_option(|o| {
+                    string_option(
+                        o,
+                        "description",
+                        "Description of the pin, read from the link if left out",
+                    )
+                    .required(false)
+                })
+                .create_option(|o| category_option(o, "Category to list the pin under"))
+                .create_option(|o| tags_option(o, "Tags for the pin, e.g. #raids #guides"))
+                .create_option(|o| {
+                    string_option(
+                        o,
+                        "expires",
+                        "When to archive the pin, e.g. 12/1/2022 9:00AM",
+                    )
+                    .required(false)
+                })
+        })
+        .create_application_command(|c| {
+            guild_command(c, "edit_pin", "Edit a Pin.")
+                .create_option(|o| id_option(o, "Pin ID shown by /pins"))
+                .create_option(|o| string_option(o, "title", "Title of the pin"))
+                .create_option(|o| string_option(o, "url", "Link the pin points at"))
+                .create_option(|o| string_option(o, "description", "Description of the pin"))
+                .create_option(|o| {
+                    category_option(o, "New category, or none to clear it; left out keeps it")
+                })
+                .create_option(|o| {
+                    tags_option(o, "New tags, or none to clear them; left out keeps them")
+                })
+        })
+        .create_application_command(|c| {
+            guild_command(c, "delete_pin", "Delete a Pin.")
+                .create_option(|o| id_option(o, "Pin ID shown by /pins"))
+        })
+        .create_application_command(|c| {
+            guild_command(c, "move_pin", "Move a Pin to another place in /pins.")
+                .create_option(|o| id_option(o, "Pin ID shown by /pins, or its place"))
+                .create_option(|o| {
+                    o.name("place")
+                        .description("Where to move it, 1 being the top")
+                        .kind(CommandOptionType::Integer)
+                        .min_int_value(1)
+                        .required(true)
+                })
+        })
+        .create_application_command(|c| {
+            guild_command(c, "feature_pin", "Feature a Pin at the top of /pins.")
+                .create_option(|o| id_option(o, "Pin ID shown by /pins"))
+                .create_option(|o| {
+                    o.name("off")
+                        .description("Stop featuring the pin")
+                        .kind(CommandOptionType::Boolean)
+                })
+        })
+        .create_application_command(|c| {
+            guild_command(c, "expire_pin", "Set when a Pin is moved to the archive.")
+                .create_option(|o| id_option(o, "Pin ID shown by /pins"))
+                .create_option(|o| {
+                    string_option(o, "date", "A date like 12/1/2022 9:00AM, or never")
+                })
+        })
+        .create_application_command(|c| {
+            guild_command(c, "restore_pin", "Bring an archived Pin back to /pins.")
+                .create_option(|o| id_option(o, "Pin ID shown by /pins archive"))
+        })
+        .create_application_command(|c| {
+            guild_command(
+                c,
+                "pin_reaction",
+                "Shows or sets the emoji that pins a message when reacted with.",
+            )
+            .create_option(|o| {
+                o.name("emoji")
+                    .description("An emoji like 📌, or off to stop pinning by reaction")
+                    .kind(CommandOptionType::String)
+            })
+        })
+        // Shown in a message's Apps menu rather than typed, so it has no description.
+        .create_application_command(|c| {
+            c.name(message_pins::CONTEXT_MENU_NAME)
+                .kind(CommandType::Message)
+                .dm_permission(false)
+        })
+        .create_application_command(|c| {
+            // Discord lists required options first, so the optional title comes last.
+            guild_command(c, "add_event", "Add an Event.")
+                .create_option(|o| string_option(o, "url", "Link for the event"))
+                .create_option(|o| string_option(o, "start", DATE_HINT))
+                .create_option(|o| string_option(o, "end", END_DATE_HINT))
+                .create_option(|o| {
+                    string_option(
+                        o,
+                        "title",
+                        "Title of the event, read from the link if left out",
+                    )
+                    .required(false)
+                })
+                .create_option(|o| {
+                    string_option(
+                        o,
+                        "description",
+                        "Description of the event, read from the link if left out",
+                    )
+                    .required(false)
+                })
+        })
+        .create_application_command(|c| {
+            guild_command(c, "edit_event", "Edit an Event.")
+                .create_option(|o| id_option(o, "Event ID shown by /events"))
+                .create_option(|o| string_option(o, "title", "Title of the event"))
+                .create_option(|o| string_option(o, "url", "Link for the event"))
+                .create_option(|o| string_option(o, "description", "Description of the event"))
+                .create_option(|o| string_option(o, "start", DATE_HINT))
+                .create_option(|o| string_option(o, "end", END_DATE_HINT))
+        })
+        .create_application_command(|c| {
+            guild_command(c, "delete_event", "Delete an Event.")
+                .create_option(|o| id_option(o, "Event ID shown by /events"))
+        })
+        .create_application_command(|c| {
+            guild_command(c, "repeat_event", "Make an Event repeat, or `off` to stop.")
+                .create_option(|o| id_option(o, "Event ID shown by /events"))
+                .create_option(|o| string_option(o, "rule", REPEAT_HINT))
+        })
+        .create_application_command(|c| {
+            guild_command(
+                c,
+                "import_events",
+                "Import Events from an iCalendar (.ics) file, after a preview.",
+            )
+            .create_option(|o| {
+                o.name("file")
+                    .description("An .ics export from Google Calendar, Outlook or similar")
+                    .kind(CommandOptionType::Attachment)
+                    .required(true)
+            })
+        })
+        .create_application_command(|c| {
+            guild_command(c, "add_faq", "Create new FAQ.")
+                .create_option(|o| string_option(o, "question", "The question"))
+                .create_option(|o| string_option(o, "answer", "The answer"))
+        })
+        .create_application_command(|c| {
+            guild_command(c, "edit_faq", "Edit an existing FAQ.")
+                .create_option(|o| id_option(o, "FAQ ID shown by /faqs"))
+                .create_option(|o| string_option(o, "question", "The question"))
+                .create_option(|o| string_option(o, "answer", "The answer"))
+        })
+        .create_application_command(|c| {
+            guild_command(c, "delete_faq", "Delete a FAQ.")
+                .create_option(|o| id_option(o, "FAQ ID shown by /faqs"))
+        })
+        .create_application_command(|c| {
+            guild_command(c, "pin_form", "Add a Pin, or edit one, using a form.")
+                .create_option(|o| optional_id_option(o, "Pin to edit, leave empty to add one"))
+        })
+        .create_application_command(|c| {
+            guild_command(c, "event_form", "Add an Event, or edit one, using a form.")
+                .create_option(|o| optional_id_option(o, "Event to edit, leave empty to add one"))
+        })
+        .create_application_command(|c| {
+            guild_command(c, "faq_form", "Add a FAQ, or edit one, using a form.")
+                .create_option(|o| optional_id_option(o, "FAQ to edit, leave empty to add one"))
+        })
+        .create_application_command(|c| {
+            guild_command(c, "guild_timezone", "Sets the server's default time zone.")
+                .create_option(zone_option)
+        })
+        .create_application_command(|c| {
+            guild_command(
+                c,
+                "reminders",
+                "Shows or sets where and when event reminders are posted.",
+            )
+            .create_option(|o| {
+                o.name("channel")
+                    .description("Channel to post reminders in")
+                    .kind(CommandOptionType::Channel)
+            })
+            .create_option(|o| {
+                o.name("offsets")
+                    .description("Times before the start, e.g. 24h 1h 0")
+                    .kind(CommandOptionType::String)
+            })
+            .create_option(|o| {
+                o.name("off")
+                    .description("Stop posting reminders")
+                    .kind(CommandOptionType::Boolean)
+            })
+        })
+        .create_application_command(|c| {
+            guild_command(
+                c,
+                "scheduled_events",
+                "Shows or sets how Events are synced with Discord's scheduled events.",
+            )
+            .create_option(|o| {
+                o.name("mode")
+                    .description("How to sync")
+                    .kind(CommandOptionType::String)
+                    .add_string_choice("Off", "off")
+                    .add_string_choice("Copy events to Discord", "mirror")
+                    .add_string_choice("Both ways", "two_way")
+            })
+        })
+        .create_application_command(|c| {
+            guild_command(
+                c,
+                "refresh",
+                "Force the bot to reload pins, events and FAQs from the Corkboard Server.",
+            )
+        });
+}
+
+// Every command reads its guild's corkboard, so none of them are offered in DMs.
+fn guild_command<'a>(
+    command: &'a mut CreateApplicationCommand,
+    name: &str,
+    description: &str,
+) -> &'a mut CreateApplicationCommand {
+    command
+        .name(name)
+        .description(description)
+        .dm_permission(false)
+}
+
+fn string_option<'a>(
+    option: &'a mut CreateApplicationCommandOption,
+    name: &str,
+    description: &str,
+) -> &'a mut CreateApplicationCommandOption {
+    option
+        .name(name)
+        .description(description)
+        .kind(CommandOptionType::String)
+        .required(true)
+}
+
+// Item IDs are suggested from the guild's current pins, events or FAQs as the user types.
+fn id_option<'a>(
+    option: &'a mut CreateApplicationCommandOption,
+    description: &str,
+) -> &'a mut CreateApplicationCommandOption {
+    string_option(option, "id", description).set_autocomplete(true)
+}
+
+fn optional_id_option<'a>(
+    option: &'a mut CreateApplicationCommandOption,
+    description: &str,
+) -> &'a mut CreateApplicationCommandOption {
+    id_option(option, description).required(false)
+}
+
+// Suggested from the categories the guild's pins already use, so they stay consistent.
+fn category_option<'a>(
+    option: &'a mut CreateApplicationCommandOption,
+    description: &str,
+) -> &'a mut CreateApplicationCommandOption {
+    string_option(option, "category", description)
+        .required(false)
+        .set_autocomplete(true)
+}
+
+fn tags_option<'a>(
+    option: &'a mut CreateApplicationCommandOption,
+    description: &str,
+) -> &'a mut CreateApplicationCommandOption {
+    string_option(option, "tags", description).required(false)
+}
+
+fn zone_option(option: &mut CreateApplicationCommandOption) -> &mut CreateApplicationCommandOption {
+    string_option(option, "zone", "Time zone name, e.g. Europe/Berlin").set_autocomplete(true)
+}
+
+// Runs a slash command through the same code as its prefix counterpart. The response
+// is deferred first since most commands wait on the Corkboard Server.
+pub async fn dispatch(ctx: &Context, command: &ApplicationCommandInteraction) {
+    let name = command.data.name.as_str();
+    println!("Got slash command {}..", name);
+
+    // Forms have to be answered with the modal itself, so they can't be deferred.
+    if let Some(kind) = FormKind::from_command(name) {
+        forms::open(ctx, command, kind).await;
+        return;
+    }
+
+    if let Err(why) = command
+        .create_interaction_response(&ctx.http, |r| {
+            r.kind(InteractionResponseType::DeferredChannelMessageWithSource)
+        })
+        .await
+    {
+        println!("Unable to acknowledge slash command {}: {:?}", name, why);
+        return;
+    }
+
+    let inv = Invocation::Slash(command);
+    let result = run(ctx, &inv, command, name).await;
+
+    if let Err(why) = result {
+        match why.downcast_ref::<BotError>() {
+            Some(bot_error) => bot_error.report(ctx, &inv, name).await,
+            None => println!("Command '{}' returned error {:?}", name, why),
+        }
+    }
+}
+
+async fn run(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    command: &ApplicationCommandInteraction,
+    name: &str,
+) -> CommandResult {
+    let text = |option: &str| string_value(command, option);
+    let given = |option: &str| Some(text(option)).filter(|value| !value.is_empty());
+
+    match name {
+        "pins" => pins::run_pins(ctx, inv, &text("filter")).await,
+        "events" if bool_value(command, "ics") => events::run_events_ics(ctx, inv).await,
+        "events" => {
+            // A month on its own can only mean everything in that month.
+            let view = match (text("view"), text("month")) {
+                (view, month) if view.is_empty() && !month.is_empty() => format!("all {}", month),
+                (view, month) => format!("{} {}", view, month),
+            };
+            let tz = store::store(ctx)
+                .await
+                .guild_timezone(inv.guild_id().unwrap().into());
+            match EventView::parse(&view, tz) {
+                Ok(view) => events::run_events(ctx, inv, view).await,
+                Err(error) => {
+                    let _msg = inv.say(ctx, error).await;
+                    Ok(())
+                }
+            }
+        }
+        "calendar" if bool_value(command, "reset") => calendar::run_calendar_reset(ctx, inv).await,
+        "calendar" => calendar::run_calendar(ctx, inv, &text("month")).await,
+        "event" => rsvp::run_event(ctx, inv, &text("id")).await,
+        "faqs" => faqs::run_faqs(ctx, inv).await,
+        "luckymon" => luckymon::run_luckymon(ctx, inv).await,
+        "luckydex" => luckydex::run_luckydex(ctx, inv).await,
+        "luckytrade" => {
+            let callee_id = user_value(command, "user").ok_or("Missing user option.")?;
+            luckytrade::run_luckytrade(ctx, inv, callee_id, text("offer"), text("request")).await
+        }
+        "add_pin" => {
+            pins::run_add_pin(
+                ctx,
+                inv,
+                text("title"),
+                text("url"),
+                text("description"),
+                &text("category"),
+                &text("tags"),
+                &text("expires"),
+            )
+            .await
+        }
+        "edit_pin" => {
+            pins::run_edit_pin(
+                ctx,
+                inv,
+                &text("id"),
+                text("title"),
+                text("url"),
+                text("description"),
+                given("category").as_deref(),
+                given("tags").as_deref(),
+            )
+            .await
+        }
+        "delete_pin" => pins::run_delete_pin(ctx, inv, &text("id")).await,
+        "move_pin" => {
+            let place = integer_value(command, "place").ok_or("Missing place option.")?;
+            pins::run_move_pin(ctx, inv, &text("id"), place.max(1) as usize).await
+        }
+        "feature_pin" => {
+            pins::run_feature_pin(ctx, inv, &text("id"), !bool_value(command, "off")).await
+        }
+        "expire_pin" => pins::run_expire_pin(ctx, inv, &text("id"), &text("date")).await,
+        "restore_pin" => pins::run_restore_pin(ctx, inv, &text("id")).await,
+        "pin_reaction" => message_pins::run_pin_reaction(ctx, inv, given("emoji").as_deref()).await,
+        name if name == message_pins::CONTEXT_MENU_NAME => {
+            let message = command
+                .data
+                .resolved
+                .messages
+                .values()
+                .next()
+                .ok_or("Missing message.")?;
+            message_pins::run_pin_message(ctx, inv, message).await
+        }
+        "add_event" => {
+            events::run_add_event(
+                ctx,
+                inv,
+                text("title"),
+                text("url"),
+                text("description"),
+                &text("start"),
+                &text("end"),
+            )
+            .await
+        }
+        "edit_event" => {
+            events::run_edit_event(
+                ctx,
+                inv,
+                &text("id"),
+                text("title"),
+                text("url"),
+                text("description"),
+                &text("start"),
+                &text("end"),
+            )
+            .await
+        }
+        "delete_event" => events::run_delete_event(ctx, inv, &text("id")).await,
+        "repeat_event" => events::run_repeat_event(ctx, inv, &text("id"), &text("rule")).await,
+        "import_events" => match attachment_value(command, "file") {
+            Some(attachment) => import::run_import_events(ctx, inv, attachment).await,
+            None => Err("Missing file option.".into()),
+        },
+        "add_faq" => faqs::run_add_faq(ctx, inv, text("question"), text("answer")).await,
+        "edit_faq" => {
+            faqs::run_edit_faq(ctx, inv, &text("id"), text("question"), text("answer")).await
+        }
+        "delete_faq" => faqs::run_delete_faq(ctx, inv, &text("id")).await,
+        "timezone" => {
+            let zone = Some(text("zone")).filter(|zone| !zone.is_empty());
+            timezone::run_timezone(ctx, inv, zone).await
+        }
+        "guild_timezone" => timezone::run_guild_timezone(ctx, inv, text("zone")).await,
+        "reminders" => {
+            let channel_id = channel_value(command, "channel");
+            let offsets: Vec<String> = text("offsets")
+                .split_whitespace()
+                .map(str::to_string)
+                .collect();
+            let change = if bool_value(command, "off") {
+                ReminderChange::Off
+            } else if channel_id.is_none() && offsets.is_empty() {
+                ReminderChange::Show
+            } else {
+                ReminderChange::Set {
+                    channel_id,
+                    offsets: Some(offsets).filter(|offsets| !offsets.is_empty()),
+                }
+            };
+            reminders::run_reminders(ctx, inv, change).await
+        }
+        "scheduled_events" => {
+            let mode = Some(text("mode")).filter(|mode| !mode.is_empty());
+            scheduled::run_scheduled_events(ctx, inv, mode.as_deref()).await
+        }
+        "refresh" => refresh::run_refresh(ctx, inv).await,
+        _ => {
+            let _msg = inv
+                .say(
+                    ctx,
+                    format!(":bangbang: Error :bangbang: - Unknown command `/{}`.", name),
+                )
+                .await;
+            Ok(())
+        }
+    }
+}
+
+// Required string options are always present, so a missing one only happens if the
+// registered command is out of date; treat it as empty and let validation complain.
+fn string_value(command: &ApplicationCommandInteraction, name: &str) -> String {
+    command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match &option.resolved {
+            Some(CommandDataOptionValue::String(value)) => Some(value.clone()),
+            _ => None,
+        })
+        .unwrap_or_default()
+}
+
+fn user_value(command: &ApplicationCommandInteraction, name: &str) -> Option<UserId> {
+    command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match &option.resolved {
+            Some(CommandDataOptionValue::User(user, _)) => Some(user.id),
+            _ => None,
+        })
+}
+
+fn attachment_value<'a>(
+    command: &'a ApplicationCommandInteraction,
+    name: &str,
+) -> Option<&'a Attachment> {
+    command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match &option.resolved {
+            Some(CommandDataOptionValue::Attachment(attachment)) => Some(attachment),
+            _ => None,
+        })
+}
+
+fn channel_value(command: &ApplicationCommandInteraction, name: &str) -> Option<ChannelId> {
+    command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match &option.resolved {
+            Some(CommandDataOptionValue::Channel(channel)) => Some(channel.id),
+            _ => None,
+        })
+}
+
+fn integer_value(command: &ApplicationCommandInteraction, name: &str) -> Option<i64> {
+    command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match &option.resolved {
+            Some(CommandDataOptionValue::Integer(value)) => Some(*value),
+            _ => None,
+        })
+}
+
+fn bool_value(command: &ApplicationCommandInteraction, name: &str) -> bool {
+    command
+        .data
+        .options
+        .iter()
+        .find(|option| option.name == name)
+        .and_then(|option| match &option.resolved {
+            Some(CommandDataOptionValue::Boolean(value)) => Some(*value),
+            _ => None,
+        })
+        .unwrap_or(false)
+}
@@ -0,0 +1,109 @@
+use serenity::framework::standard::macros::command;
+use serenity::framework::standard::{Args, CommandResult};
+use serenity::model::channel::Message;
+use serenity::prelude::*;
+
+use crate::store;
+use crate::timezone;
+use crate::validation::validation;
+
+use super::invocation::Invocation;
+
+#[command]
+#[description = "Shows your time zone, or sets it when given a zone name like `Europe/Berlin`. Use `reset` to go back to the server default."]
+#[usage = "[zone]"]
+async fn timezone(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let zone = args.remains().map(|zone| zone.to_string());
+    run_timezone(ctx, &Invocation::Prefix(msg), zone).await
+}
+
+pub async fn run_timezone(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    zone: Option<String>,
+) -> CommandResult {
+    let store = store::store(ctx).await;
+    let guild_id = u64::from(inv.guild_id().unwrap());
+    let user_id = u64::from(inv.user().id);
+
+    let reply = match zone {
+        None => format!(
+            "Your time zone is `{}`. The server default is `{}`.",
+            store.timezone_for(guild_id, user_id),
+            guild_timezone_name(&store, guild_id)
+        ),
+        Some(zone) if zone.eq_ignore_ascii_case("reset") => {
+            store.update_user(user_id, |settings| settings.timezone = None);
+            format!(
+                "Cleared your time zone. Dates you enter will use the server default, `{}`.",
+                guild_timezone_name(&store, guild_id)
+            )
+        }
+        Some(zone) => match timezone::parse_timezone(&zone) {
+            Some(tz) => {
+                store.update_user(user_id, |settings| {
+                    settings.timezone = Some(tz.name().to_string())
+                });
+                format!("Set your time zone to `{}`.", tz.name())
+            }
+            None => unknown_zone_error(&zone),
+        },
+    };
+
+    let _msg = inv.say(ctx, reply).await;
+    Ok(())
+}
+
+#[command]
+#[allowed_roles("corkboard")]
+#[description = "Sets the server's default time zone, used for anyone who hasn't set their own."]
+#[usage = "zone"]
+async fn guild_timezone(ctx: &Context, msg: &Message, args: Args) -> CommandResult {
+    let arg_names = vec!["Zone"];
+    if !validation::has_correct_arg_count(ctx, msg, 1, args.len(), arg_names, "guild_timezone")
+        .await
+    {
+        return Ok(());
+    }
+
+    let zone = args.rest().to_string();
+    run_guild_timezone(ctx, &Invocation::Prefix(msg), zone).await
+}
+
+pub async fn run_guild_timezone(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    zone: String,
+) -> CommandResult {
+    if !validation::has_corkboard_role(ctx, inv).await {
+        return Ok(());
+    }
+
+    let reply = match timezone::parse_timezone(&zone) {
+        Some(tz) => {
+            let store = store::store(ctx).await;
+            store.update_guild(inv.guild_id().unwrap().into(), |settings| {
+                settings.timezone = Some(tz.name().to_string())
+            });
+            format!("Set the server's default time zone to `{}`.", tz.name())
+        }
+        None => unknown_zone_error(&zone),
+    };
+
+    let _msg = inv.say(ctx, reply).await;
+    Ok(())
+}
+
+fn guild_timezone_name(store: &store::Store, guild_id: u64) -> String {
+    store
+        .guild(guild_id)
+        .timezone
+        .unwrap_or_else(|| timezone::DEFAULT_TIMEZONE.name().to_string())
+}
+
+fn unknown_zone_error(zone: &str) -> String {
+    format!(
+        ":bangbang: Error :bangbang: - `{}` is not a known time zone. Use a name from the tz database such as `America/New_York` or `Europe/Berlin`.",
+        zone
+    )
+}
@@ -0,0 +1,131 @@
+use std::env;
+use std::fs;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serenity::prelude::{Context, TypeMapKey};
+
+static DEFAULT_CONFIG_PATH: &str = "./corkboard.json";
+static DEFAULT_API_URL: &str = "http://localhost:8000/api/v1";
+static DEFAULT_API_TIMEOUT_SECS: u64 = 10;
+static DEFAULT_CACHE_TTL_SECS: u64 = 60;
+static DEFAULT_STATE_PATH: &str = "./corkboard-state.json";
+
+// Settings for talking to the Corkboard Server and for the bot's own local state. Values
+// are read from an optional JSON config file first and then overridden by any matching
+// environment variables.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    pub api_url: String,
+    pub api_timeout_secs: u64,
+    pub api_token: Option<String>,
+    pub api_key: Option<String>,
+    pub cache_ttl_secs: u64,
+    pub state_path: String,
+    // Address the calendar feed server listens on, e.g. `0.0.0.0:8080`. No feed without it.
+    pub feed_addr: Option<String>,
+    // Public base URL of the feed server, for the links handed out by `.calendar`.
+    pub feed_url: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            api_url: DEFAULT_API_URL.to_string(),
+            api_timeout_secs: DEFAULT_API_TIMEOUT_SECS,
+            api_token: None,
+            api_key: None,
+            cache_ttl_secs: DEFAULT_CACHE_TTL_SECS,
+            state_path: DEFAULT_STATE_PATH.to_string(),
+            feed_addr: None,
+            feed_url: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn load() -> Self {
+        let path = env::var("CORKBOARD_CONFIG").unwrap_or(DEFAULT_CONFIG_PATH.to_string());
+        let mut config = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str::<Config>(&contents)
+                .unwrap_or_else(|e| panic!("Unable to parse config file {}: {}", path, e)),
+            Err(_) => {
+                println!("No config file found at {}, using defaults.", path);
+                Config::default()
+            }
+        };
+
+        if let Ok(url) = env::var("CORKBOARD_API_URL") {
+            config.api_url = url;
+        }
+        if let Ok(timeout) = env::var("CORKBOARD_API_TIMEOUT_SECS") {
+            config.api_timeout_secs = timeout
+                .parse()
+                .expect("CORKBOARD_API_TIMEOUT_SECS must be a whole number of seconds!");
+        }
+        if let Ok(token) = env::var("CORKBOARD_API_TOKEN") {
+            config.api_token = Some(token);
+        }
+        if let Ok(key) = env::var("CORKBOARD_API_KEY") {
+            config.api_key = Some(key);
+        }
+        if let Ok(ttl) = env::var("CORKBOARD_CACHE_TTL_SECS") {
+            config.cache_ttl_secs = ttl
+                .parse()
+                .expect("CORKBOARD_CACHE_TTL_SECS must be a whole number of seconds!");
+        }
+        if let Ok(path) = env::var("CORKBOARD_STATE_PATH") {
+            config.state_path = path;
+        }
+        if let Ok(addr) = env::var("CORKBOARD_FEED_ADDR") {
+            config.feed_addr = Some(addr);
+        }
+        if let Ok(url) = env::var("CORKBOARD_FEED_URL") {
+            config.feed_url = Some(url);
+        }
+
+        config.api_url = config.api_url.trim_end_matches('/').to_string();
+        config
+    }
+
+    pub fn api_timeout(&self) -> Duration {
+        Duration::from_secs(self.api_timeout_secs)
+    }
+
+    pub fn cache_ttl(&self) -> Duration {
+        Duration::from_secs(self.cache_ttl_secs)
+    }
+
+    pub fn feed_addr(&self) -> Option<SocketAddr> {
+        self.feed_addr.as_ref().map(|addr| {
+            addr.parse()
+                .expect("CORKBOARD_FEED_ADDR must be an address like 0.0.0.0:8080!")
+        })
+    }
+
+    // Falls back to the listening address, which is only reachable on the same network.
+    pub fn feed_url(&self) -> Option<String> {
+        self.feed_url
+            .clone()
+            .or_else(|| {
+                self.feed_addr
+                    .as_ref()
+                    .map(|addr| format!("http://{}", addr))
+            })
+            .map(|url| url.trim_end_matches('/').to_string())
+    }
+}
+
+impl TypeMapKey for Config {
+    type Value = Arc<Config>;
+}
+
+pub async fn config(ctx: &Context) -> Arc<Config> {
+    let data = ctx.data.read().await;
+    data.get::<Config>()
+        .expect("Expected Config in the client data.")
+        .clone()
+}
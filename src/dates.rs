@@ -0,0 +1,548 @@
+use std::fmt;
+
+use chrono::{DateTime, Datelike, Duration, Months, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use chrono_tz::Tz;
+
+use crate::timezone;
+
+pub static ACCEPTED_FORMATS: &str = "Dates can be written as:
+- `12/1/2022 9:00AM` or `12/1/2022 21:00`
+- `2022-12-01 21:00` or `2022-12-01T21:00:00Z` (ISO 8601)
+- `Dec 1 2022 9pm` or `1 Dec 9pm`
+- `today 18:30`, `tomorrow 8pm`, `friday 7pm`, `next friday 19:00`
+- `8pm` (the next time it's 8pm) or `in 3 days`, `in 2 hours`
+The end can also be a duration from the start, like `2h`, `90m` or `1h30m`.";
+
+// Why a date couldn't be read. It displays as the error the bot replies with, and turns
+// into that text where a plain message is enough.
+#[derive(Debug, PartialEq)]
+pub enum DateError {
+    // Not written in any of the accepted formats.
+    Unrecognized(String),
+    // A duration that ends too far after the start to be stored.
+    OutOfRange(String),
+}
+
+impl fmt::Display for DateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DateError::Unrecognized(input) => write!(
+                f,
+                ":bangbang: Error :bangbang: - Couldn't understand the date `{}`.\n{}",
+                input, ACCEPTED_FORMATS
+            ),
+            DateError::OutOfRange(input) => write!(
+                f,
+                ":bangbang: Error :bangbang: - `{}` ends too far after the start.",
+                input
+            ),
+        }
+    }
+}
+
+impl From<DateError> for String {
+    fn from(error: DateError) -> Self {
+        error.to_string()
+    }
+}
+
+// Reads a start date in `tz` (unless it carries its own UTC offset) and returns it in UTC.
+// A bare time of day means the next time the clock shows it.
+pub fn parse_start(input: &str, tz: Tz, now: NaiveDateTime) -> Result<NaiveDateTime, DateError> {
+    let local_now = timezone::from_utc(tz, now);
+    parse(input, tz, local_now, local_now).ok_or_else(|| DateError::Unrecognized(input.to_string()))
+}
+
+// Like `parse_start`, but also accepts a duration after the start, and a bare time of day
+// means the first time the clock shows it after the start.
+pub fn parse_end(
+    input: &str,
+    start: NaiveDateTime,
+    tz: Tz,
+    now: NaiveDateTime,
+) -> Result<NaiveDateTime, DateError> {
+    if let Some(duration) = parse_duration(input) {
+        return start
+            .checked_add_signed(duration)
+            .ok_or_else(|| DateError::OutOfRange(input.to_string()));
+    }
+
+    let local_now = timezone::from_utc(tz, now);
+    let local_start = timezone::from_utc(tz, start);
+    parse(input, tz, local_now, local_start)
+        .ok_or_else(|| DateError::Unrecognized(input.to_string()))
+}
+
+// `now` anchors relative dates, `after` is the moment a bare time of day has to follow.
+fn parse(input: &str, tz: Tz, now: NaiveDateTime, after: NaiveDateTime) -> Option<NaiveDateTime> {
+    let input = input.trim();
+    if let Ok(time) = DateTime::parse_from_rfc3339(input) {
+        return Some(time.naive_utc());
+    }
+    for format in [
+        "%Y-%m-%dT%H:%M%:z",
+        "%Y-%m-%d %H:%M%:z",
+        "%Y-%m-%d %H:%M:%S%:z",
+    ] {
+        if let Ok(time) = DateTime::parse_from_str(input, format) {
+            return Some(time.naive_utc());
+        }
+    }
+
+    let normalized = input.to_lowercase().replace(',', " ");
+    let tokens: Vec<&str> = normalized.split_whitespace().collect();
+    parse_local(&tokens, now, after).map(|local| timezone::to_utc(tz, local))
+}
+
+fn parse_local(tokens: &[&str], now: NaiveDateTime, after: NaiveDateTime) -> Option<NaiveDateTime> {
+    if tokens.is_empty() {
+        return None;
+    }
+
+    if tokens[0] == "in" {
+        return parse_relative(&tokens[1..], now);
+    }
+
+    // A single token may be a full ISO date and time like `2022-12-01t21:00`.
+    if tokens.len() == 1 {
+        for format in ["%Y-%m-%dt%H:%M", "%Y-%m-%dt%H:%M:%S"] {
+            if let Ok(time) = NaiveDateTime::parse_from_str(tokens[0], format) {
+                return Some(time);
+            }
+        }
+    }
+
+    let tokens: Vec<&str> = tokens
+        .iter()
+        .copied()
+        .filter(|token| *token != "at" && *token != "on")
+        .collect();
+
+    // Try every split of the tokens into a date part and a time part, in either order.
+    for split in 0..=tokens.len() {
+        let (first, second) = tokens.split_at(split);
+        for (date_part, time_part) in [(first, second), (second, first)] {
+            let date = if date_part.is_empty() {
+                None
+            } else {
+                match parse_date(date_part, now.date()) {
+                    Some(date) => Some(date),
+                    None => continue,
+                }
+            };
+            let time = if time_part.is_empty() {
+                None
+            } else {
+                match parse_time(time_part) {
+                    Some(time) => Some(time),
+                    None => continue,
+                }
+            };
+
+            match (date, time) {
+                (Some(date), Some(time)) => return Some(date.and_time(time)),
+                (Some(date), None) => return Some(date.and_time(NaiveTime::MIN)),
+                (None, Some(time)) => {
+                    let candidate = after.date().and_time(time);
+                    return Some(if candidate <= after {
+                        candidate + Duration::days(1)
+                    } else {
+                        candidate
+                    });
+                }
+                (None, None) => {}
+            }
+        }
+    }
+
+    None
+}
+
+// `in 3 days`, `in 2 hours`, optionally followed by a time of day for day-sized units.
+fn parse_relative(tokens: &[&str], now: NaiveDateTime) -> Option<NaiveDateTime> {
+    let (duration, rest) = match tokens {
+        [amount, unit, rest @ ..] => match unit_duration(amount, unit) {
+            Some(duration) => (duration, rest),
+            None => (parse_duration(&format!("{}{}", amount, unit))?, rest),
+        },
+        [compact] => (parse_duration(compact)?, &[][..]),
+        _ => return None,
+    };
+
+    let shifted = now.checked_add_signed(duration)?;
+    if rest.is_empty() {
+        return Some(shifted);
+    }
+    let rest: Vec<&str> = rest.iter().copied().filter(|t| *t != "at").collect();
+    parse_time(&rest).map(|time| shifted.date().and_time(time))
+}
+
+fn unit_duration(amount: &str, unit: &str) -> Option<Duration> {
+    let amount: i64 = match amount {
+        "a" | "an" | "one" => 1,
+        _ => amount.parse().ok()?,
+    };
+    // Amounts too big for a duration are turned down rather than overflowing.
+    match unit.trim_end_matches('s') {
+        "min" | "minute" => Duration::try_minutes(amount),
+        "hr" | "hour" => Duration::try_hours(amount),
+        "day" => Duration::try_days(amount),
+        "week" | "wk" => Duration::try_weeks(amount),
+        _ => None,
+    }
+}
+
+// Compact durations such as `2h`, `90m`, `1h30m`, `1d`, optionally prefixed with `+` or
+// `for`, and spelled-out ones such as `2 hours` or `for 45 minutes`.
+pub fn parse_duration(input: &str) -> Option<Duration> {
+    let input = input.trim().to_lowercase();
+    let input = input
+        .strip_prefix("for ")
+        .or_else(|| input.strip_prefix('+'))
+        .unwrap_or(&input)
+        .trim()
+        .to_string();
+
+    let words: Vec<&str> = input.split_whitespace().collect();
+    if let [amount, unit] = words.as_slice() {
+        return unit_duration(amount, unit);
+    }
+
+    let mut total = Duration::zero();
+    let mut digits = String::new();
+    let mut matched = false;
+    for c in input.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let amount: i64 = digits.parse().ok()?;
+        digits.clear();
+        let part = match c {
+            'w' => Duration::try_weeks(amount),
+            'd' => Duration::try_days(amount),
+            'h' => Duration::try_hours(amount),
+            'm' => Duration::try_minutes(amount),
+            _ => return None,
+        };
+        total = total.checked_add(&part?)?;
+        matched = true;
+    }
+
+    if matched && digits.is_empty() && total > Duration::zero() {
+        Some(total)
+    } else {
+        None
+    }
+}
+
+// The first day of a month written as `december`, `dec 2024`, `12/2024`, `2024-12`, or
+// `this month`, `next month`, `last month`. A month name alone is in the current year.
+pub fn parse_year_month(input: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let input = input.trim().to_lowercase();
+    let tokens: Vec<&str> = input.split_whitespace().collect();
+    let this_month = today.with_day(1)?;
+    match tokens.as_slice() {
+        ["this", "month"] => Some(this_month),
+        ["next", "month"] => this_month.checked_add_months(Months::new(1)),
+        ["last", "month"] => this_month.checked_sub_months(Months::new(1)),
+        [month] if parse_month(month).is_some() => {
+            NaiveDate::from_ymd_opt(today.year(), parse_month(month)?, 1)
+        }
+        [month, year] => NaiveDate::from_ymd_opt(year.parse().ok()?, parse_month(month)?, 1),
+        [numeric] => {
+            let (first, second) = numeric
+                .split_once('/')
+                .or_else(|| numeric.split_once('-'))?;
+            let (year, month) = if first.len() == 4 {
+                (first, second)
+            } else {
+                (second, first)
+            };
+            NaiveDate::from_ymd_opt(year.parse().ok()?, month.parse().ok()?, 1)
+        }
+        _ => None,
+    }
+}
+
+fn parse_date(tokens: &[&str], today: NaiveDate) -> Option<NaiveDate> {
+    match tokens {
+        ["today"] | ["tonight"] => Some(today),
+        ["tomorrow"] => today.succ_opt(),
+        ["next", day] => parse_weekday(day).map(|day| next_weekday(today, day, false)),
+        ["this", day] => parse_weekday(day).map(|day| next_weekday(today, day, true)),
+        [single] => parse_weekday(single)
+            .map(|day| next_weekday(today, day, true))
+            .or_else(|| parse_numeric_date(single, today)),
+        [month, day] => {
+            month_day(month, day, None, today).or_else(|| month_day(day, month, None, today))
+        }
+        [month, day, year] => month_day(month, day, Some(year), today)
+            .or_else(|| month_day(day, month, Some(year), today)),
+        _ => None,
+    }
+}
+
+// `12/1/2022`, `12/1` (the next such date), `2022-12-01`.
+fn parse_numeric_date(token: &str, today: NaiveDate) -> Option<NaiveDate> {
+    for format in ["%m/%d/%Y", "%Y-%m-%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(token, format) {
+            return Some(date);
+        }
+    }
+
+    let (month, day) = token.split_once('/')?;
+    upcoming(month.parse().ok()?, day.parse().ok()?, today)
+}
+
+fn month_day(month: &str, day: &str, year: Option<&&str>, today: NaiveDate) -> Option<NaiveDate> {
+    let month = parse_month(month)?;
+    let day: u32 = day
+        .trim_end_matches("st")
+        .trim_end_matches("nd")
+        .trim_end_matches("rd")
+        .trim_end_matches("th")
+        .parse()
+        .ok()?;
+    match year {
+        Some(year) => NaiveDate::from_ymd_opt(year.parse().ok()?, month, day),
+        None => upcoming(month, day, today),
+    }
+}
+
+// The next time a month and day comes around, counting today.
+fn upcoming(month: u32, day: u32, today: NaiveDate) -> Option<NaiveDate> {
+    let this_year = NaiveDate::from_ymd_opt(today.year(), month, day)?;
+    if this_year >= today {
+        Some(this_year)
+    } else {
+        NaiveDate::from_ymd_opt(today.year() + 1, month, day)
+    }
+}
+
+fn parse_month(token: &str) -> Option<u32> {
+    let months = [
+        "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+    ];
+    if token.len() < 3 {
+        return None;
+    }
+    months
+        .iter()
+        .position(|month| token.starts_with(month))
+        .map(|index| index as u32 + 1)
+}
+
+pub fn parse_weekday(token: &str) -> Option<Weekday> {
+    let day = match token.get(..3)? {
+        "mon" => Weekday::Mon,
+        "tue" => Weekday::Tue,
+        "wed" => Weekday::Wed,
+        "thu" => Weekday::Thu,
+        "fri" => Weekday::Fri,
+        "sat" => Weekday::Sat,
+        "sun" => Weekday::Sun,
+        _ => return None,
+    };
+    Some(day)
+}
+
+// `friday` is the coming Friday, today included; `next friday` always skips today.
+fn next_weekday(today: NaiveDate, day: Weekday, include_today: bool) -> NaiveDate {
+    let mut days_ahead =
+        (7 + day.num_days_from_monday() as i64 - today.weekday().num_days_from_monday() as i64) % 7;
+    if days_ahead == 0 && !include_today {
+        days_ahead = 7;
+    }
+    today + Duration::days(days_ahead)
+}
+
+// `9:00AM`, `9am`, `9 pm`, `21:00`, `21:00:00`, `noon`, `midnight`.
+fn parse_time(tokens: &[&str]) -> Option<NaiveTime> {
+    let joined = tokens.concat();
+    match joined.as_str() {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return Some(NaiveTime::MIN),
+        _ => {}
+    }
+
+    let (clock, meridiem) = if let Some(clock) = joined.strip_suffix("am") {
+        (clock, Some(false))
+    } else if let Some(clock) = joined.strip_suffix("pm") {
+        (clock, Some(true))
+    } else {
+        (joined.as_str(), None)
+    };
+
+    let mut parts = clock.split(':');
+    let hour: u32 = parts.next()?.parse().ok()?;
+    let minute: u32 = match parts.next() {
+        Some(minute) => minute.parse().ok()?,
+        // A bare number is only a time with am/pm, otherwise it could be a day.
+        None if meridiem.is_some() => 0,
+        None => return None,
+    };
+    let second: u32 = match parts.next() {
+        Some(second) => second.parse().ok()?,
+        None => 0,
+    };
+    if parts.next().is_some() {
+        return None;
+    }
+
+    let hour = match meridiem {
+        Some(_) if hour == 0 || hour > 12 => return None,
+        Some(false) => hour % 12,
+        Some(true) => hour % 12 + 12,
+        None => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, second)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::America::Los_Angeles;
+    use chrono_tz::UTC;
+
+    use super::*;
+
+    // A Wednesday morning.
+    fn now() -> NaiveDateTime {
+        at(2024, 12, 4, 10, 0)
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn parses_relative_dates() {
+        let start = |input| parse_start(input, UTC, now()).unwrap();
+        assert_eq!(start("today 18:30"), at(2024, 12, 4, 18, 30));
+        assert_eq!(start("tomorrow 8pm"), at(2024, 12, 5, 20, 0));
+        assert_eq!(start("friday 7pm"), at(2024, 12, 6, 19, 0));
+        assert_eq!(start("wednesday 7pm"), at(2024, 12, 4, 19, 0));
+        assert_eq!(start("next wednesday 7pm"), at(2024, 12, 11, 19, 0));
+        assert_eq!(start("in 3 days"), at(2024, 12, 7, 10, 0));
+        assert_eq!(start("in 2 hours"), at(2024, 12, 4, 12, 0));
+        assert_eq!(start("in 1h30m"), at(2024, 12, 4, 11, 30));
+        assert_eq!(start("in 2 days at 9am"), at(2024, 12, 6, 9, 0));
+    }
+
+    #[test]
+    fn bare_time_is_the_next_time_the_clock_shows_it() {
+        let start = |input| parse_start(input, UTC, now()).unwrap();
+        assert_eq!(start("8pm"), at(2024, 12, 4, 20, 0));
+        assert_eq!(start("9am"), at(2024, 12, 5, 9, 0));
+        assert_eq!(start("noon"), at(2024, 12, 4, 12, 0));
+    }
+
+    #[test]
+    fn parses_absolute_dates() {
+        let start = |input| parse_start(input, UTC, now()).unwrap();
+        assert_eq!(start("12/1/2022 9:00AM"), at(2022, 12, 1, 9, 0));
+        assert_eq!(start("12/1/2022 21:00"), at(2022, 12, 1, 21, 0));
+        assert_eq!(start("Dec 1 2022 9pm"), at(2022, 12, 1, 21, 0));
+        // Without a year, a date that has passed means next year's.
+        assert_eq!(start("1 Dec 9pm"), at(2025, 12, 1, 21, 0));
+        assert_eq!(start("12/25 noon"), at(2024, 12, 25, 12, 0));
+    }
+
+    #[test]
+    fn parses_iso_dates() {
+        let start = |input| parse_start(input, UTC, now()).unwrap();
+        assert_eq!(start("2022-12-01 21:00"), at(2022, 12, 1, 21, 0));
+        assert_eq!(start("2022-12-01T21:00"), at(2022, 12, 1, 21, 0));
+        assert_eq!(start("2022-12-01T21:00:00Z"), at(2022, 12, 1, 21, 0));
+        assert_eq!(start("2022-12-01T21:00:00+02:00"), at(2022, 12, 1, 19, 0));
+    }
+
+    #[test]
+    fn local_dates_are_converted_to_utc() {
+        // An explicit offset wins over the guild's time zone.
+        let parse = |input| parse_start(input, Los_Angeles, now()).unwrap();
+        assert_eq!(parse("2022-12-01 21:00"), at(2022, 12, 2, 5, 0));
+        assert_eq!(parse("2022-12-01T21:00:00Z"), at(2022, 12, 1, 21, 0));
+    }
+
+    #[test]
+    fn rejects_unrecognized_dates() {
+        assert!(parse_start("", UTC, now()).is_err());
+        assert!(parse_start("someday", UTC, now()).is_err());
+        assert!(parse_start("13/45/2022", UTC, now()).is_err());
+        assert!(parse_start("25:00", UTC, now()).is_err());
+        assert!(parse_start("13pm", UTC, now()).is_err());
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("2h"), Some(Duration::hours(2)));
+        assert_eq!(parse_duration("90m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("+1d"), Some(Duration::days(1)));
+        assert_eq!(parse_duration("1w"), Some(Duration::weeks(1)));
+        assert_eq!(
+            parse_duration("for 45 minutes"),
+            Some(Duration::minutes(45))
+        );
+        assert_eq!(parse_duration("2 hours"), Some(Duration::hours(2)));
+        assert_eq!(parse_duration("an hour"), Some(Duration::hours(1)));
+        assert_eq!(parse_duration("0h"), None);
+        assert_eq!(parse_duration("2"), None);
+        assert_eq!(parse_duration("2x"), None);
+        assert_eq!(parse_duration("h"), None);
+    }
+
+    #[test]
+    fn end_can_be_a_duration_or_a_time_after_the_start() {
+        let start = at(2024, 12, 4, 22, 0);
+        let end = |input| parse_end(input, start, UTC, now()).unwrap();
+        assert_eq!(end("2h"), at(2024, 12, 5, 0, 0));
+        assert_eq!(end("1h30m"), at(2024, 12, 4, 23, 30));
+        // A bare time follows the start, not now.
+        assert_eq!(end("11pm"), at(2024, 12, 4, 23, 0));
+        assert_eq!(end("1am"), at(2024, 12, 5, 1, 0));
+        assert_eq!(end("2024-12-06 18:00"), at(2024, 12, 6, 18, 0));
+    }
+
+    #[test]
+    fn parses_months() {
+        let today = NaiveDate::from_ymd_opt(2024, 12, 4).unwrap();
+        let month = |year, month| NaiveDate::from_ymd_opt(year, month, 1);
+        assert_eq!(parse_year_month("this month", today), month(2024, 12));
+        assert_eq!(parse_year_month("next month", today), month(2025, 1));
+        assert_eq!(parse_year_month("last month", today), month(2024, 11));
+        assert_eq!(parse_year_month("march", today), month(2024, 3));
+        assert_eq!(parse_year_month("dec 2023", today), month(2023, 12));
+        assert_eq!(parse_year_month("12/2023", today), month(2023, 12));
+        assert_eq!(parse_year_month("2023-12", today), month(2023, 12));
+        assert_eq!(parse_year_month("smarch", today), None);
+    }
+
+    #[test]
+    fn errors_say_whether_the_date_was_understood() {
+        assert_eq!(
+            parse_start("someday", UTC, now()),
+            Err(DateError::Unrecognized("someday".to_string()))
+        );
+        assert_eq!(
+            parse_end("9999999999h", now(), UTC, now()),
+            Err(DateError::OutOfRange("9999999999h".to_string()))
+        );
+        assert!(
+            String::from(DateError::Unrecognized("someday".to_string())).contains(ACCEPTED_FORMATS)
+        );
+    }
+
+    #[test]
+    fn huge_durations_are_rejected_instead_of_overflowing() {
+        assert_eq!(parse_duration("99999999999999w"), None);
+        assert!(parse_end("9999999999d", now(), UTC, now()).is_err());
+        assert!(parse_end("9999999999h", now(), UTC, now()).is_err());
+        assert!(parse_start("in 9999999999 days", UTC, now()).is_err());
+        assert!(parse_start("in 99999999999999999999 days", UTC, now()).is_err());
+    }
+}
@@ -0,0 +1,157 @@
+use std::error::Error;
+use std::fmt;
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use serenity::builder::CreateEmbed;
+use serenity::prelude::*;
+use serenity::utils::Colour;
+
+use crate::commands::invocation::{Invocation, Reply};
+
+// Errors that can come out of talking to the Corkboard Server. Each kind gets its own
+// user-facing message so a command never has to panic or leak raw error text.
+#[derive(Debug)]
+pub enum BotError {
+    // The request never got a response: connection refused, DNS failure, timeout...
+    Unreachable {
+        url: String,
+        source: reqwest::Error,
+    },
+    // The server answered with a 4xx or 5xx status.
+    Status {
+        url: String,
+        status: StatusCode,
+        body: String,
+    },
+    // The server answered successfully but the payload didn't match what we expected.
+    Malformed {
+        url: String,
+        reason: String,
+    },
+    // The circuit breaker is open after repeated failures, so the call was never made.
+    Unavailable {
+        retry_after: Duration,
+    },
+}
+
+impl BotError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            BotError::Unreachable { .. } => "unreachable",
+            BotError::Status { .. } => "status",
+            BotError::Malformed { .. } => "malformed",
+            BotError::Unavailable { .. } => "unavailable",
+        }
+    }
+
+    // Failures that say the server is down or struggling rather than that our request
+    // was wrong. These are worth retrying and count towards the circuit breaker.
+    pub fn is_transient(&self) -> bool {
+        match self {
+            BotError::Unreachable { .. } => true,
+            BotError::Status { status, .. } => status.is_server_error(),
+            _ => false,
+        }
+    }
+
+    pub fn user_message(&self) -> String {
+        match self {
+            BotError::Unreachable { .. } => {
+                "Couldn't reach the Corkboard Server. Please try again in a bit.".to_string()
+            }
+            BotError::Status { status, .. } if status.is_client_error() => {
+                format!("The Corkboard Server rejected the request ({}).", status)
+            }
+            BotError::Status { status, .. } => format!(
+                "The Corkboard Server ran into a problem ({}). Please try again later.",
+                status
+            ),
+            BotError::Malformed { .. } => {
+                "The Corkboard Server sent back data the bot couldn't understand.".to_string()
+            }
+            BotError::Unavailable { retry_after } => format!(
+                "The corkboard is temporarily unavailable. Please try again in about {} seconds.",
+                retry_after.as_secs().max(1)
+            ),
+        }
+    }
+
+    // Single-line key=value log entry so failures are easy to grep for.
+    pub fn log(&self, command_name: &str) {
+        match self {
+            BotError::Unreachable { url, source } => println!(
+                "backend_error kind={} command={} url={} detail={:?}",
+                self.kind(),
+                command_name,
+                url,
+                source.to_string()
+            ),
+            BotError::Status { url, status, body } => println!(
+                "backend_error kind={} command={} url={} status={} body={:?}",
+                self.kind(),
+                command_name,
+                url,
+                status.as_u16(),
+                body
+            ),
+            BotError::Malformed { url, reason } => println!(
+                "backend_error kind={} command={} url={} detail={:?}",
+                self.kind(),
+                command_name,
+                url,
+                reason
+            ),
+            BotError::Unavailable { retry_after } => println!(
+                "backend_error kind={} command={} retry_after_secs={}",
+                self.kind(),
+                command_name,
+                retry_after.as_secs()
+            ),
+        }
+    }
+
+    pub async fn report(&self, ctx: &Context, inv: &Invocation<'_>, command_name: &str) {
+        self.log(command_name);
+        let mut embed = CreateEmbed::default();
+        embed
+            .title(":bangbang: Error :bangbang:")
+            .description(self.user_message())
+            .color(Colour::RED);
+        let _msg = inv.reply(ctx, Reply::embed(embed)).await;
+    }
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotError::Unreachable { url, source } => {
+                write!(f, "Corkboard Server unreachable at {}: {}", url, source)
+            }
+            BotError::Status { url, status, .. } => {
+                write!(f, "Corkboard Server returned {} for {}", status, url)
+            }
+            BotError::Malformed { url, reason } => {
+                write!(
+                    f,
+                    "Malformed Corkboard Server payload from {}: {}",
+                    url, reason
+                )
+            }
+            BotError::Unavailable { retry_after } => write!(
+                f,
+                "Corkboard Server circuit breaker open for another {:?}",
+                retry_after
+            ),
+        }
+    }
+}
+
+impl Error for BotError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            BotError::Unreachable { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
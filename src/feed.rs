@@ -0,0 +1,78 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+
+use crate::api::client::CorkboardClient;
+use crate::ics;
+use crate::store::Store;
+
+// Serves each guild's events as an iCalendar feed at `/calendar/<guild_id>/<token>.ics`,
+// so calendar apps can subscribe to them. The token is a per-guild secret handed out by
+// `.calendar`; without it anyone who knew a guild ID could read its events.
+pub async fn serve(addr: SocketAddr, client: Arc<CorkboardClient>, store: Arc<Store>) {
+    let make_service = make_service_fn(move |_| {
+        let client = client.clone();
+        let store = store.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let client = client.clone();
+                let store = store.clone();
+                async move { Ok::<_, Infallible>(handle(request, &client, &store).await) }
+            }))
+        }
+    });
+
+    println!("Serving calendar feeds on http://{}", addr);
+    if let Err(why) = Server::bind(&addr).serve(make_service).await {
+        println!("Calendar feed server stopped: {:?}", why);
+    }
+}
+
+async fn handle(request: Request<Body>, client: &CorkboardClient, store: &Store) -> Response<Body> {
+    if request.method() != Method::GET {
+        return plain(StatusCode::METHOD_NOT_ALLOWED, "Only GET is supported.");
+    }
+
+    let guild_id = match guild_for_path(request.uri().path(), store) {
+        Some(guild_id) => guild_id,
+        None => return plain(StatusCode::NOT_FOUND, "No such calendar."),
+    };
+
+    match client.list_current_events(guild_id as i64).await {
+        Ok(events) => Response::builder()
+            .header(CONTENT_TYPE, "text/calendar; charset=utf-8")
+            .body(Body::from(ics::calendar(
+                &events,
+                store.guild_timezone(guild_id),
+            )))
+            .unwrap(),
+        Err(why) => {
+            why.log("calendar feed");
+            plain(StatusCode::BAD_GATEWAY, &why.user_message())
+        }
+    }
+}
+
+// The guild a feed path belongs to, if its token is the guild's current one.
+fn guild_for_path(path: &str, store: &Store) -> Option<u64> {
+    let rest = path.strip_prefix("/calendar/")?.strip_suffix(".ics")?;
+    let (guild_id, token) = rest.split_once('/')?;
+    let guild_id: u64 = guild_id.parse().ok()?;
+    let expected = store.guild(guild_id).feed_token?;
+    if token == expected {
+        Some(guild_id)
+    } else {
+        None
+    }
+}
+
+fn plain(status: StatusCode, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(message.to_string()))
+        .unwrap()
+}
@@ -0,0 +1,599 @@
+use std::collections::HashMap;
+
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Utc};
+use chrono_tz::Tz;
+
+use crate::commands::events::{Event, NewEvent};
+use crate::recurrence::Recurrence;
+use crate::timezone;
+
+static PRODUCT_ID: &str = "-//Corkboard Bot//Events//EN";
+static CALENDAR_NAME: &str = "Corkboard Events";
+// RFC 5545 lines are folded once they reach 75 octets.
+static MAX_LINE_LEN: usize = 75;
+
+// Builds an iCalendar file holding `events`. One-off events are written in UTC. Recurring
+// events are written in the guild's zone `tz` instead, because their rule repeats in
+// local time; a weekly 7pm event written in UTC would move by an hour at each daylight
+// saving change, and could even land on the wrong weekday.
+pub fn calendar(events: &[Event], tz: Tz) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:{}", PRODUCT_ID),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", CALENDAR_NAME),
+    ];
+    let stamp = utc_stamp(Utc::now().naive_utc());
+    for event in events {
+        lines.extend(vevent(event, tz, &stamp));
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut ics = String::new();
+    for line in lines {
+        ics.push_str(&fold(&line));
+    }
+    ics
+}
+
+fn vevent(event: &Event, tz: Tz, stamp: &str) -> Vec<String> {
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}@corkboard", event.id),
+        format!("DTSTAMP:{}", stamp),
+    ];
+
+    match event.recurrence(tz) {
+        Some(recurrence) => {
+            let local = |utc| timezone::from_utc(tz, utc).format("%Y%m%dT%H%M%S");
+            lines.push(format!(
+                "DTSTART;TZID={}:{}",
+                tz.name(),
+                local(event.start_date)
+            ));
+            lines.push(format!(
+                "DTEND;TZID={}:{}",
+                tz.name(),
+                local(event.end_date)
+            ));
+            lines.push(format!("RRULE:{}", rrule(&recurrence, tz)));
+            let start_time = timezone::from_utc(tz, event.start_date).time();
+            for date in &event.exceptions {
+                lines.push(format!(
+                    "EXDATE;TZID={}:{}",
+                    tz.name(),
+                    date.and_time(start_time).format("%Y%m%dT%H%M%S")
+                ));
+            }
+        }
+        None => {
+            lines.push(format!("DTSTART:{}", utc_stamp(event.start_date)));
+            lines.push(format!("DTEND:{}", utc_stamp(event.end_date)));
+        }
+    }
+
+    lines.push(format!("SUMMARY:{}", escape(&event.title)));
+    if !event.description.is_empty() {
+        lines.push(format!("DESCRIPTION:{}", escape(&event.description)));
+    }
+    if !event.url.is_empty() {
+        lines.push(format!("URL:{}", event.url));
+    }
+    lines.push("END:VEVENT".to_string());
+    lines
+}
+
+// The stored rule ends on a local date, but with a date-time DTSTART iCalendar wants
+// UNTIL as the UTC moment that date ends.
+fn rrule(recurrence: &Recurrence, tz: Tz) -> String {
+    let mut recurrence = recurrence.clone();
+    let until = recurrence.until.take();
+    let mut rule = recurrence.to_rrule();
+    if let Some(until) = until {
+        let end_of_day = until.and_time(NaiveTime::MIN) + Duration::days(1) - Duration::seconds(1);
+        rule.push_str(&format!(
+            ";UNTIL={}",
+            utc_stamp(timezone::to_utc(tz, end_of_day))
+        ));
+    }
+    rule
+}
+
+fn utc_stamp(utc: NaiveDateTime) -> String {
+    utc.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// Text values escape backslashes, separators and newlines.
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+// Splits a content line into CRLF-terminated pieces of at most 75 octets, each
+// continuation starting with a space. Never splits inside a UTF-8 character.
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE_LEN {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+// What came out of reading an iCalendar file: the events worth creating, plus one note for
+// each VEVENT that was skipped or only partly understood.
+pub struct Import {
+    pub events: Vec<NewEvent>,
+    pub notes: Vec<String>,
+}
+
+// Reads every VEVENT in `ics` as a new event for `guild_id`. Times without a zone of
+// their own are read in `tz`, and events that have already ended are left out.
+pub fn parse_events(ics: &str, guild_id: i64, tz: Tz) -> Import {
+    let mut import = Import {
+        events: Vec::new(),
+        notes: Vec::new(),
+    };
+    let now = Utc::now().naive_utc();
+
+    let mut current: Option<HashMap<String, Property>> = None;
+    // Components nested in an event, such as VALARM, whose properties aren't the event's.
+    let mut nested = 0;
+    for line in unfold(ics) {
+        let property = match Property::parse(&line) {
+            Some(property) => property,
+            None => continue,
+        };
+        match (
+            property.name.as_str(),
+            property.value.to_uppercase().as_str(),
+        ) {
+            ("BEGIN", "VEVENT") => current = Some(HashMap::new()),
+            ("BEGIN", _) if current.is_some() => nested += 1,
+            ("END", "VEVENT") => {
+                if let Some(properties) = current.take() {
+                    match new_event(&properties, guild_id, tz, now) {
+                        Ok(Some(event)) => import.events.push(event),
+                        Ok(None) => {}
+                        Err(note) => import.notes.push(note),
+                    }
+                }
+                nested = 0;
+            }
+            ("END", _) if nested > 0 => nested -= 1,
+            _ => {
+                if let (Some(properties), 0) = (current.as_mut(), nested) {
+                    properties.entry(property.name.clone()).or_insert(property);
+                }
+            }
+        }
+    }
+
+    import.events.sort_by_key(|event| event.start_date);
+    import
+}
+
+// Ok(None) for events that are simply not worth importing (cancelled or already over).
+fn new_event(
+    properties: &HashMap<String, Property>,
+    guild_id: i64,
+    tz: Tz,
+    now: NaiveDateTime,
+) -> Result<Option<NewEvent>, String> {
+    let text = |name: &str| {
+        properties
+            .get(name)
+            .map(|property| unescape(&property.value))
+            .unwrap_or_default()
+    };
+    let title = match text("SUMMARY") {
+        title if title.is_empty() => "Untitled event".to_string(),
+        title => title,
+    };
+    if text("STATUS").eq_ignore_ascii_case("CANCELLED") {
+        return Ok(None);
+    }
+
+    let start = properties
+        .get("DTSTART")
+        .ok_or_else(|| format!("`{}` has no start time, so it was skipped.", title))?;
+    let (start_date, all_day) = start
+        .date_time(tz)
+        .ok_or_else(|| format!("`{}` has a start time that couldn't be read.", title))?;
+    let end_date = match (properties.get("DTEND"), properties.get("DURATION")) {
+        (Some(end), _) => {
+            end.date_time(tz)
+                .ok_or_else(|| format!("`{}` has an end time that couldn't be read.", title))?
+                .0
+        }
+        (None, Some(duration)) => parse_ics_duration(&duration.value)
+            .and_then(|duration| start_date.checked_add_signed(duration))
+            .ok_or_else(|| format!("`{}` has a duration that couldn't be read.", title))?,
+        // iCalendar's defaults: an all-day event lasts the day, anything else is a moment.
+        (None, None) if all_day => start_date + Duration::days(1),
+        (None, None) => start_date,
+    };
+
+    let mut recurrence = None;
+    if let Some(rule) = properties.get("RRULE") {
+        // Stored rules repeat in the guild's zone, so that's the zone UNTIL is read in.
+        match Recurrence::parse(&rule.value, tz) {
+            Ok(rule) => recurrence = Some(rule.to_rrule()),
+            Err(_) => {
+                return Err(format!(
+                    "`{}` repeats in a way the bot doesn't support (`{}`), so it was skipped.",
+                    title, rule.value
+                ))
+            }
+        }
+    }
+    if recurrence.is_none() && end_date < now {
+        return Ok(None);
+    }
+
+    let mut event = NewEvent::new(
+        guild_id,
+        title,
+        text("URL"),
+        text("DESCRIPTION"),
+        start_date,
+        end_date,
+    );
+    event.recurrence = recurrence;
+    Ok(Some(event))
+}
+
+struct Property {
+    name: String,
+    params: HashMap<String, String>,
+    value: String,
+}
+
+impl Property {
+    // `NAME;PARAM=VALUE;...:value`. Parameter values may be quoted and contain `:` or `;`.
+    fn parse(line: &str) -> Option<Property> {
+        let mut in_quotes = false;
+        let mut split = None;
+        for (i, c) in line.char_indices() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                ':' if !in_quotes => {
+                    split = Some(i);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let (head, value) = line.split_at(split?);
+        let mut parts = head.split(';');
+        let name = parts.next()?.trim().to_uppercase();
+        let params = parts
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+            .collect();
+
+        Some(Property {
+            name,
+            params,
+            value: value[1..].to_string(),
+        })
+    }
+
+    // The UTC time of a DTSTART/DTEND and whether it's an all-day date. Zones that aren't
+    // IANA names (Outlook writes Windows ones) fall back to `tz`.
+    fn date_time(&self, tz: Tz) -> Option<(NaiveDateTime, bool)> {
+        let zone = self
+            .params
+            .get("TZID")
+            .and_then(|name| timezone::parse_timezone(name.trim_start_matches('/')))
+            .unwrap_or(tz);
+        let value = self.value.trim();
+
+        if self.params.get("VALUE").map(String::as_str) == Some("DATE") || value.len() == 8 {
+            let date = NaiveDate::parse_from_str(value, "%Y%m%d").ok()?;
+            return Some((timezone::to_utc(zone, date.and_time(NaiveTime::MIN)), true));
+        }
+        if let Some(utc) = value.strip_suffix('Z') {
+            let utc = NaiveDateTime::parse_from_str(utc, "%Y%m%dT%H%M%S").ok()?;
+            return Some((utc, false));
+        }
+        let local = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?;
+        Some((timezone::to_utc(zone, local), false))
+    }
+}
+
+// Joins folded lines back together; a line starting with a space or tab continues the
+// previous one.
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        match (
+            line.strip_prefix(' ').or_else(|| line.strip_prefix('\t')),
+            lines.last_mut(),
+        ) {
+            (Some(rest), Some(previous)) => previous.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape(text: &str) -> String {
+    let mut unescaped = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => {}
+        }
+    }
+    unescaped
+}
+
+// `PT1H30M`, `P1D`, `P2W`, `P1DT12H`.
+fn parse_ics_duration(value: &str) -> Option<Duration> {
+    let value = value.trim().trim_start_matches('+');
+    let rest = value.strip_prefix('P')?;
+    let mut total = Duration::zero();
+    let mut digits = String::new();
+    for c in rest.chars() {
+        match c {
+            'T' => continue,
+            '0'..='9' => {
+                digits.push(c);
+                continue;
+            }
+            _ => {}
+        }
+        let amount: i64 = digits.parse().ok()?;
+        digits.clear();
+        // Amounts too big for a duration make the whole value unreadable.
+        let part = match c {
+            'W' => Duration::try_weeks(amount),
+            'D' => Duration::try_days(amount),
+            'H' => Duration::try_hours(amount),
+            'M' => Duration::try_minutes(amount),
+            'S' => Duration::try_seconds(amount),
+            _ => return None,
+        };
+        total = total.checked_add(&part?)?;
+    }
+    Some(total)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono_tz::America::Los_Angeles;
+    use chrono_tz::Europe::Berlin;
+    use chrono_tz::UTC;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(year, month, day)
+            .unwrap()
+            .and_hms_opt(hour, minute, 0)
+            .unwrap()
+    }
+
+    // The content lines of `ics` with folding undone.
+    fn content_lines(ics: &str) -> Vec<String> {
+        ics.replace("\r\n ", "")
+            .split("\r\n")
+            .map(str::to_string)
+            .collect()
+    }
+
+    fn vcalendar(vevents: &[&str]) -> String {
+        let mut ics = "BEGIN:VCALENDAR\r\nVERSION:2.0\r\n".to_string();
+        for vevent in vevents {
+            ics.push_str("BEGIN:VEVENT\r\n");
+            ics.push_str(vevent);
+            ics.push_str("END:VEVENT\r\n");
+        }
+        ics.push_str("END:VCALENDAR\r\n");
+        ics
+    }
+
+    fn parse_one(vevent: &str, tz: Tz) -> NewEvent {
+        let mut import = parse_events(&vcalendar(&[vevent]), 1, tz);
+        assert!(import.notes.is_empty(), "{:?}", import.notes);
+        assert_eq!(import.events.len(), 1);
+        import.events.remove(0)
+    }
+
+    #[test]
+    fn folds_long_lines_at_75_octets() {
+        let line = format!("DESCRIPTION:{}", "a".repeat(200));
+        let folded = fold(&line);
+        assert!(folded.ends_with("\r\n"));
+        for piece in folded.trim_end_matches("\r\n").split("\r\n") {
+            assert!(piece.len() <= MAX_LINE_LEN);
+        }
+        assert_eq!(content_lines(&folded)[0], line);
+        assert_eq!(fold("SUMMARY:Short"), "SUMMARY:Short\r\n");
+    }
+
+    #[test]
+    fn folding_never_splits_a_character() {
+        let line = format!("SUMMARY:{}", "é🎲".repeat(40));
+        let folded = fold(&line);
+        for piece in folded.trim_end_matches("\r\n").split("\r\n") {
+            assert!(piece.len() <= MAX_LINE_LEN);
+        }
+        assert_eq!(content_lines(&folded)[0], line);
+    }
+
+    #[test]
+    fn writes_recurring_events_in_the_guilds_zone() {
+        let mut event = Event::new(
+            Uuid::nil(),
+            1,
+            "Game night".to_string(),
+            String::new(),
+            String::new(),
+            at(2025, 1, 9, 3, 0),
+            at(2025, 1, 9, 5, 0),
+        );
+        event.recurrence = Some("FREQ=WEEKLY;UNTIL=20250227".to_string());
+        event.exceptions = vec![NaiveDate::from_ymd_opt(2025, 1, 15).unwrap()];
+        let lines = content_lines(&calendar(&[event], Los_Angeles));
+        for expected in [
+            "DTSTART;TZID=America/Los_Angeles:20250108T190000",
+            "DTEND;TZID=America/Los_Angeles:20250108T210000",
+            "RRULE:FREQ=WEEKLY;UNTIL=20250228T075959Z",
+            "EXDATE;TZID=America/Los_Angeles:20250115T190000",
+        ] {
+            assert!(lines.iter().any(|line| line == expected), "{}", expected);
+        }
+    }
+
+    #[test]
+    fn writes_one_off_events_in_utc() {
+        let event = Event::new(
+            Uuid::nil(),
+            1,
+            "Launch, party".to_string(),
+            "https://example.com".to_string(),
+            "Line one\nLine two".to_string(),
+            at(2025, 1, 9, 3, 0),
+            at(2025, 1, 9, 5, 0),
+        );
+        let lines = content_lines(&calendar(&[event], Los_Angeles));
+        for expected in [
+            "DTSTART:20250109T030000Z",
+            "DTEND:20250109T050000Z",
+            "SUMMARY:Launch\\, party",
+            "DESCRIPTION:Line one\\nLine two",
+            "URL:https://example.com",
+        ] {
+            assert!(lines.iter().any(|line| line == expected), "{}", expected);
+        }
+    }
+
+    #[test]
+    fn reads_folded_and_escaped_text() {
+        let event = parse_one(
+            "SUMMARY:Board games\\, snacks\r\n\
+             DESCRIPTION:Bring a game\\; any\r\n  game.\\nDoors at 7.\r\n\
+             DTSTART:20300105T190000Z\r\n\
+             DTEND:20300105T220000Z\r\n",
+            UTC,
+        );
+        assert_eq!(event.title, "Board games, snacks");
+        assert_eq!(event.description, "Bring a game; any game.\nDoors at 7.");
+    }
+
+    #[test]
+    fn reads_times_in_their_zone() {
+        // TZID wins over the guild's zone, and floating times are read in the guild's.
+        let event = parse_one(
+            "SUMMARY:Meetup\r\n\
+             DTSTART;TZID=Europe/Berlin:20300105T190000\r\n\
+             DTEND:20300105T200000\r\n",
+            Los_Angeles,
+        );
+        assert_eq!(event.start_date, at(2030, 1, 5, 18, 0));
+        assert_eq!(event.end_date, at(2030, 1, 6, 4, 0));
+    }
+
+    #[test]
+    fn unknown_zones_fall_back_to_the_guilds() {
+        let event = parse_one(
+            "SUMMARY:Meetup\r\n\
+             DTSTART;TZID=\"W. Europe Standard Time\":20300105T190000\r\n\
+             DTEND;TZID=\"W. Europe Standard Time\":20300105T200000\r\n",
+            Berlin,
+        );
+        assert_eq!(event.start_date, at(2030, 1, 5, 18, 0));
+    }
+
+    #[test]
+    fn reads_all_day_events() {
+        let event = parse_one(
+            "SUMMARY:Holiday\r\nDTSTART;VALUE=DATE:20300105\r\n",
+            Los_Angeles,
+        );
+        assert_eq!(event.start_date, at(2030, 1, 5, 8, 0));
+        assert_eq!(event.end_date, at(2030, 1, 6, 8, 0));
+
+        let event = parse_one(
+            "SUMMARY:Festival\r\nDTSTART:20300105\r\nDTEND:20300107\r\n",
+            UTC,
+        );
+        assert_eq!(event.end_date, at(2030, 1, 7, 0, 0));
+    }
+
+    #[test]
+    fn reads_durations() {
+        let event = parse_one(
+            "SUMMARY:Stream\r\nDTSTART:20300105T190000Z\r\nDURATION:PT1H30M\r\n",
+            UTC,
+        );
+        assert_eq!(event.end_date, at(2030, 1, 5, 20, 30));
+        assert_eq!(parse_ics_duration("P1DT12H"), Some(Duration::hours(36)));
+        assert_eq!(parse_ics_duration("P2W"), Some(Duration::weeks(2)));
+        assert_eq!(parse_ics_duration("1H"), None);
+        assert_eq!(parse_ics_duration("P99999999999999W"), None);
+    }
+
+    #[test]
+    fn keeps_supported_rules_and_notes_the_rest() {
+        let ics = vcalendar(&[
+            "SUMMARY:Weekly\r\nDTSTART:20200105T190000Z\r\nRRULE:FREQ=WEEKLY;BYDAY=SU\r\n",
+            "SUMMARY:Yearly\r\nDTSTART:20300105T190000Z\r\nRRULE:FREQ=YEARLY\r\n",
+        ]);
+        let import = parse_events(&ics, 1, UTC);
+        // A series that started long ago is still imported.
+        assert_eq!(import.events.len(), 1);
+        assert_eq!(
+            import.events[0].recurrence.as_deref(),
+            Some("FREQ=WEEKLY;BYDAY=SU")
+        );
+        assert_eq!(import.notes.len(), 1);
+        assert!(import.notes[0].contains("Yearly"));
+    }
+
+    #[test]
+    fn skips_cancelled_past_and_broken_events() {
+        let ics = vcalendar(&[
+            "SUMMARY:Cancelled\r\nSTATUS:CANCELLED\r\nDTSTART:20300105T190000Z\r\n",
+            "SUMMARY:Over\r\nDTSTART:20200105T190000Z\r\n",
+            "SUMMARY:No start\r\n",
+            "SUMMARY:Too long\r\nDTSTART:20300105T190000Z\r\nDURATION:P99999999999D\r\n",
+        ]);
+        let import = parse_events(&ics, 1, UTC);
+        assert!(import.events.is_empty());
+        assert_eq!(import.notes.len(), 2);
+    }
+
+    #[test]
+    fn ignores_nested_components() {
+        let event = parse_one(
+            "SUMMARY:Meetup\r\n\
+             DTSTART:20300105T190000Z\r\n\
+             BEGIN:VALARM\r\n\
+             DESCRIPTION:Reminder\r\n\
+             END:VALARM\r\n",
+            UTC,
+        );
+        assert_eq!(event.description, "");
+    }
+}
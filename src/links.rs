@@ -0,0 +1,609 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::Utc;
+use serenity::async_trait;
+use serenity::prelude::*;
+use uuid::Uuid;
+
+use crate::api;
+use crate::commands::invocation::Invocation;
+use crate::commands::pins::{self, Pin};
+use crate::store::{self, Store};
+use crate::validation::validation;
+
+static CHECK_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+static FETCH_TIMEOUT: Duration = Duration::from_secs(10);
+static USER_AGENT: &str = "corkboard-bot link checker";
+// A link is only flagged after failing this many checks in a row, so a site that's
+// down for a moment isn't.
+static DEAD_AFTER: u32 = 2;
+// Titles and descriptions are in the page's <head>, so there's no need to read it all.
+static MAX_PAGE_LEN: usize = 256 * 1024;
+static MAX_TITLE_LEN: usize = 100;
+static MAX_DESCRIPTION_LEN: usize = 300;
+static MAX_REDIRECTS: usize = 10;
+
+pub struct Page {
+    pub status: u16,
+    pub body: String,
+}
+
+// Everything the link checker and link previews need from the web. It's a trait so the
+// bot can be pointed at a fake instead of real sites.
+#[async_trait]
+pub trait Web: Send + Sync {
+    // Requests the URL, following redirects. The body is only read when asked for, and
+    // then only its start.
+    async fn get(&self, url: &str, read_body: bool) -> Result<Page, String>;
+
+    // The addresses a host name points at.
+    async fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, String>;
+}
+
+pub struct HttpWeb {
+    client: reqwest::Client,
+}
+
+impl HttpWeb {
+    pub fn new() -> reqwest::Result<Self> {
+        // Redirects into the bot's own network aren't followed.
+        let redirects = reqwest::redirect::Policy::custom(|attempt| {
+            let private = attempt
+                .url()
+                .host_str()
+                .and_then(ip_address)
+                .is_some_and(|ip| !is_public_address(ip));
+            if private {
+                attempt.stop()
+            } else if attempt.previous().len() >= MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else {
+                attempt.follow()
+            }
+        });
+        let client = reqwest::Client::builder()
+            .timeout(FETCH_TIMEOUT)
+            .user_agent(USER_AGENT)
+            .redirect(redirects)
+            .build()?;
+        Ok(HttpWeb { client })
+    }
+}
+
+#[async_trait]
+impl Web for HttpWeb {
+    async fn get(&self, url: &str, read_body: bool) -> Result<Page, String> {
+        let mut response = self.client.get(url).send().await.map_err(describe)?;
+        let status = response.status().as_u16();
+        let mut body = Vec::new();
+        while read_body && body.len() < MAX_PAGE_LEN {
+            match response.chunk().await.map_err(describe)? {
+                Some(chunk) => body.extend_from_slice(&chunk),
+                None => break,
+            }
+        }
+        Ok(Page {
+            status,
+            body: String::from_utf8_lossy(&body).into_owned(),
+        })
+    }
+
+    async fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+        match tokio::net::lookup_host((host, 0)).await {
+            Ok(addresses) => Ok(addresses.map(|address| address.ip()).collect()),
+            Err(_) => Err("couldn't resolve".to_string()),
+        }
+    }
+}
+
+fn describe(error: reqwest::Error) -> String {
+    if error.is_timeout() {
+        "timed out".to_string()
+    } else if error.is_connect() {
+        "couldn't connect".to_string()
+    } else {
+        "request failed".to_string()
+    }
+}
+
+pub struct WebKey;
+
+impl TypeMapKey for WebKey {
+    type Value = Arc<dyn Web>;
+}
+
+// Fetches the shared web client that was built in main().
+pub async fn web(ctx: &Context) -> Arc<dyn Web> {
+    let data = ctx.data.read().await;
+    data.get::<WebKey>()
+        .expect("Expected Web in the client data.")
+        .clone()
+}
+
+// Requests every pinned link now and then, and records which ones have stopped working
+// so `.pins` can flag them.
+pub async fn run(ctx: Context) {
+    println!("Started pin link checker.");
+    loop {
+        for guild_id in ctx.cache.guilds() {
+            check_guild(&ctx, guild_id.0).await;
+        }
+
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+async fn check_guild(ctx: &Context, guild_id: u64) {
+    let pins = match api::client(ctx).await.list_pins(guild_id as i64).await {
+        Ok(pins) => pins,
+        Err(why) => {
+            why.log("link checker");
+            return;
+        }
+    };
+
+    let web = web(ctx).await;
+    let mut results = Vec::new();
+    for pin in pins {
+        if validation::is_web_url(&pin.url) {
+            let result = check(&*web, &pin.url).await;
+            results.push((pin.id, pin.url, result));
+        }
+    }
+    store::store(ctx)
+        .await
+        .record_link_checks(guild_id, results, Utc::now().naive_utc());
+}
+
+// Only answers that say the page is gone count as dead. Sites often turn away bots
+// with a 403 or 429 while working fine in a browser.
+pub async fn check(web: &dyn Web, url: &str) -> Result<(), String> {
+    match web.get(url, false).await? {
+        Page { status, .. } if status == 404 || status == 410 || status >= 500 => {
+            Err(format!("HTTP {}", status))
+        }
+        _ => Ok(()),
+    }
+}
+
+// Why each of the guild's pins looks dead, for pins whose link has failed enough checks
+// in a row. A link that has been edited since its last check isn't flagged.
+pub fn dead_links(store: &Store, pins: &[Pin]) -> HashMap<Uuid, String> {
+    pins.iter()
+        .filter_map(|pin| {
+            let check = store.link_check(pin.id)?;
+            match check.error {
+                Some(error) if check.url == pin.url && check.failures >= DEAD_AFTER => {
+                    Some((pin.id, error))
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+pub struct Preview {
+    pub title: String,
+    pub description: Option<String>,
+}
+
+// The page's title and description, preferring the Open Graph tags that link previews
+// in Discord and elsewhere use. Pages in the bot's own network are never read, since
+// anyone who can add a pin could otherwise have the bot fetch them.
+pub async fn preview(web: &dyn Web, url: &str) -> Option<Preview> {
+    if !is_public(web, url).await {
+        println!(
+            "Not reading a preview of {} as it isn't a public address.",
+            url
+        );
+        return None;
+    }
+    let page = web.get(url, true).await.ok()?;
+    if !(200..300).contains(&page.status) {
+        return None;
+    }
+
+    let mut meta: HashMap<String, String> = HashMap::new();
+    for tag in tags(&page.body, "meta") {
+        let attributes = attributes(tag);
+        let key = attributes
+            .get("property")
+            .or_else(|| attributes.get("name"))
+            .map(|key| key.to_lowercase());
+        if let (Some(key), Some(content)) = (key, attributes.get("content")) {
+            meta.entry(key).or_insert_with(|| clean(content));
+        }
+    }
+
+    let title = meta
+        .remove("og:title")
+        .or_else(|| title(&page.body))
+        .filter(|title| !title.is_empty())?;
+    let description = meta
+        .remove("og:description")
+        .or_else(|| meta.remove("description"))
+        .filter(|description| !description.is_empty());
+    Some(Preview { title, description })
+}
+
+// Whether every address the URL's host points at can be reached from the internet.
+async fn is_public(web: &dyn Web, url: &str) -> bool {
+    let url = match reqwest::Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return false,
+    };
+    let addresses = match url.host_str() {
+        Some(host) => match ip_address(host) {
+            Some(ip) => vec![ip],
+            None => web.resolve(host).await.unwrap_or_default(),
+        },
+        None => return false,
+    };
+    !addresses.is_empty() && addresses.into_iter().all(is_public_address)
+}
+
+// The address a URL host is written as, if it is one. IPv6 addresses are in brackets.
+fn ip_address(host: &str) -> Option<IpAddr> {
+    host.trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse()
+        .ok()
+}
+
+// Private, loopback, link-local and other addresses that don't lead out to the internet
+// aren't public.
+fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [first, second, ..] = ip.octets();
+            // 100.64.0.0/10 is shared by carriers and cloud providers.
+            let shared = first == 100 && (64..128).contains(&second);
+            !(ip.is_private()
+                || ip.is_loopback()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_documentation()
+                || shared)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_address(IpAddr::V4(ip)),
+            None => {
+                let first = ip.segments()[0];
+                // Unique local addresses are fc00::/7 and link-local ones fe80::/10.
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || first & 0xfe00 == 0xfc00
+                    || first & 0xffc0 == 0xfe80)
+            }
+        },
+    }
+}
+
+// The inside of each `<name ...>` tag. Lowercased copies keep the same byte offsets, as
+// only ASCII letters change.
+fn tags<'a>(html: &'a str, name: &str) -> Vec<&'a str> {
+    let lower = html.to_ascii_lowercase();
+    let open = format!("<{}", name);
+    let mut found = Vec::new();
+    let mut from = 0;
+    while let Some(start) = lower[from..].find(&open).map(|i| from + i + open.len()) {
+        let end = match html[start..].find('>') {
+            Some(i) => start + i,
+            None => break,
+        };
+        if html[start..].starts_with(|c: char| c.is_whitespace()) {
+            found.push(&html[start..end]);
+        }
+        from = end;
+    }
+    found
+}
+
+fn attributes(tag: &str) -> HashMap<String, String> {
+    let mut attributes = HashMap::new();
+    let mut rest = tag.trim_start();
+    while !rest.is_empty() {
+        let name_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let name = rest[..name_end].trim_matches('/').to_lowercase();
+        rest = rest[name_end..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(after) => {
+                let after = after.trim_start();
+                let (value, remaining) = match after.chars().next() {
+                    Some(quote @ ('"' | '\'')) => match after[1..].find(quote) {
+                        Some(close) => (&after[1..close + 1], &after[close + 2..]),
+                        None => (&after[1..], ""),
+                    },
+                    _ => {
+                        let end = after.find(char::is_whitespace).unwrap_or(after.len());
+                        (&after[..end], &after[end..])
+                    }
+                };
+                rest = remaining.trim_start();
+                value
+            }
+            None => "",
+        };
+        if !name.is_empty() {
+            attributes.entry(name).or_insert_with(|| value.to_string());
+        }
+    }
+    attributes
+}
+
+fn title(html: &str) -> Option<String> {
+    let lower = html.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let start = start + html[start..].find('>')? + 1;
+    let end = start + lower[start..].find("</title")?;
+    Some(clean(&html[start..end]))
+}
+
+// Decodes the common HTML entities and collapses whitespace.
+fn clean(text: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = text;
+    while let Some(amp) = rest.find('&') {
+        decoded.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+        let entity = rest[1..].find(';').map(|end| &rest[1..end + 1]);
+        let character = entity.and_then(|entity| match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            _ => match entity.strip_prefix('#') {
+                Some(code) => match code.strip_prefix(['x', 'X']) {
+                    Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                    None => code.parse().ok(),
+                }
+                .and_then(char::from_u32),
+                None => None,
+            },
+        });
+        match (entity, character) {
+            (Some(entity), Some(character)) => {
+                decoded.push(character);
+                rest = &rest[entity.len() + 2..];
+            }
+            _ => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+// Fills in a missing title or description from the page the URL points at. Replies with
+// an error and returns `None` if the title is missing and the page has none either.
+pub async fn complete(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    url: &str,
+    title: String,
+    description: String,
+) -> Option<(String, String)> {
+    if !title.trim().is_empty() && !description.trim().is_empty() {
+        return Some((title, description));
+    }
+
+    let preview = preview(&*web(ctx).await, url).await;
+    let description = match (description.trim().is_empty(), &preview) {
+        (
+            true,
+            Some(Preview {
+                description: Some(fetched),
+                ..
+            }),
+        ) => pins::truncate(fetched, MAX_DESCRIPTION_LEN),
+        _ => description,
+    };
+    if !title.trim().is_empty() {
+        return Some((title, description));
+    }
+    match preview {
+        Some(preview) => Some((pins::truncate(&preview.title, MAX_TITLE_LEN), description)),
+        None => {
+            let _msg = inv
+                .say(
+                    ctx,
+                    format!(
+                        ":bangbang: Error :bangbang: - Couldn't read a title from {}. Please give one yourself.",
+                        url
+                    ),
+                )
+                .await;
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Answers every request with the same status and page, or fails it.
+    struct FakeWeb {
+        status: u16,
+        body: &'static str,
+        error: Option<&'static str>,
+    }
+
+    #[async_trait]
+    impl Web for FakeWeb {
+        async fn get(&self, _url: &str, read_body: bool) -> Result<Page, String> {
+            if let Some(error) = self.error {
+                return Err(error.to_string());
+            }
+            Ok(Page {
+                status: self.status,
+                body: if read_body { self.body } else { "" }.to_string(),
+            })
+        }
+
+        async fn resolve(&self, host: &str) -> Result<Vec<IpAddr>, String> {
+            match host {
+                "localhost" => Ok(vec!["127.0.0.1".parse().unwrap(), "::1".parse().unwrap()]),
+                "intranet.example.com" => Ok(vec!["10.0.0.7".parse().unwrap()]),
+                "nowhere.example.com" => Err("couldn't resolve".to_string()),
+                _ => Ok(vec!["93.184.215.14".parse().unwrap()]),
+            }
+        }
+    }
+
+    fn answering(status: u16, body: &'static str) -> FakeWeb {
+        FakeWeb {
+            status,
+            body,
+            error: None,
+        }
+    }
+
+    async fn preview_of(body: &'static str) -> Option<Preview> {
+        preview(&answering(200, body), "https://example.com").await
+    }
+
+    #[tokio::test]
+    async fn only_gone_pages_are_dead() {
+        for status in [404, 410, 500, 503] {
+            let result = check(&answering(status, ""), "https://example.com").await;
+            assert_eq!(result, Err(format!("HTTP {}", status)));
+        }
+        for status in [200, 301, 403, 429] {
+            assert_eq!(
+                check(&answering(status, ""), "https://example.com").await,
+                Ok(())
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_requests_are_dead() {
+        let web = FakeWeb {
+            status: 200,
+            body: "",
+            error: Some("timed out"),
+        };
+        assert_eq!(
+            check(&web, "https://example.com").await,
+            Err("timed out".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn prefers_open_graph_tags() {
+        let preview = preview_of(
+            r#"<html><head><title>Page title</title>
+            <meta name="description" content="Plain description">
+            <meta property="og:title" content="Shared title">
+            <meta property="og:description" content="Shared description">
+            </head></html>"#,
+        )
+        .await
+        .unwrap();
+        assert_eq!(preview.title, "Shared title");
+        assert_eq!(preview.description.as_deref(), Some("Shared description"));
+    }
+
+    #[tokio::test]
+    async fn falls_back_to_the_title_and_description() {
+        let preview = preview_of(
+            "<HTML><HEAD><TITLE>\n  Raid   night\n</TITLE>\
+            <META NAME=\"Description\" CONTENT=\"Every Friday\"></HEAD></HTML>",
+        )
+        .await
+        .unwrap();
+        assert_eq!(preview.title, "Raid night");
+        assert_eq!(preview.description.as_deref(), Some("Every Friday"));
+
+        let preview = preview_of("<title>Only a title</title>").await.unwrap();
+        assert_eq!(preview.description, None);
+    }
+
+    #[tokio::test]
+    async fn pages_without_a_title_or_that_failed_have_no_preview() {
+        assert!(preview_of("<html><body>Hello</body></html>")
+            .await
+            .is_none());
+        assert!(preview_of("<title>  </title>").await.is_none());
+        let missing = answering(404, "<title>Not Found</title>");
+        assert!(preview(&missing, "https://example.com").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn reads_unquoted_and_single_quoted_attributes() {
+        let preview = preview_of(
+            "<meta property=og:title content='It\"s \"here\"'>\
+            <meta content=Short property=og:description>",
+        )
+        .await
+        .unwrap();
+        assert_eq!(preview.title, "It\"s \"here\"");
+        assert_eq!(preview.description.as_deref(), Some("Short"));
+    }
+
+    #[test]
+    fn unclosed_quotes_run_to_the_end_of_the_tag() {
+        let attributes = attributes(r#" property="og:title" content="Broken"#);
+        assert_eq!(attributes["property"], "og:title");
+        assert_eq!(attributes["content"], "Broken");
+    }
+
+    #[test]
+    fn only_whole_tag_names_match() {
+        let html = "<metadata a=1><meta a=2><meta\na=3>";
+        assert_eq!(tags(html, "meta"), vec![" a=2", "\na=3"]);
+    }
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(clean("Fish &amp; Chips"), "Fish & Chips");
+        assert_eq!(
+            clean("&lt;b&gt; &quot;x&quot; &apos;y&apos;"),
+            "<b> \"x\" 'y'"
+        );
+        assert_eq!(clean("caf&#233; &#x2014; &#X41;"), "café — A");
+        assert_eq!(clean("a&nbsp;b"), "a b");
+    }
+
+    #[test]
+    fn leaves_unknown_or_broken_entities_alone() {
+        assert_eq!(
+            clean("R&D &bogus; &#xZZ; &#1114112; & done"),
+            "R&D &bogus; &#xZZ; &#1114112; & done"
+        );
+        assert_eq!(clean("trailing &amp"), "trailing &amp");
+    }
+
+    #[tokio::test]
+    async fn private_hosts_are_never_previewed() {
+        let web = answering(200, "<title>Secret</title>");
+        for url in [
+            "http://localhost:8080/admin",
+            "http://127.0.0.1/",
+            "http://169.254.169.254/latest/meta-data/",
+            "http://10.1.2.3/",
+            "http://192.168.0.1/",
+            "http://[::1]/",
+            "http://[fe80::1]/",
+            "http://[::ffff:127.0.0.1]/",
+            "http://intranet.example.com/",
+            "http://nowhere.example.com/",
+            "not a url",
+        ] {
+            assert!(preview(&web, url).await.is_none(), "{}", url);
+        }
+        assert!(preview(&web, "https://example.com/").await.is_some());
+        assert!(preview(&web, "http://8.8.8.8/").await.is_some());
+    }
+}
@@ -1,29 +1,81 @@
 use std::collections::HashSet;
 use std::env;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
 
 use serenity::async_trait;
-use serenity::framework::standard::macros::{group, help};
+use serenity::framework::standard::macros::{group, help, hook};
 use serenity::framework::standard::{
     help_commands, Args, CommandGroup, CommandResult, HelpOptions, StandardFramework,
 };
-use serenity::model::channel::Message;
+use serenity::model::application::interaction::Interaction;
+use serenity::model::channel::{Message, Reaction};
+use serenity::model::gateway::Ready;
+use serenity::model::guild::ScheduledEvent;
 use serenity::model::id::UserId;
 use serenity::prelude::*;
 
+mod api;
+use api::client::CorkboardClient;
+
+mod archive;
+
 mod commands;
 use commands::{
+    autocomplete,
+    calendar::*,
     events::*,
     faqs::*,
+    forms,
+    import::*,
+    invocation::Invocation,
     luckydex::*,
     luckymon::{self, *},
     luckytrade::*,
+    message_pins::{self, *},
     pins::*,
+    refresh::*,
+    reminders::*,
+    rsvp::{self, *},
+    scheduled::*,
+    slash,
+    timezone::*,
 };
 
+mod config;
+use config::Config;
+
+mod dates;
+
+mod error;
+use error::BotError;
+
+mod feed;
+
+mod ics;
+
+mod links;
+use links::{HttpWeb, WebKey};
+
+mod migration;
+
+mod recurrence;
+
+mod reminders;
+
+mod scheduled;
+
+mod store;
+use store::Store;
+
+mod timezone;
+
 mod validation;
 
 #[group]
-#[commands(pins, events, faqs, luckymon, luckydex, luckytrade)]
+#[commands(
+    pins, events, event, calendar, faqs, luckymon, luckydex, luckytrade, timezone
+)]
 pub struct General;
 
 #[group]
@@ -34,16 +86,75 @@ pub struct General;
     add_event,
     edit_event,
     delete_event,
+    repeat_event,
+    import_events,
     add_pin,
     edit_pin,
-    delete_pin
+    delete_pin,
+    move_pin,
+    feature_pin,
+    expire_pin,
+    restore_pin,
+    pin_reaction,
+    guild_timezone,
+    reminders,
+    scheduled_events,
+    refresh
 )]
 pub struct Admin;
 
-struct Handler;
+struct Handler {
+    // `ready` fires again after every reconnect, but only one of each background task
+    // should run.
+    tasks_started: AtomicBool,
+}
 
 #[async_trait]
-impl EventHandler for Handler {}
+impl EventHandler for Handler {
+    async fn ready(&self, ctx: Context, ready: Ready) {
+        println!("{} is connected!", ready.user.name);
+        slash::register(&ctx).await;
+
+        if !self.tasks_started.swap(true, Ordering::SeqCst) {
+            // Old event dates are converted before any task reads them.
+            let guild_ids = ready.guilds.iter().map(|guild| guild.id.0).collect();
+            migration::convert_event_dates(&ctx, guild_ids).await;
+            tokio::spawn(reminders::run(ctx.clone()));
+            tokio::spawn(scheduled::run(ctx.clone()));
+            tokio::spawn(links::run(ctx.clone()));
+            tokio::spawn(archive::run(ctx));
+        }
+    }
+
+    async fn reaction_add(&self, ctx: Context, reaction: Reaction) {
+        message_pins::on_reaction(&ctx, &reaction).await;
+    }
+
+    async fn guild_scheduled_event_create(&self, ctx: Context, event: ScheduledEvent) {
+        scheduled::on_create(&ctx, &event).await;
+    }
+
+    async fn guild_scheduled_event_update(&self, ctx: Context, event: ScheduledEvent) {
+        scheduled::on_update(&ctx, &event).await;
+    }
+
+    async fn guild_scheduled_event_delete(&self, ctx: Context, event: ScheduledEvent) {
+        scheduled::on_delete(&ctx, &event).await;
+    }
+
+    async fn interaction_create(&self, ctx: Context, interaction: Interaction) {
+        match interaction {
+            Interaction::ApplicationCommand(command) => slash::dispatch(&ctx, &command).await,
+            Interaction::Autocomplete(autocomplete) => {
+                autocomplete::respond(&ctx, &autocomplete).await
+            }
+            Interaction::ModalSubmit(modal) => forms::submit(&ctx, &modal).await,
+            // Luckydex and trade buttons are handled by their commands' own collectors.
+            Interaction::MessageComponent(component) => rsvp::respond(&ctx, &component).await,
+            _ => {}
+        }
+    }
+}
 
 #[tokio::main]
 async fn main() {
@@ -51,6 +162,7 @@ async fn main() {
         .configure(|c| c.prefix(".")) // set the bot's prefix to "."
         .group(&GENERAL_GROUP)
         .group(&ADMIN_GROUP)
+        .after(after)
         .help(&HELP);
 
     // Login with a bot token from the environment
@@ -58,11 +170,33 @@ async fn main() {
         env::var("DISCORD_TOKEN").expect("Unable to retrieve DISCORD_TOKEN environment variable!");
     let intents = GatewayIntents::non_privileged() | GatewayIntents::MESSAGE_CONTENT;
     let mut client = Client::builder(token, intents)
-        .event_handler(Handler)
+        .event_handler(Handler {
+            tasks_started: AtomicBool::new(false),
+        })
         .framework(framework)
         .await
         .expect("Error creating client");
 
+    {
+        let config = Config::load();
+        println!("Using Corkboard Server at {}", config.api_url);
+        let api_client = Arc::new(
+            CorkboardClient::new(&config).expect("Error creating Corkboard Server client"),
+        );
+        let store = Arc::new(Store::load(&config.state_path));
+        if let Some(addr) = config.feed_addr() {
+            tokio::spawn(feed::serve(addr, api_client.clone(), store.clone()));
+        }
+
+        let mut data = client.data.write().await;
+        data.insert::<CorkboardClient>(api_client);
+        data.insert::<Store>(store);
+        data.insert::<Config>(Arc::new(config));
+        data.insert::<WebKey>(Arc::new(
+            HttpWeb::new().expect("Error creating link checker client"),
+        ));
+    }
+
     let _ = luckymon::initialize().await;
 
     // start listening for events by starting a single shard
@@ -71,6 +205,20 @@ async fn main() {
     }
 }
 
+#[hook]
+async fn after(ctx: &Context, msg: &Message, command_name: &str, command_result: CommandResult) {
+    if let Err(why) = command_result {
+        match why.downcast_ref::<BotError>() {
+            Some(bot_error) => {
+                bot_error
+                    .report(ctx, &Invocation::Prefix(msg), command_name)
+                    .await
+            }
+            None => println!("Command '{}' returned error {:?}", command_name, why),
+        }
+    }
+}
+
 #[help]
 #[command_not_found_text = "Could not find: `{}`."]
 #[max_levenshtein_distance(3)]
@@ -0,0 +1,128 @@
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use serenity::prelude::*;
+use uuid::Uuid;
+
+use crate::api;
+use crate::commands::events::STORED_TZ;
+use crate::commands::invocation::Invocation;
+use crate::store;
+use crate::timezone;
+
+// Set once the conversion below has run. Until then changes to events are held back, as
+// an event changed while its old dates are queued for conversion would be shifted again.
+static CONVERTED: AtomicBool = AtomicBool::new(false);
+static WAIT_INTERVAL: Duration = Duration::from_secs(1);
+
+// Replies with an error and returns false while event dates are still being converted.
+pub async fn events_writable(ctx: &Context, inv: &Invocation<'_>) -> bool {
+    if CONVERTED.load(Ordering::SeqCst) {
+        return true;
+    }
+    let _msg = inv
+        .say(
+            ctx,
+            ":bangbang: Error :bangbang: - Event dates are still being converted to UTC after an update. Please try again in a minute.",
+        )
+        .await;
+    false
+}
+
+// For changes from Discord, which can wait for the conversion instead of being turned
+// down.
+pub async fn wait_for_event_dates() {
+    while !CONVERTED.load(Ordering::SeqCst) {
+        tokio::time::sleep(WAIT_INTERVAL).await;
+    }
+}
+
+// Converts the dates of events stored before time zone support from Pacific time to
+// UTC. The guilds are taken from the first `ready` with this version, and anything left
+// over after a failure is retried on the next start. Converted events are marked with
+// their zone, so an event that already has one is never converted twice.
+pub async fn convert_event_dates(ctx: &Context, guild_ids: Vec<u64>) {
+    convert(ctx, guild_ids).await;
+    CONVERTED.store(true, Ordering::SeqCst);
+}
+
+async fn convert(ctx: &Context, guild_ids: Vec<u64>) {
+    let store = store::store(ctx).await;
+    if !store.date_migration().started {
+        println!("Converting stored event dates to UTC..");
+        store.update_date_migration(|migration| {
+            migration.started = true;
+            migration.unlisted_guilds = guild_ids;
+        });
+    }
+
+    let client = api::client(ctx).await;
+    for guild_id in store.date_migration().unlisted_guilds {
+        match client.list_all_events(guild_id as i64).await {
+            Ok(events) => store.update_date_migration(|migration| {
+                migration.unlisted_guilds.retain(|id| *id != guild_id);
+                migration
+                    .pending_events
+                    .extend(events.iter().map(|event| (event.id, guild_id)));
+            }),
+            Err(why) => why.log("event date conversion"),
+        }
+    }
+
+    let pending = store.date_migration().pending_events;
+    let mut unmarked = false;
+    let guilds: HashSet<u64> = pending.values().copied().collect();
+    for guild_id in guilds {
+        let events = match client.list_all_events(guild_id as i64).await {
+            Ok(events) => events,
+            Err(why) => {
+                why.log("event date conversion");
+                continue;
+            }
+        };
+        // Events deleted in the meantime have nothing left to convert.
+        let listed: HashSet<Uuid> = events.iter().map(|event| event.id).collect();
+        store.update_date_migration(|migration| {
+            migration
+                .pending_events
+                .retain(|id, guild| *guild != guild_id || listed.contains(id))
+        });
+
+        for mut event in events.into_iter().filter(|e| pending.contains_key(&e.id)) {
+            if event.tz.is_some() {
+                store.update_date_migration(|migration| {
+                    migration.pending_events.remove(&event.id);
+                });
+                continue;
+            }
+            event.start_date = timezone::to_utc(timezone::DEFAULT_TIMEZONE, event.start_date);
+            event.end_date = timezone::to_utc(timezone::DEFAULT_TIMEZONE, event.end_date);
+            event.tz = Some(STORED_TZ.to_string());
+            println!("Sending Event date conversion request with {:?}", event);
+            match client.update_event(&event).await {
+                Ok(converted) => {
+                    if converted.tz.is_none() && !unmarked {
+                        println!("The Corkboard Server doesn't keep the tz field of an event, so only the bot's list of pending events keeps them from being converted twice.");
+                        unmarked = true;
+                    }
+                    store.update_date_migration(|migration| {
+                        migration.pending_events.remove(&event.id);
+                    })
+                }
+                Err(why) => why.log("event date conversion"),
+            }
+        }
+    }
+
+    let left = store.date_migration();
+    if left.unlisted_guilds.is_empty() && left.pending_events.is_empty() {
+        println!("Stored event dates are in UTC.");
+    } else {
+        println!(
+            "{} guilds and {} events are still to be converted to UTC; retrying on the next start.",
+            left.unlisted_guilds.len(),
+            left.pending_events.len()
+        );
+    }
+}
@@ -0,0 +1,610 @@
+use std::collections::VecDeque;
+
+use chrono::{Datelike, Duration, Months, NaiveDate, NaiveDateTime, Utc, Weekday};
+use chrono_tz::Tz;
+
+use crate::dates;
+use crate::timezone;
+
+pub static ACCEPTED_RULES: &str = "Repeats can be written as:
+- `daily`, `weekly`, `monthly`
+- `every 2 weeks on mon, wed`, `every friday`, `every other month`
+- any of those followed by `until 12/31/2024` or `for 10 times`
+- an iCalendar rule such as `FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;COUNT=10`";
+
+// Occurrences are only generated this far past the first one. It also stops a rule that
+// can never match (e.g. every 7 days but only on a different weekday) from being
+// searched forever.
+static MAX_YEARS: u32 = 100;
+// Anything longer between occurrences would leave a series with one or two of them.
+static MAX_INTERVAL: u32 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+// The subset of an iCalendar RRULE that events support. Occurrences are generated in the
+// guild's local time, so a weekly 7pm event stays at 7pm across daylight saving changes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Recurrence {
+    pub frequency: Frequency,
+    pub interval: u32,
+    // Only for daily and weekly rules; empty means the weekday of the first occurrence.
+    pub weekdays: Vec<Weekday>,
+    // Last local date an occurrence may fall on.
+    pub until: Option<NaiveDate>,
+    pub count: Option<u32>,
+}
+
+impl Recurrence {
+    // Reads either the friendly form or an RRULE, reading `until` dates in `tz`.
+    pub fn parse(input: &str, tz: Tz) -> Result<Recurrence, String> {
+        let input = input.trim();
+        let rule = input
+            .strip_prefix("RRULE:")
+            .or_else(|| input.strip_prefix("rrule:"))
+            .unwrap_or(input);
+        let parsed = if rule.to_uppercase().starts_with("FREQ=") {
+            parse_rrule(rule, tz)
+        } else {
+            parse_friendly(rule, tz)
+        };
+
+        parsed.map_err(|reason| {
+            format!(
+                ":bangbang: Error :bangbang: - Couldn't understand the repeat `{}`: {}.\n{}",
+                input, reason, ACCEPTED_RULES
+            )
+        })
+    }
+
+    pub fn to_rrule(&self) -> String {
+        let mut parts = vec![format!(
+            "FREQ={}",
+            match self.frequency {
+                Frequency::Daily => "DAILY",
+                Frequency::Weekly => "WEEKLY",
+                Frequency::Monthly => "MONTHLY",
+            }
+        )];
+        if self.interval != 1 {
+            parts.push(format!("INTERVAL={}", self.interval));
+        }
+        if !self.weekdays.is_empty() {
+            let days: Vec<&str> = self
+                .weekdays
+                .iter()
+                .map(|day| rrule_weekday(*day))
+                .collect();
+            parts.push(format!("BYDAY={}", days.join(",")));
+        }
+        if let Some(until) = self.until {
+            parts.push(format!("UNTIL={}", until.format("%Y%m%d")));
+        }
+        if let Some(count) = self.count {
+            parts.push(format!("COUNT={}", count));
+        }
+        parts.join(";")
+    }
+
+    // `every 2 weeks on Mon, Wed until Tue 12/31/2024`, for showing next to an event.
+    pub fn describe(&self) -> String {
+        let unit = match self.frequency {
+            Frequency::Daily => "day",
+            Frequency::Weekly => "week",
+            Frequency::Monthly => "month",
+        };
+        let mut description = match self.interval {
+            1 => format!("every {}", unit),
+            n => format!("every {} {}s", n, unit),
+        };
+        if !self.weekdays.is_empty() {
+            let days: Vec<String> = self.weekdays.iter().map(|day| day.to_string()).collect();
+            description.push_str(&format!(" on {}", days.join(", ")));
+        }
+        if let Some(until) = self.until {
+            description.push_str(&format!(" until {}", until.format("%a %m/%d/%Y")));
+        }
+        if let Some(count) = self.count {
+            description.push_str(&format!(", {} times", count));
+        }
+        description
+    }
+
+    // Every occurrence in local time, starting with `start` itself if it matches the rule,
+    // up to `MAX_YEARS` after it.
+    pub fn occurrences(self, start: NaiveDateTime) -> impl Iterator<Item = NaiveDateTime> {
+        let horizon = start
+            .date()
+            .checked_add_months(Months::new(MAX_YEARS * 12))
+            .unwrap_or(NaiveDate::MAX);
+        let mut pending: VecDeque<NaiveDateTime> = VecDeque::new();
+        let mut period = 0;
+        let mut emitted = 0;
+        std::iter::from_fn(move || loop {
+            if self.count.is_some_and(|count| emitted >= count) {
+                return None;
+            }
+            if let Some(next) = pending.pop_front() {
+                if self.until.is_some_and(|until| next.date() > until) {
+                    return None;
+                }
+                emitted += 1;
+                return Some(next);
+            }
+            let dates = match self.period_dates(start.date(), period) {
+                Some((begins, dates)) if begins <= horizon => dates,
+                _ => return None,
+            };
+            pending.extend(
+                dates
+                    .into_iter()
+                    .filter(|date| *date >= start.date())
+                    .map(|date| date.and_time(start.time())),
+            );
+            period += 1;
+        })
+    }
+
+    // The first day of the `period`th day, week or month of the rule, and the dates in it
+    // that match. `None` once they would be past the last date chrono can represent.
+    fn period_dates(&self, first: NaiveDate, period: u32) -> Option<(NaiveDate, Vec<NaiveDate>)> {
+        let step = period.checked_mul(self.interval)?;
+        match self.frequency {
+            Frequency::Daily => {
+                let date = first.checked_add_signed(Duration::try_days(step.into())?)?;
+                if self.weekdays.is_empty() || self.weekdays.contains(&date.weekday()) {
+                    Some((date, vec![date]))
+                } else {
+                    Some((date, Vec::new()))
+                }
+            }
+            Frequency::Weekly => {
+                let monday = first
+                    .checked_sub_signed(Duration::days(
+                        first.weekday().num_days_from_monday().into(),
+                    ))?
+                    .checked_add_signed(Duration::try_weeks(step.into())?)?;
+                let mut days = if self.weekdays.is_empty() {
+                    vec![first.weekday()]
+                } else {
+                    self.weekdays.clone()
+                };
+                days.sort_by_key(|day| day.num_days_from_monday());
+                let dates = days
+                    .into_iter()
+                    .map(|day| {
+                        monday.checked_add_signed(Duration::days(day.num_days_from_monday().into()))
+                    })
+                    .collect::<Option<Vec<NaiveDate>>>()?;
+                Some((monday, dates))
+            }
+            Frequency::Monthly => {
+                let month = first.with_day(1)?.checked_add_months(Months::new(step))?;
+                // Months without the day (e.g. the 31st) are skipped, as in iCalendar.
+                Some((month, month.with_day(first.day()).into_iter().collect()))
+            }
+        }
+    }
+}
+
+fn parse_rrule(rule: &str, tz: Tz) -> Result<Recurrence, String> {
+    let mut frequency = None;
+    let mut interval = 1;
+    let mut weekdays = Vec::new();
+    let mut until = None;
+    let mut count = None;
+    for part in rule.split(';').filter(|part| !part.is_empty()) {
+        let (key, value) = part
+            .split_once('=')
+            .ok_or_else(|| format!("`{}` isn't a KEY=VALUE pair", part))?;
+        match key.to_uppercase().as_str() {
+            "FREQ" => {
+                frequency = Some(match value.to_uppercase().as_str() {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    _ => return Err(format!("`FREQ={}` isn't supported", value)),
+                })
+            }
+            "INTERVAL" => interval = parse_positive(value)?,
+            "COUNT" => count = Some(parse_positive(value)?),
+            "BYDAY" => {
+                for day in value.split(',') {
+                    weekdays.push(
+                        parse_rrule_weekday(day)
+                            .ok_or_else(|| format!("`{}` isn't a weekday like MO or FR", day))?,
+                    );
+                }
+            }
+            "UNTIL" => until = Some(parse_rrule_until(value, tz)?),
+            // iCalendar's default week start is Monday, which is what occurrences use.
+            "WKST" if value.eq_ignore_ascii_case("MO") => {}
+            _ => return Err(format!("`{}` isn't supported", part)),
+        }
+    }
+
+    let frequency = frequency.ok_or("it has no FREQ")?;
+    build(frequency, interval, weekdays, until, count)
+}
+
+fn parse_friendly(rule: &str, tz: Tz) -> Result<Recurrence, String> {
+    let normalized = rule.to_lowercase().replace(',', " ");
+    let tokens: Vec<&str> = normalized
+        .split_whitespace()
+        .filter(|token| *token != "and")
+        .collect();
+
+    let mut frequency = None;
+    let mut interval = 1;
+    let mut weekdays = Vec::new();
+    let mut until = None;
+    let mut count = None;
+    let mut i = 0;
+    while i < tokens.len() {
+        match tokens[i] {
+            "daily" => frequency = Some(Frequency::Daily),
+            "weekly" => frequency = Some(Frequency::Weekly),
+            "monthly" => frequency = Some(Frequency::Monthly),
+            "biweekly" => {
+                frequency = Some(Frequency::Weekly);
+                interval = 2;
+            }
+            "every" => {
+                i += 1;
+                match tokens.get(i).copied() {
+                    Some("other") => {
+                        interval = 2;
+                        i += 1;
+                    }
+                    Some(number) if number.parse::<u32>().is_ok() => {
+                        interval = parse_positive(number)?;
+                        i += 1;
+                    }
+                    _ => {}
+                }
+                match tokens.get(i).map(|unit| unit.trim_end_matches('s')) {
+                    Some("day") => frequency = Some(Frequency::Daily),
+                    Some("week") => frequency = Some(Frequency::Weekly),
+                    Some("month") => frequency = Some(Frequency::Monthly),
+                    Some(_) => {
+                        // `every friday` or `every mon wed`.
+                        frequency = Some(Frequency::Weekly);
+                        let days = take_weekdays(&tokens[i..]);
+                        if days.is_empty() {
+                            return Err(format!("`{}` isn't a day, week or month", tokens[i]));
+                        }
+                        i += days.len() - 1;
+                        weekdays.extend(days);
+                    }
+                    None => return Err("`every` needs a day, week or month after it".into()),
+                }
+            }
+            "on" => {
+                let days = take_weekdays(&tokens[i + 1..]);
+                if days.is_empty() {
+                    return Err("`on` needs weekdays after it".into());
+                }
+                i += days.len();
+                weekdays.extend(days);
+            }
+            "until" => {
+                let end = tokens[i + 1..]
+                    .iter()
+                    .position(|token| *token == "for")
+                    .map_or(tokens.len(), |offset| i + 1 + offset);
+                let date = tokens[i + 1..end].join(" ");
+                let utc = dates::parse_start(&date, tz, Utc::now().naive_utc())
+                    .map_err(|_| format!("`{}` isn't a date", date))?;
+                until = Some(timezone::from_utc(tz, utc).date());
+                i = end - 1;
+            }
+            "for" => {}
+            "times" | "occurrences" => {}
+            number => match number.parse::<u32>() {
+                Ok(_) if matches!(tokens.get(i + 1), Some(&"times" | &"occurrences")) => {
+                    count = Some(parse_positive(number)?)
+                }
+                _ => return Err(format!("`{}` wasn't expected", number)),
+            },
+        }
+        i += 1;
+    }
+
+    let frequency = frequency.ok_or("it doesn't say how often, like `weekly`")?;
+    build(frequency, interval, weekdays, until, count)
+}
+
+fn take_weekdays(tokens: &[&str]) -> Vec<Weekday> {
+    tokens
+        .iter()
+        .map_while(|token| dates::parse_weekday(token))
+        .collect()
+}
+
+fn build(
+    frequency: Frequency,
+    interval: u32,
+    mut weekdays: Vec<Weekday>,
+    until: Option<NaiveDate>,
+    count: Option<u32>,
+) -> Result<Recurrence, String> {
+    if until.is_some() && count.is_some() {
+        return Err("it can end on a date or after a number of times, but not both".into());
+    }
+    if interval > MAX_INTERVAL {
+        return Err(format!(
+            "it can repeat at most every {} days, weeks or months",
+            MAX_INTERVAL
+        ));
+    }
+    if frequency == Frequency::Monthly && !weekdays.is_empty() {
+        return Err("monthly repeats can't be limited to weekdays".into());
+    }
+    weekdays.sort_by_key(|day| day.num_days_from_monday());
+    weekdays.dedup();
+
+    Ok(Recurrence {
+        frequency,
+        interval,
+        weekdays,
+        until,
+        count,
+    })
+}
+
+fn parse_positive(value: &str) -> Result<u32, String> {
+    match value.parse::<u32>() {
+        Ok(number) if number > 0 => Ok(number),
+        _ => Err(format!("`{}` isn't a positive number", value)),
+    }
+}
+
+// `20241231` or `20241231T235959Z`; a UTC time is moved to the local date it falls on.
+fn parse_rrule_until(value: &str, tz: Tz) -> Result<NaiveDate, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return Ok(date);
+    }
+    let utc = NaiveDateTime::parse_from_str(value.trim_end_matches('Z'), "%Y%m%dT%H%M%S")
+        .map_err(|_| format!("`UNTIL={}` isn't a date", value))?;
+    Ok(timezone::from_utc(tz, utc).date())
+}
+
+fn rrule_weekday(day: Weekday) -> &'static str {
+    match day {
+        Weekday::Mon => "MO",
+        Weekday::Tue => "TU",
+        Weekday::Wed => "WE",
+        Weekday::Thu => "TH",
+        Weekday::Fri => "FR",
+        Weekday::Sat => "SA",
+        Weekday::Sun => "SU",
+    }
+}
+
+fn parse_rrule_weekday(day: &str) -> Option<Weekday> {
+    let day = day.trim().to_uppercase();
+    [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ]
+    .into_iter()
+    .find(|weekday| rrule_weekday(*weekday) == day)
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveTime;
+    use chrono_tz::America::Los_Angeles;
+    use chrono_tz::UTC;
+    use uuid::Uuid;
+
+    use super::*;
+    use crate::commands::events::{self, Event};
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn at(year: i32, month: u32, day: u32, hour: u32) -> NaiveDateTime {
+        date(year, month, day).and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    fn dates(rule: &str, start: NaiveDateTime, take: usize) -> Vec<NaiveDate> {
+        Recurrence::parse(rule, UTC)
+            .unwrap()
+            .occurrences(start)
+            .take(take)
+            .map(|occurrence| occurrence.date())
+            .collect()
+    }
+
+    #[test]
+    fn parses_friendly_rules() {
+        let rule = |input| Recurrence::parse(input, UTC).unwrap().to_rrule();
+        assert_eq!(rule("daily"), "FREQ=DAILY");
+        assert_eq!(rule("every other month"), "FREQ=MONTHLY;INTERVAL=2");
+        assert_eq!(rule("every friday"), "FREQ=WEEKLY;BYDAY=FR");
+        assert_eq!(
+            rule("every 2 weeks on wed, mon"),
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE"
+        );
+        assert_eq!(rule("weekly for 10 times"), "FREQ=WEEKLY;COUNT=10");
+        assert_eq!(
+            rule("weekly until 12/31/2024"),
+            "FREQ=WEEKLY;UNTIL=20241231"
+        );
+    }
+
+    #[test]
+    fn rrules_round_trip() {
+        for rule in [
+            "FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE;COUNT=10",
+            "FREQ=DAILY;BYDAY=MO,TU,WE,TH,FR;UNTIL=20241231",
+            "FREQ=MONTHLY",
+        ] {
+            assert_eq!(Recurrence::parse(rule, UTC).unwrap().to_rrule(), rule);
+        }
+        let prefixed = Recurrence::parse("RRULE:FREQ=WEEKLY;WKST=MO", UTC).unwrap();
+        assert_eq!(prefixed.to_rrule(), "FREQ=WEEKLY");
+    }
+
+    #[test]
+    fn until_with_a_time_is_read_in_the_guilds_zone() {
+        // 07:00 UTC on the 1st is still the 31st in Los Angeles.
+        let rule = Recurrence::parse("FREQ=DAILY;UNTIL=20250101T070000Z", Los_Angeles).unwrap();
+        assert_eq!(rule.until, Some(date(2024, 12, 31)));
+    }
+
+    #[test]
+    fn rejects_bad_rules() {
+        for rule in [
+            "",
+            "sometimes",
+            "every",
+            "every fortnight",
+            "FREQ=YEARLY",
+            "FREQ=WEEKLY;BYDAY=XX",
+            "FREQ=WEEKLY;INTERVAL=0",
+            "FREQ=WEEKLY;COUNT=3;UNTIL=20241231",
+            "FREQ=MONTHLY;BYDAY=MO",
+            "INTERVAL=2",
+        ] {
+            assert!(Recurrence::parse(rule, UTC).is_err(), "{}", rule);
+        }
+    }
+
+    #[test]
+    fn expands_byday() {
+        // Starts on a Wednesday, so the Monday of the first week is already past.
+        let start = at(2024, 12, 4, 19);
+        assert_eq!(
+            dates("FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,WE", start, 4),
+            vec![
+                date(2024, 12, 4),
+                date(2024, 12, 16),
+                date(2024, 12, 18),
+                date(2024, 12, 30),
+            ]
+        );
+        assert_eq!(
+            dates("FREQ=DAILY;BYDAY=SA,SU", start, 3),
+            vec![date(2024, 12, 7), date(2024, 12, 8), date(2024, 12, 14)]
+        );
+    }
+
+    #[test]
+    fn expansion_keeps_the_time_of_day() {
+        let start = at(2024, 12, 4, 19);
+        let times: Vec<NaiveTime> = Recurrence::parse("daily", UTC)
+            .unwrap()
+            .occurrences(start)
+            .take(3)
+            .map(|occurrence| occurrence.time())
+            .collect();
+        assert_eq!(times, vec![start.time(); 3]);
+    }
+
+    #[test]
+    fn expansion_stops_at_until_and_count() {
+        let start = at(2024, 12, 4, 19);
+        assert_eq!(
+            dates("FREQ=WEEKLY;UNTIL=20241218", start, 10),
+            vec![date(2024, 12, 4), date(2024, 12, 11), date(2024, 12, 18)]
+        );
+        assert_eq!(
+            dates("FREQ=WEEKLY;BYDAY=MO,WE;COUNT=3", start, 10),
+            vec![date(2024, 12, 4), date(2024, 12, 9), date(2024, 12, 11)]
+        );
+    }
+
+    #[test]
+    fn monthly_skips_months_without_the_day() {
+        assert_eq!(
+            dates("FREQ=MONTHLY;COUNT=3", at(2025, 1, 31, 19), 10),
+            vec![date(2025, 1, 31), date(2025, 3, 31), date(2025, 5, 31)]
+        );
+    }
+
+    #[test]
+    fn expansion_ends_instead_of_searching_forever() {
+        // Every 7 days from a Wednesday never lands on a Monday.
+        let start = at(2024, 12, 4, 19);
+        assert!(dates("FREQ=DAILY;INTERVAL=7;BYDAY=MO", start, 1).is_empty());
+    }
+
+    #[test]
+    fn exceptions_skip_occurrences_but_still_count() {
+        let start = at(2024, 12, 4, 19);
+        let mut event = Event::new(
+            Uuid::nil(),
+            1,
+            "Game night".to_string(),
+            String::new(),
+            String::new(),
+            start,
+            at(2024, 12, 4, 21),
+        );
+        event.recurrence = Some("FREQ=WEEKLY;COUNT=4".to_string());
+        event.exceptions = vec![date(2024, 12, 11), date(2024, 12, 25)];
+        let occurrences: Vec<(NaiveDateTime, NaiveDateTime)> = events::occurrences(&event, UTC)
+            .map(|occurrence| (occurrence.start_date, occurrence.end_date))
+            .collect();
+        assert_eq!(
+            occurrences,
+            vec![
+                (at(2024, 12, 4, 19), at(2024, 12, 4, 21)),
+                (at(2024, 12, 18, 19), at(2024, 12, 18, 21)),
+            ]
+        );
+    }
+
+    #[test]
+    fn occurrences_keep_local_time_across_daylight_saving() {
+        // 7pm in Los Angeles is 03:00 UTC in winter and 02:00 UTC in summer.
+        let mut event = Event::new(
+            Uuid::nil(),
+            1,
+            "Game night".to_string(),
+            String::new(),
+            String::new(),
+            at(2025, 3, 6, 3),
+            at(2025, 3, 6, 5),
+        );
+        event.recurrence = Some("FREQ=WEEKLY;COUNT=2".to_string());
+        let starts: Vec<NaiveDateTime> = events::occurrences(&event, Los_Angeles)
+            .map(|occurrence| occurrence.start_date)
+            .collect();
+        assert_eq!(starts, vec![at(2025, 3, 6, 3), at(2025, 3, 13, 2)]);
+    }
+
+    #[test]
+    fn expansion_stops_a_hundred_years_on() {
+        let start = at(2024, 12, 4, 19);
+        assert_eq!(
+            Recurrence::parse("daily", UTC)
+                .unwrap()
+                .occurrences(start)
+                .last()
+                .map(|last| last.date()),
+            Some(date(2124, 12, 4))
+        );
+        let far = NaiveDate::MAX.and_hms_opt(19, 0, 0).unwrap();
+        assert_eq!(dates("monthly", far, 10).len(), 1);
+    }
+
+    #[test]
+    fn rejects_huge_intervals() {
+        assert!(Recurrence::parse("every 1000 days", UTC).is_ok());
+        assert!(Recurrence::parse("every 1001 days", UTC).is_err());
+        assert!(Recurrence::parse("FREQ=MONTHLY;INTERVAL=4294967295", UTC).is_err());
+    }
+}
@@ -0,0 +1,179 @@
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, NaiveDateTime, Utc};
+use chrono_tz::Tz;
+use serenity::model::id::ChannelId;
+use serenity::prelude::*;
+
+use crate::api;
+use crate::commands::events::{self, Event};
+use crate::commands::rsvp::{self, RsvpStatus};
+use crate::store::{self, GuildSettings};
+
+static CHECK_INTERVAL: StdDuration = StdDuration::from_secs(60);
+// An "at start" reminder still goes out if the bot was down when the event began, as long
+// as it's caught within this window.
+static LATE_START_GRACE_MINS: i64 = 15;
+pub static DEFAULT_OFFSETS: [i64; 3] = [24 * 60, 60, 0];
+// Reminders are posted at most this long before an event.
+pub static MAX_OFFSET_DAYS: i64 = 30;
+
+// Polls each guild that has a reminder channel and posts any reminder that has come due.
+// Sent reminders are recorded in the store so a restart never posts one twice.
+pub async fn run(ctx: Context) {
+    println!("Started event reminder scheduler.");
+    loop {
+        let store = store::store(&ctx).await;
+        let now = Utc::now().naive_utc();
+        for (guild_id, settings) in store.guilds() {
+            if let Some(channel_id) = settings.reminder_channel {
+                check_guild(&ctx, guild_id, ChannelId(channel_id), &settings, now).await;
+            }
+        }
+        store.prune_sent_reminders(now - Duration::days(1));
+
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+async fn check_guild(
+    ctx: &Context,
+    guild_id: u64,
+    channel_id: ChannelId,
+    settings: &GuildSettings,
+    now: NaiveDateTime,
+) {
+    let events = match api::client(ctx)
+        .await
+        .list_current_events(guild_id as i64)
+        .await
+    {
+        Ok(events) => events,
+        Err(why) => {
+            why.log("reminders");
+            return;
+        }
+    };
+
+    let offsets = offsets(settings);
+    let store = store::store(ctx).await;
+    // Each occurrence of a recurring event gets its own reminders.
+    let horizon = Duration::try_minutes(offsets.iter().copied().max().unwrap_or(0))
+        .unwrap_or(Duration::max_value());
+    let tz = store.guild_timezone(guild_id);
+    for event in events::upcoming_occurrences(&events, tz, now, horizon, usize::MAX) {
+        // All reminders that are due but unsent. Only the one closest to the start is
+        // posted; the rest are stale (e.g. the event was added an hour before it starts).
+        let due: Vec<i64> = offsets
+            .iter()
+            .copied()
+            .filter(|offset| is_due(&event, *offset, now))
+            .filter(|offset| !store.reminder_sent(&reminder_key(&event, *offset)))
+            .collect();
+        let closest = match due.iter().min() {
+            Some(offset) => *offset,
+            None => continue,
+        };
+
+        match post_reminder(ctx, channel_id, &event, tz, closest).await {
+            Ok(()) => {
+                let keys = due
+                    .iter()
+                    .map(|offset| reminder_key(&event, *offset))
+                    .collect();
+                store.mark_reminders_sent(keys, event.start_date);
+            }
+            Err(why) => println!(
+                "Unable to post reminder for event {} in channel {}: {:?}",
+                event.id, channel_id, why
+            ),
+        }
+    }
+}
+
+pub fn offsets(settings: &GuildSettings) -> Vec<i64> {
+    settings
+        .reminder_offsets
+        .clone()
+        .unwrap_or_else(|| DEFAULT_OFFSETS.to_vec())
+}
+
+// Dates out of chrono's range can only come from an absurd offset; such a reminder
+// just never fires.
+fn is_due(event: &Event, offset: i64, now: NaiveDateTime) -> bool {
+    let fire_at = Duration::try_minutes(offset)
+        .and_then(|offset| event.start_date.checked_sub_signed(offset));
+    let expires_at = event
+        .start_date
+        .checked_add_signed(Duration::minutes(LATE_START_GRACE_MINS));
+    match (fire_at, expires_at) {
+        (Some(fire_at), Some(expires_at)) => fire_at <= now && now < expires_at,
+        _ => false,
+    }
+}
+
+// Includes the start time so that moving an event re-arms its reminders.
+fn reminder_key(event: &Event, offset: i64) -> String {
+    format!(
+        "{}:{}:{}",
+        event.id,
+        event.start_date.and_utc().timestamp(),
+        offset
+    )
+}
+
+async fn post_reminder(
+    ctx: &Context,
+    channel_id: ChannelId,
+    event: &Event,
+    tz: Tz,
+    offset: i64,
+) -> serenity::Result<()> {
+    let start = event.start_date.and_utc().timestamp();
+    let mut content = if offset == 0 {
+        format!(":alarm_clock: **{}** is starting now!", event.title)
+    } else {
+        format!(":alarm_clock: **{}** starts <t:{}:R>.", event.title, start)
+    };
+
+    // Only the people who said they're going get pinged.
+    let rsvps = store::store(ctx).await.rsvps(&rsvp::rsvp_key(event, tz));
+    let mut going: Vec<u64> = rsvps
+        .iter()
+        .filter(|(_, status)| **status == RsvpStatus::Going)
+        .map(|(user_id, _)| *user_id)
+        .collect();
+    going.sort_unstable();
+    if !going.is_empty() {
+        let mentions: Vec<String> = going
+            .iter()
+            .map(|user_id| format!("<@{}>", user_id))
+            .collect();
+        content.push('\n');
+        content.push_str(&mentions.join(" "));
+    }
+
+    channel_id
+        .send_message(&ctx.http, |m| {
+            m.content(content)
+                .allowed_mentions(|a| a.users(going.iter().copied()))
+                .set_embed(rsvp::event_card(event, &rsvps))
+                .set_components(rsvp::rsvp_buttons(event, tz))
+        })
+        .await?;
+
+    Ok(())
+}
+
+// `1d`, `2h`, `30m`, or `at start`, for showing the configured offsets.
+pub fn describe_offset(offset: i64) -> String {
+    if offset == 0 {
+        "at start".to_string()
+    } else if offset % (24 * 60) == 0 {
+        format!("{}d before", offset / (24 * 60))
+    } else if offset % 60 == 0 {
+        format!("{}h before", offset / 60)
+    } else {
+        format!("{}m before", offset)
+    }
+}
@@ -0,0 +1,502 @@
+use std::time::Duration as StdDuration;
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serenity::model::guild::{ScheduledEvent, ScheduledEventStatus, ScheduledEventType};
+use serenity::model::id::{GuildId, ScheduledEventId};
+use serenity::model::Timestamp;
+use serenity::prelude::*;
+use uuid::Uuid;
+
+use crate::api;
+use crate::commands::events::{self, Event, NewEvent};
+use crate::error::BotError;
+use crate::migration;
+use crate::store::{self, ScheduledLink, Store, SyncMode};
+
+static CHECK_INTERVAL: StdDuration = StdDuration::from_secs(5 * 60);
+// Discord's limits on scheduled event fields.
+static MAX_NAME_LEN: usize = 100;
+static MAX_DESCRIPTION_LEN: usize = 1000;
+static MAX_LOCATION_LEN: usize = 100;
+// Scheduled events held outside Discord need a location and an end after the start.
+static DEFAULT_LOCATION: &str = "Corkboard";
+static DEFAULT_LENGTH_MINS: i64 = 60;
+
+// Copies an event to the guild's Discord scheduled events, or updates the copy it already
+// has. Discord has no repeating events, so a recurring event is mirrored one occurrence
+// at a time and `run` moves it on to the next once the current one is over. Failures,
+// such as the bot lacking the Manage Events permission, are only logged; the event itself
+// was saved either way.
+pub async fn mirror(ctx: &Context, event: &Event) {
+    let guild_id = event.guild_id as u64;
+    let store = store::store(ctx).await;
+    if store.guild(guild_id).scheduled_events == SyncMode::Off {
+        return;
+    }
+
+    let tz = store.guild_timezone(guild_id);
+    let now = Utc::now().naive_utc();
+    let link = store.scheduled_link(event.id);
+
+    // A scheduled event that has started can't be moved, only renamed.
+    if let (Some(link), Some(current)) = (link, events::find_occurrence(event, tz, None)) {
+        if link.start_date <= now && link.start_date == current.start_date {
+            if let Err(why) = edit(ctx, link, &current, false).await {
+                println!(
+                    "Unable to update scheduled event for {}: {:?}",
+                    event.id, why
+                );
+            }
+            return;
+        }
+    }
+
+    let next = events::occurrences(event, tz).find(|occurrence| occurrence.start_date > now);
+    let result = match (link, next) {
+        (Some(link), None) if link.start_date > now => return unmirror(ctx, event.id).await,
+        // The occurrence it was made for has happened; Discord ends it on its own.
+        (Some(_), None) => return store.set_scheduled_link(event.id, None),
+        (None, None) => return,
+        (Some(link), Some(next)) if link.start_date > now => {
+            match edit(ctx, link, &next, true).await {
+                // Deleted in Discord while sync was one-way, so it's made again.
+                Err(why) if is_not_found(&why) => create(ctx, guild_id, &next).await,
+                result => result,
+            }
+            .map(|scheduled| (scheduled, next))
+        }
+        (_, Some(next)) => create(ctx, guild_id, &next)
+            .await
+            .map(|scheduled| (scheduled, next)),
+    };
+
+    match result {
+        Ok((scheduled, occurrence)) => store.set_scheduled_link(
+            event.id,
+            Some(ScheduledLink {
+                guild_id,
+                scheduled_event_id: scheduled.id.0,
+                start_date: occurrence.start_date,
+            }),
+        ),
+        Err(why) => println!(
+            "Unable to mirror event {} as a Discord scheduled event: {:?}",
+            event.id, why
+        ),
+    }
+}
+
+// Deletes the scheduled event mirroring an event, if there is one.
+pub async fn unmirror(ctx: &Context, event_id: Uuid) {
+    let store = store::store(ctx).await;
+    let link = match store.scheduled_link(event_id) {
+        Some(link) => link,
+        None => return,
+    };
+
+    // Forgotten first, so the delete Discord echoes back isn't taken for one made there.
+    store.set_scheduled_link(event_id, None);
+    let result = GuildId(link.guild_id)
+        .delete_scheduled_event(&ctx.http, ScheduledEventId(link.scheduled_event_id))
+        .await;
+    match result {
+        Err(why) if !is_not_found(&why) => {
+            println!(
+                "Unable to delete scheduled event for {}: {:?}",
+                event_id, why
+            )
+        }
+        _ => {}
+    }
+}
+
+// Mirrors every current event that has no scheduled event yet and, for two-way sync,
+// imports the scheduled events made in Discord. Returns how many of each there were.
+pub async fn sync_guild(ctx: &Context, guild_id: u64) -> Result<(usize, usize), BotError> {
+    let store = store::store(ctx).await;
+    let client = api::client(ctx).await;
+    let mut mirrored = 0;
+    for event in client.list_current_events(guild_id as i64).await? {
+        if store.scheduled_link(event.id).is_none() {
+            mirror(ctx, &event).await;
+            if store.scheduled_link(event.id).is_some() {
+                mirrored += 1;
+            }
+        }
+    }
+
+    let mut imported = 0;
+    if store.guild(guild_id).scheduled_events == SyncMode::TwoWay {
+        let scheduled_events = match GuildId(guild_id).scheduled_events(&ctx.http, false).await {
+            Ok(scheduled_events) => scheduled_events,
+            Err(why) => {
+                println!(
+                    "Unable to list scheduled events for guild {}: {:?}",
+                    guild_id, why
+                );
+                Vec::new()
+            }
+        };
+        for scheduled in scheduled_events {
+            if is_importable(ctx, &store, &scheduled) {
+                import(ctx, &store, &scheduled).await?;
+                imported += 1;
+            }
+        }
+    }
+
+    Ok((mirrored, imported))
+}
+
+// Moves each recurring event's scheduled event on to the next occurrence once the
+// current one is over, and drops links to events the server no longer lists.
+pub async fn run(ctx: Context) {
+    println!("Started scheduled event sync.");
+    loop {
+        for guild_id in ctx.cache.guilds() {
+            roll_forward(&ctx, guild_id.0).await;
+        }
+
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+async fn roll_forward(ctx: &Context, guild_id: u64) {
+    let store = store::store(ctx).await;
+    let links = store.scheduled_links(guild_id);
+    if links.is_empty() {
+        return;
+    }
+
+    let events = match api::client(ctx)
+        .await
+        .list_current_events(guild_id as i64)
+        .await
+    {
+        Ok(events) => events,
+        Err(why) => {
+            why.log("scheduled event sync");
+            return;
+        }
+    };
+
+    let tz = store.guild_timezone(guild_id);
+    let now = Utc::now().naive_utc();
+    for (event_id, link) in links {
+        match events.iter().find(|event| event.id == event_id) {
+            Some(event) if event.recurrence.is_some() && link.start_date <= now => {
+                let current = events::find_occurrence(event, tz, None);
+                if current.map(|current| current.start_date) != Some(link.start_date) {
+                    mirror(ctx, event).await;
+                }
+            }
+            Some(_) => {}
+            // Deleted without going through the bot, so its upcoming copy goes too.
+            None if link.start_date > now => unmirror(ctx, event_id).await,
+            None => store.set_scheduled_link(event_id, None),
+        }
+    }
+}
+
+// With two-way sync, a scheduled event made in Discord is imported as an event.
+pub async fn on_create(ctx: &Context, scheduled: &ScheduledEvent) {
+    migration::wait_for_event_dates().await;
+    let store = store::store(ctx).await;
+    if !is_importable(ctx, &store, scheduled) {
+        return;
+    }
+    if let Err(why) = import(ctx, &store, scheduled).await {
+        why.log("scheduled event sync");
+    }
+}
+
+// With two-way sync, changes made in Discord to a mirrored one-off event are copied back.
+// Discord only ever holds one occurrence of a recurring event, so those are left alone.
+pub async fn on_update(ctx: &Context, scheduled: &ScheduledEvent) {
+    migration::wait_for_event_dates().await;
+    if matches!(scheduled.status, ScheduledEventStatus::Canceled) {
+        return on_delete(ctx, scheduled).await;
+    }
+
+    let store = store::store(ctx).await;
+    let (event_id, event) = match two_way_event(ctx, &store, scheduled).await {
+        Some(found) => found,
+        None => return,
+    };
+    if event.recurrence.is_some() {
+        return;
+    }
+
+    // Compared with what the bot last sent, so the echo of its own edits changes nothing.
+    let sent = Details::of(&event);
+    let mut edited = event.clone();
+    let mut changed = false;
+    if scheduled.name != sent.name {
+        edited.title = scheduled.name.clone();
+        changed = true;
+    }
+    let description = scheduled.description.clone().unwrap_or_default();
+    if description != sent.description {
+        edited.description = description;
+        changed = true;
+    }
+    let location = location(scheduled);
+    if location != sent.location && is_link(&location) {
+        edited.url = location;
+        changed = true;
+    }
+    let (start_date, end_date) = dates(scheduled);
+    if start_date != sent.start_date || end_date != sent.end_date {
+        edited.start_date = start_date;
+        edited.end_date = end_date;
+        changed = true;
+    }
+    if !changed {
+        return;
+    }
+
+    println!("Sending Event edit request from Discord with {:?}", edited);
+    match api::client(ctx).await.update_event(&edited).await {
+        Ok(event) => store.set_scheduled_link(
+            event_id,
+            Some(ScheduledLink {
+                guild_id: scheduled.guild_id.0,
+                scheduled_event_id: scheduled.id.0,
+                start_date: event.start_date,
+            }),
+        ),
+        Err(why) => why.log("scheduled event sync"),
+    }
+}
+
+// With two-way sync, deleting a mirrored scheduled event in Discord deletes the event,
+// or for a recurring event skips the occurrence it was showing.
+pub async fn on_delete(ctx: &Context, scheduled: &ScheduledEvent) {
+    migration::wait_for_event_dates().await;
+    let store = store::store(ctx).await;
+    let (event_id, mut event) = match two_way_event(ctx, &store, scheduled).await {
+        Some(found) => found,
+        None => return,
+    };
+    let link = store.scheduled_link(event_id);
+    store.set_scheduled_link(event_id, None);
+    let client = api::client(ctx).await;
+
+    if event.recurrence.is_some() {
+        let tz = store.guild_timezone(scheduled.guild_id.0);
+        let start_date = link.map_or(event.start_date, |link| link.start_date);
+        event.exceptions.push(events::occurrence_date(
+            &Event {
+                start_date,
+                ..event.clone()
+            },
+            tz,
+        ));
+        println!("Sending Event edit request from Discord with {:?}", event);
+        match client.update_event(&event).await {
+            Ok(event) => mirror(ctx, &event).await,
+            Err(why) => why.log("scheduled event sync"),
+        }
+        return;
+    }
+
+    println!(
+        "Sending Event delete request from Discord with ID {:?}",
+        event_id
+    );
+    match client.delete_event(event_id).await {
+        Ok(_) => store.forget_rsvps(event_id),
+        Err(why) => why.log("scheduled event sync"),
+    }
+}
+
+fn is_importable(ctx: &Context, store: &Store, scheduled: &ScheduledEvent) -> bool {
+    store.guild(scheduled.guild_id.0).scheduled_events == SyncMode::TwoWay
+        // The bot's own mirrors come back through the gateway too.
+        && scheduled.creator_id != Some(ctx.cache.current_user_id())
+        && store.event_for_scheduled(scheduled.id.0).is_none()
+        && matches!(
+            scheduled.status,
+            ScheduledEventStatus::Scheduled | ScheduledEventStatus::Active
+        )
+}
+
+async fn import(ctx: &Context, store: &Store, scheduled: &ScheduledEvent) -> Result<(), BotError> {
+    let (start_date, end_date) = dates(scheduled);
+    let location = location(scheduled);
+    let new = NewEvent::new(
+        scheduled.guild_id.0 as i64,
+        scheduled.name.clone(),
+        if is_link(&location) {
+            location
+        } else {
+            String::new()
+        },
+        scheduled.description.clone().unwrap_or_default(),
+        start_date,
+        end_date,
+    );
+
+    println!("Sending Event creation request from Discord with {:?}", new);
+    let event = api::client(ctx).await.create_event(&new).await?;
+    store.set_scheduled_link(
+        event.id,
+        Some(ScheduledLink {
+            guild_id: scheduled.guild_id.0,
+            scheduled_event_id: scheduled.id.0,
+            start_date: event.start_date,
+        }),
+    );
+    Ok(())
+}
+
+// The mirrored event behind a scheduled event, when the guild syncs both ways.
+async fn two_way_event(
+    ctx: &Context,
+    store: &Store,
+    scheduled: &ScheduledEvent,
+) -> Option<(Uuid, Event)> {
+    if store.guild(scheduled.guild_id.0).scheduled_events != SyncMode::TwoWay {
+        return None;
+    }
+    let event_id = store.event_for_scheduled(scheduled.id.0)?;
+    let events = match api::client(ctx)
+        .await
+        .list_current_events(scheduled.guild_id.0 as i64)
+        .await
+    {
+        Ok(events) => events,
+        Err(why) => {
+            why.log("scheduled event sync");
+            return None;
+        }
+    };
+    let event = events.into_iter().find(|event| event.id == event_id)?;
+    Some((event_id, event))
+}
+
+// What a scheduled event shows for one occurrence of an event.
+struct Details {
+    name: String,
+    description: String,
+    location: String,
+    start_date: NaiveDateTime,
+    end_date: NaiveDateTime,
+}
+
+impl Details {
+    fn of(occurrence: &Event) -> Details {
+        let mut description = occurrence.description.clone();
+        // The link is the location when it fits, else it goes at the end of the description.
+        let location = if occurrence.url.is_empty() {
+            DEFAULT_LOCATION.to_string()
+        } else if occurrence.url.chars().count() <= MAX_LOCATION_LEN {
+            occurrence.url.clone()
+        } else {
+            description = format!("{}\n{}", description, occurrence.url)
+                .trim()
+                .to_string();
+            DEFAULT_LOCATION.to_string()
+        };
+        let end_date = if occurrence.end_date > occurrence.start_date {
+            occurrence.end_date
+        } else {
+            occurrence.start_date + Duration::minutes(DEFAULT_LENGTH_MINS)
+        };
+
+        Details {
+            name: truncate(&occurrence.title, MAX_NAME_LEN),
+            description: truncate(&description, MAX_DESCRIPTION_LEN),
+            location,
+            start_date: occurrence.start_date,
+            end_date,
+        }
+    }
+}
+
+async fn create(
+    ctx: &Context,
+    guild_id: u64,
+    occurrence: &Event,
+) -> serenity::Result<ScheduledEvent> {
+    let details = Details::of(occurrence);
+    GuildId(guild_id)
+        .create_scheduled_event(&ctx.http, |e| {
+            e.name(details.name)
+                .description(details.description)
+                .kind(ScheduledEventType::External)
+                .location(details.location)
+                .start_time(timestamp(details.start_date))
+                .end_time(timestamp(details.end_date))
+        })
+        .await
+}
+
+async fn edit(
+    ctx: &Context,
+    link: ScheduledLink,
+    occurrence: &Event,
+    move_dates: bool,
+) -> serenity::Result<ScheduledEvent> {
+    let details = Details::of(occurrence);
+    GuildId(link.guild_id)
+        .edit_scheduled_event(&ctx.http, ScheduledEventId(link.scheduled_event_id), |e| {
+            e.name(details.name)
+                .description(details.description)
+                .location(details.location);
+            if move_dates {
+                e.start_time(timestamp(details.start_date))
+                    .end_time(timestamp(details.end_date));
+            }
+            e
+        })
+        .await
+}
+
+fn dates(scheduled: &ScheduledEvent) -> (NaiveDateTime, NaiveDateTime) {
+    let start_date = naive_utc(scheduled.start_time);
+    let end_date = scheduled
+        .end_time
+        .map(naive_utc)
+        .unwrap_or(start_date + Duration::minutes(DEFAULT_LENGTH_MINS));
+    (start_date, end_date)
+}
+
+fn location(scheduled: &ScheduledEvent) -> String {
+    scheduled
+        .metadata
+        .as_ref()
+        .map(|metadata| metadata.location.clone())
+        .unwrap_or_default()
+}
+
+fn is_link(text: &str) -> bool {
+    text.starts_with("https://") || text.starts_with("http://")
+}
+
+fn timestamp(utc: NaiveDateTime) -> Timestamp {
+    Timestamp::from_unix_timestamp(utc.and_utc().timestamp())
+        .expect("Event dates are always valid timestamps.")
+}
+
+fn naive_utc(timestamp: Timestamp) -> NaiveDateTime {
+    DateTime::from_timestamp(timestamp.unix_timestamp(), 0)
+        .expect("Discord timestamps are always valid dates.")
+        .naive_utc()
+}
+
+fn truncate(text: &str, max_len: usize) -> String {
+    if text.chars().count() <= max_len {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_len - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+fn is_not_found(why: &serenity::Error) -> bool {
+    matches!(
+        why,
+        serenity::Error::Http(http) if http.status_code().map(|status| status.as_u16()) == Some(404)
+    )
+}
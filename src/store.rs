@@ -0,0 +1,426 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::NaiveDateTime;
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use serenity::prelude::{Context, TypeMapKey};
+use uuid::Uuid;
+
+use crate::commands::rsvp::RsvpStatus;
+use crate::timezone;
+
+// Bot-side settings that the Corkboard Server has no place for. Everything lives in one
+// JSON file that is rewritten after each change, which is plenty for a handful of guilds.
+pub struct Store {
+    path: PathBuf,
+    data: Mutex<StoreData>,
+    // Each save is numbered while `data` is locked, and the number of the last one
+    // written is kept here, so a slow write can't overwrite a newer one.
+    saves: Mutex<u64>,
+    written: Arc<Mutex<u64>>,
+    // Messages a pin is being made for right now. Not saved, as it only matters while
+    // the bot runs.
+    pinning: Mutex<HashSet<u64>>,
+}
+
+// Holds a message in the in-flight set until dropped.
+pub struct PinningClaim<'a> {
+    store: &'a Store,
+    message_id: u64,
+}
+
+impl Drop for PinningClaim<'_> {
+    fn drop(&mut self) {
+        self.store.pinning.lock().unwrap().remove(&self.message_id);
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+#[serde(default)]
+struct StoreData {
+    guilds: HashMap<u64, GuildSettings>,
+    users: HashMap<u64, UserSettings>,
+    // Reminder keys already posted, with the start of the event each one was for.
+    sent_reminders: HashMap<String, NaiveDateTime>,
+    // Each event's RSVPs, by user. A recurring event's are kept per occurrence, under
+    // its ID and the occurrence date (see `rsvp::rsvp_key`).
+    rsvps: HashMap<String, HashMap<u64, RsvpStatus>>,
+    // The Discord scheduled event mirroring each event.
+    scheduled_events: HashMap<Uuid, ScheduledLink>,
+    // The link checker's latest results, by pin.
+    link_checks: HashMap<Uuid, LinkCheck>,
+    date_migration: DateMigration,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct GuildSettings {
+    pub timezone: Option<String>,
+    pub reminder_channel: Option<u64>,
+    // Minutes before an event starts to post each reminder, 0 meaning at the start.
+    pub reminder_offsets: Option<Vec<i64>>,
+    // Secret part of the guild's calendar feed URL.
+    pub feed_token: Option<String>,
+    pub scheduled_events: SyncMode,
+    // Emoji that pins a message when a `corkboard` member reacts with it; off when None.
+    pub pin_reaction: Option<String>,
+    // The order pins are listed in, as arranged with `.move_pin`, and the featured pins
+    // listed ahead of the rest.
+    pub pin_order: Vec<Uuid>,
+    pub featured_pins: Vec<Uuid>,
+}
+
+// How events are kept in step with the guild's Discord scheduled events.
+#[derive(Serialize, Deserialize, Default, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncMode {
+    Off,
+    // Events are copied to Discord; changes made in Discord are ignored.
+    #[default]
+    Mirror,
+    // As Mirror, and scheduled events created or changed in Discord come back as events.
+    TwoWay,
+}
+
+impl SyncMode {
+    pub fn key(&self) -> &'static str {
+        match self {
+            SyncMode::Off => "off",
+            SyncMode::Mirror => "mirror",
+            SyncMode::TwoWay => "two_way",
+        }
+    }
+
+    pub fn from_key(key: &str) -> Option<SyncMode> {
+        match key {
+            "off" => Some(SyncMode::Off),
+            "mirror" => Some(SyncMode::Mirror),
+            "two_way" => Some(SyncMode::TwoWay),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
+pub struct ScheduledLink {
+    pub guild_id: u64,
+    pub scheduled_event_id: u64,
+    // Start of the occurrence the scheduled event was made for.
+    pub start_date: NaiveDateTime,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct LinkCheck {
+    pub guild_id: u64,
+    // The URL that was checked, so a fixed link isn't flagged until it's checked again.
+    pub url: String,
+    // Checks failed in a row, and why the last one failed.
+    pub failures: u32,
+    pub error: Option<String>,
+    pub checked_at: NaiveDateTime,
+}
+
+// Events stored before time zone support hold Pacific wall-clock times rather than UTC.
+// The guilds the bot was in when it first started with UTC dates are listed once, so
+// events created since are never mistaken for old ones, and each listed event is
+// converted exactly once even if the bot stops part way through.
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct DateMigration {
+    pub started: bool,
+    // Guilds whose events haven't been listed yet.
+    pub unlisted_guilds: Vec<u64>,
+    // Listed events still to convert, with their guild.
+    pub pending_events: HashMap<Uuid, u64>,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone, Debug)]
+#[serde(default)]
+pub struct UserSettings {
+    pub timezone: Option<String>,
+}
+
+impl Store {
+    pub fn load(path: &str) -> Self {
+        let data = match fs::read_to_string(path) {
+            Ok(contents) => serde_json::from_str::<StoreData>(&contents)
+                .unwrap_or_else(|e| panic!("Unable to parse state file {}: {}", path, e)),
+            Err(_) => {
+                println!("No state file found at {}, starting fresh.", path);
+                StoreData::default()
+            }
+        };
+
+        Store {
+            path: PathBuf::from(path),
+            data: Mutex::new(data),
+            saves: Mutex::new(0),
+            written: Arc::new(Mutex::new(0)),
+            pinning: Mutex::new(HashSet::new()),
+        }
+    }
+
+    pub fn guild(&self, guild_id: u64) -> GuildSettings {
+        let data = self.data.lock().unwrap();
+        data.guilds.get(&guild_id).cloned().unwrap_or_default()
+    }
+
+    pub fn update_guild(&self, guild_id: u64, update: impl FnOnce(&mut GuildSettings)) {
+        let mut data = self.data.lock().unwrap();
+        update(data.guilds.entry(guild_id).or_default());
+        self.save(&data);
+    }
+
+    // The guild's feed token, creating one the first time it's asked for.
+    pub fn feed_token(&self, guild_id: u64) -> String {
+        if let Some(token) = self.guild(guild_id).feed_token {
+            return token;
+        }
+        let token = Uuid::new_v4().to_simple().to_string();
+        self.update_guild(guild_id, |settings| {
+            settings.feed_token = Some(token.clone())
+        });
+        token
+    }
+
+    pub fn user(&self, user_id: u64) -> UserSettings {
+        let data = self.data.lock().unwrap();
+        data.users.get(&user_id).cloned().unwrap_or_default()
+    }
+
+    pub fn update_user(&self, user_id: u64, update: impl FnOnce(&mut UserSettings)) {
+        let mut data = self.data.lock().unwrap();
+        update(data.users.entry(user_id).or_default());
+        self.save(&data);
+    }
+
+    pub fn guilds(&self) -> Vec<(u64, GuildSettings)> {
+        let data = self.data.lock().unwrap();
+        data.guilds
+            .iter()
+            .map(|(guild_id, settings)| (*guild_id, settings.clone()))
+            .collect()
+    }
+
+    pub fn reminder_sent(&self, key: &str) -> bool {
+        let data = self.data.lock().unwrap();
+        data.sent_reminders.contains_key(key)
+    }
+
+    pub fn mark_reminders_sent(&self, keys: Vec<String>, event_start: NaiveDateTime) {
+        let mut data = self.data.lock().unwrap();
+        for key in keys {
+            data.sent_reminders.insert(key, event_start);
+        }
+        self.save(&data);
+    }
+
+    // Forgets reminders for events that started before `cutoff`; they can't fire again.
+    pub fn prune_sent_reminders(&self, cutoff: NaiveDateTime) {
+        let mut data = self.data.lock().unwrap();
+        let before = data.sent_reminders.len();
+        data.sent_reminders.retain(|_, start| *start >= cutoff);
+        if data.sent_reminders.len() != before {
+            self.save(&data);
+        }
+    }
+
+    pub fn rsvps(&self, key: &str) -> HashMap<u64, RsvpStatus> {
+        let data = self.data.lock().unwrap();
+        data.rsvps.get(key).cloned().unwrap_or_default()
+    }
+
+    // `None` takes the user's RSVP back.
+    pub fn set_rsvp(&self, key: &str, user_id: u64, status: Option<RsvpStatus>) {
+        let mut data = self.data.lock().unwrap();
+        let rsvps = data.rsvps.entry(key.to_string()).or_default();
+        match status {
+            Some(status) => rsvps.insert(user_id, status),
+            None => rsvps.remove(&user_id),
+        };
+        if rsvps.is_empty() {
+            data.rsvps.remove(key);
+        }
+        self.save(&data);
+    }
+
+    // Forgets the RSVPs for an event, and for every occurrence if it's a series.
+    pub fn forget_rsvps(&self, event_id: Uuid) {
+        let mut data = self.data.lock().unwrap();
+        let id = event_id.to_string();
+        let before = data.rsvps.len();
+        data.rsvps
+            .retain(|key, _| key.split('@').next() != Some(id.as_str()));
+        if data.rsvps.len() != before {
+            self.save(&data);
+        }
+    }
+
+    pub fn forget_occurrence_rsvps(&self, key: &str) {
+        let mut data = self.data.lock().unwrap();
+        if data.rsvps.remove(key).is_some() {
+            self.save(&data);
+        }
+    }
+
+    pub fn scheduled_link(&self, event_id: Uuid) -> Option<ScheduledLink> {
+        let data = self.data.lock().unwrap();
+        data.scheduled_events.get(&event_id).copied()
+    }
+
+    // `None` forgets the link.
+    pub fn set_scheduled_link(&self, event_id: Uuid, link: Option<ScheduledLink>) {
+        let mut data = self.data.lock().unwrap();
+        match link {
+            Some(link) => data.scheduled_events.insert(event_id, link),
+            None => data.scheduled_events.remove(&event_id),
+        };
+        self.save(&data);
+    }
+
+    // The event a Discord scheduled event mirrors, if any.
+    pub fn event_for_scheduled(&self, scheduled_event_id: u64) -> Option<Uuid> {
+        let data = self.data.lock().unwrap();
+        data.scheduled_events
+            .iter()
+            .find(|(_, link)| link.scheduled_event_id == scheduled_event_id)
+            .map(|(event_id, _)| *event_id)
+    }
+
+    pub fn scheduled_links(&self, guild_id: u64) -> Vec<(Uuid, ScheduledLink)> {
+        let data = self.data.lock().unwrap();
+        data.scheduled_events
+            .iter()
+            .filter(|(_, link)| link.guild_id == guild_id)
+            .map(|(event_id, link)| (*event_id, *link))
+            .collect()
+    }
+
+    // Claims the message for pinning, or returns None if a pin for it is already being
+    // made, so two reactions at once can't both create one.
+    pub fn claim_pinning(&self, message_id: u64) -> Option<PinningClaim<'_>> {
+        if !self.pinning.lock().unwrap().insert(message_id) {
+            return None;
+        }
+        Some(PinningClaim {
+            store: self,
+            message_id,
+        })
+    }
+
+    pub fn link_check(&self, pin_id: Uuid) -> Option<LinkCheck> {
+        let data = self.data.lock().unwrap();
+        data.link_checks.get(&pin_id).cloned()
+    }
+
+    // Records one round of checks over a guild's pins. Results for pins that are no
+    // longer there are dropped.
+    pub fn record_link_checks(
+        &self,
+        guild_id: u64,
+        results: Vec<(Uuid, String, Result<(), String>)>,
+        now: NaiveDateTime,
+    ) {
+        let mut data = self.data.lock().unwrap();
+        data.link_checks.retain(|pin_id, check| {
+            check.guild_id != guild_id || results.iter().any(|r| r.0 == *pin_id)
+        });
+        for (pin_id, url, result) in results {
+            let previous = data
+                .link_checks
+                .get(&pin_id)
+                .filter(|check| check.url == url)
+                .map_or(0, |check| check.failures);
+            let (failures, error) = match result {
+                Ok(()) => (0, None),
+                Err(error) => (previous + 1, Some(error)),
+            };
+            data.link_checks.insert(
+                pin_id,
+                LinkCheck {
+                    guild_id,
+                    url,
+                    failures,
+                    error,
+                    checked_at: now,
+                },
+            );
+        }
+        self.save(&data);
+    }
+
+    pub fn date_migration(&self) -> DateMigration {
+        let data = self.data.lock().unwrap();
+        data.date_migration.clone()
+    }
+
+    pub fn update_date_migration(&self, update: impl FnOnce(&mut DateMigration)) {
+        let mut data = self.data.lock().unwrap();
+        update(&mut data.date_migration);
+        self.save(&data);
+    }
+
+    // The zone a user's dates are read in: their own if set, else their guild's default.
+    pub fn timezone_for(&self, guild_id: u64, user_id: u64) -> Tz {
+        self.user(user_id)
+            .timezone
+            .and_then(|name| timezone::parse_timezone(&name))
+            .unwrap_or_else(|| self.guild_timezone(guild_id))
+    }
+
+    // The zone recurring events repeat in, so everyone sees the same series.
+    pub fn guild_timezone(&self, guild_id: u64) -> Tz {
+        self.guild(guild_id)
+            .timezone
+            .and_then(|name| timezone::parse_timezone(&name))
+            .unwrap_or(timezone::DEFAULT_TIMEZONE)
+    }
+
+    // The state is copied while `data` is locked and written to disk on tokio's blocking
+    // pool, so async tasks never wait on the file system.
+    fn save(&self, data: &StoreData) {
+        let contents = serde_json::to_string_pretty(data).expect("Unable to serialize state.");
+        let save = {
+            let mut saves = self.saves.lock().unwrap();
+            *saves += 1;
+            *saves
+        };
+        let path = self.path.clone();
+        let written = self.written.clone();
+        let write = move || {
+            let mut written = written.lock().unwrap();
+            if *written < save {
+                write_state(&path, contents);
+                *written = save;
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn_blocking(write)),
+            Err(_) => write(),
+        }
+    }
+}
+
+// Written to a temporary file first so a crash mid-write can't truncate the state.
+fn write_state(path: &Path, contents: String) {
+    let tmp_path = path.with_extension("json.tmp");
+    let result = fs::write(&tmp_path, contents).and_then(|_| fs::rename(&tmp_path, path));
+    if let Err(why) = result {
+        println!("Unable to save state to {}: {:?}", path.display(), why);
+    }
+}
+
+impl TypeMapKey for Store {
+    type Value = Arc<Store>;
+}
+
+// Fetches the shared state store that was loaded in main().
+pub async fn store(ctx: &Context) -> Arc<Store> {
+    let data = ctx.data.read().await;
+    data.get::<Store>()
+        .expect("Expected Store in the client data.")
+        .clone()
+}
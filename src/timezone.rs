@@ -0,0 +1,52 @@
+use chrono::{Duration, NaiveDateTime, TimeZone};
+use chrono_tz::{Tz, TZ_VARIANTS};
+
+// Everything was pinned to Pacific time before zones were configurable, so it stays the
+// fallback for guilds and users that haven't picked one.
+pub static DEFAULT_TIMEZONE: Tz = chrono_tz::America::Los_Angeles;
+
+// Accepts IANA names like `Europe/Berlin` regardless of case.
+pub fn parse_timezone(name: &str) -> Option<Tz> {
+    let name = name.trim();
+    TZ_VARIANTS
+        .iter()
+        .find(|tz| tz.name().eq_ignore_ascii_case(name))
+        .copied()
+}
+
+// Zone names containing the typed text, for autocomplete.
+pub fn matching_timezones(typed: &str) -> Vec<&'static str> {
+    let typed = typed.to_lowercase();
+    TZ_VARIANTS
+        .iter()
+        .map(|tz| tz.name())
+        .filter(|name| name.to_lowercase().contains(&typed))
+        .collect()
+}
+
+// Converts a wall-clock time in `tz` to UTC. Ambiguous times (clocks going back) use the
+// first occurrence; times skipped when clocks go forward are moved an hour later.
+pub fn to_utc(tz: Tz, local: NaiveDateTime) -> NaiveDateTime {
+    tz.from_local_datetime(&local)
+        .earliest()
+        .or_else(|| {
+            tz.from_local_datetime(&(local + Duration::hours(1)))
+                .earliest()
+        })
+        .map(|time| time.naive_utc())
+        .unwrap_or(local)
+}
+
+pub fn from_utc(tz: Tz, utc: NaiveDateTime) -> NaiveDateTime {
+    tz.from_utc_datetime(&utc).naive_local()
+}
+
+// Discord renders `<t:...>` markup in each viewer's own time zone.
+pub fn discord_timestamp(utc: NaiveDateTime) -> String {
+    format!("<t:{}:f>", utc.and_utc().timestamp())
+}
+
+// `in 3 days` or `2 hours ago`, kept up to date by Discord.
+pub fn discord_relative(utc: NaiveDateTime) -> String {
+    format!("<t:{}:R>", utc.and_utc().timestamp())
+}
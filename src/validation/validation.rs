@@ -1,28 +1,41 @@
-use serenity::prelude::*;
+use serenity::http::CacheHttp;
 use serenity::model::channel::Message;
 use serenity::model::guild::Role;
-use serenity::http::CacheHttp;
+use serenity::model::id::GuildId;
+use serenity::model::user::User;
+use serenity::prelude::*;
+use uuid::Uuid;
+
+use crate::commands::invocation::Invocation;
+
+pub static CORKBOARD_ROLE_ERROR: &str =
+    ":bangbang: Error :bangbang: - Only users with the `corkboard` role can execute this command.";
+
+pub async fn has_corkboard_role(ctx: &Context, inv: &Invocation<'_>) -> bool {
+    match user_has_corkboard_role(ctx, inv.guild_id().unwrap(), inv.user()).await {
+        Some(true) => true,
+        Some(false) => {
+            let _msg = inv.say(ctx, CORKBOARD_ROLE_ERROR).await;
+            false
+        }
+        None => false,
+    }
+}
 
-pub async fn has_corkboard_role(ctx: &Context, msg: &Message) -> bool {
-    let guild_id = msg.guild_id.unwrap();
-    let roles : Vec<Role> = ctx.http().get_guild_roles(guild_id.into()).await.unwrap();
+// Whether the user holds the guild's `corkboard` role, or None if the guild has no such role.
+pub async fn user_has_corkboard_role(
+    ctx: &Context,
+    guild_id: GuildId,
+    user: &User,
+) -> Option<bool> {
+    let roles: Vec<Role> = ctx.http().get_guild_roles(guild_id.into()).await.unwrap();
     for role in roles {
         if role.name == "corkboard" {
-            if msg.author.has_role(&ctx.http, guild_id, role.id).await.unwrap() {
-                return true;
-            } else {
-                let _msg = msg
-                    .channel_id.say(
-                        &ctx.http,
-                        ":bangbang: Error :bangbang: - Only users with the `corkboard` role can execute this command."
-                    )
-                    .await;
-                return false;
-            }
+            return Some(user.has_role(&ctx.http, guild_id, role.id).await.unwrap());
         }
     }
 
-    false
+    None
 }
 
 pub async fn has_correct_arg_count(
@@ -31,7 +44,7 @@ pub async fn has_correct_arg_count(
     expected: usize,
     actual: usize,
     args: Vec<&str>,
-    command_name: &str
+    command_name: &str,
 ) -> bool {
     if expected != actual {
         let _msg = msg
@@ -53,3 +66,191 @@ pub async fn has_correct_arg_count(
 
     true
 }
+
+static SHORT_ID_LEN: usize = 6;
+pub static MIN_SHORT_ID_LEN: usize = 4;
+
+// Stable handle shown in listings in place of the item's position. It's the start of the
+// UUID, so adding or deleting other items never changes what a handle points at.
+pub fn short_id(id: &Uuid) -> String {
+    id.to_simple().to_string()[..SHORT_ID_LEN].to_string()
+}
+
+pub enum ShortIdMatch {
+    Found(Uuid),
+    TooShort,
+    NotFound,
+    Ambiguous,
+}
+
+pub fn match_short_id(handle: &str, ids: &[Uuid]) -> ShortIdMatch {
+    let handle = handle
+        .trim()
+        .trim_matches('`')
+        .replace('-', "")
+        .to_lowercase();
+    if handle.len() < MIN_SHORT_ID_LEN || !handle.chars().all(|c| c.is_ascii_hexdigit()) {
+        return ShortIdMatch::TooShort;
+    }
+
+    let matches: Vec<&Uuid> = ids
+        .iter()
+        .filter(|id| id.to_simple().to_string().starts_with(&handle))
+        .collect();
+    match matches.as_slice() {
+        [id] => ShortIdMatch::Found(**id),
+        [] => ShortIdMatch::NotFound,
+        _ => ShortIdMatch::Ambiguous,
+    }
+}
+
+// Resolves a handle typed by a user to the item's UUID, replying with an explanation
+// when it doesn't point at exactly one existing item.
+pub async fn resolve_short_id(
+    ctx: &Context,
+    inv: &Invocation<'_>,
+    handle: &str,
+    ids: &[Uuid],
+    list_command: &str,
+) -> Option<Uuid> {
+    match lookup_short_id(handle, ids, list_command) {
+        Ok(id) => Some(id),
+        Err(error) => {
+            let _msg = inv.say(ctx, error).await;
+            None
+        }
+    }
+}
+
+// Same as `resolve_short_id` but hands back the explanation instead of replying, for
+// callers that have to answer the interaction in a particular way.
+pub fn lookup_short_id(handle: &str, ids: &[Uuid], list_command: &str) -> Result<Uuid, String> {
+    Err(match match_short_id(handle, ids) {
+        ShortIdMatch::Found(id) => return Ok(id),
+        ShortIdMatch::TooShort => format!(
+            ":bangbang: Error :bangbang: - `{}` is not a valid ID. IDs are the short codes shown next to each item in `.{}` (list positions are no longer accepted).",
+            handle, list_command
+        ),
+        ShortIdMatch::NotFound => format!(
+            ":bangbang: Error :bangbang: - No item with ID `{}` exists. It may have been deleted; run the `.{}` command to see current IDs.",
+            handle, list_command
+        ),
+        ShortIdMatch::Ambiguous => format!(
+            ":bangbang: Error :bangbang: - ID `{}` matches more than one item. Please use more characters of the ID.",
+            handle
+        ),
+    })
+}
+
+// Replies with an error unless the URL is a well-formed http:// or https:// link.
+pub async fn has_web_url(ctx: &Context, inv: &Invocation<'_>, url: &str) -> bool {
+    if is_web_url(url) {
+        return true;
+    }
+
+    let _msg = inv
+        .say(
+            ctx,
+            format!(
+                ":bangbang: Error :bangbang: - `{}` isn't a valid http:// or https:// link.",
+                url
+            ),
+        )
+        .await;
+    false
+}
+
+pub fn is_web_url(url: &str) -> bool {
+    match reqwest::Url::parse(url) {
+        Ok(url) => url.scheme() == "http" || url.scheme() == "https",
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids() -> Vec<Uuid> {
+        [
+            "3fa85f64-5717-4562-b3fc-2c963f66afa6",
+            "3fa85e00-0000-4000-8000-000000000000",
+            "9b2c1d00-0000-4000-8000-000000000000",
+        ]
+        .iter()
+        .map(|id| Uuid::parse_str(id).unwrap())
+        .collect()
+    }
+
+    #[test]
+    fn short_ids_are_the_start_of_the_uuid() {
+        assert_eq!(short_id(&ids()[0]), "3fa85f");
+    }
+
+    #[test]
+    fn finds_an_id_by_its_prefix() {
+        let ids = ids();
+        for handle in ["3fa85f", "3FA85F", "`3fa85f`", "3fa8-5f64", " 9b2c "] {
+            assert!(
+                matches!(match_short_id(handle, &ids), ShortIdMatch::Found(_)),
+                "{}",
+                handle
+            );
+        }
+        assert!(matches!(
+            match_short_id("3fa85f64-5717-4562-b3fc-2c963f66afa6", &ids),
+            ShortIdMatch::Found(id) if id == ids[0]
+        ));
+    }
+
+    #[test]
+    fn ambiguous_prefixes_are_turned_down() {
+        assert!(matches!(
+            match_short_id("3fa8", &ids()),
+            ShortIdMatch::Ambiguous
+        ));
+        assert!(matches!(
+            match_short_id("3fa85", &ids()),
+            ShortIdMatch::Ambiguous
+        ));
+    }
+
+    #[test]
+    fn too_short_or_non_hex_handles_are_turned_down() {
+        // List positions are no longer IDs, even when an ID starts with those digits.
+        for handle in ["", "3", "3f", "3fa", "12", "999", "zzzz", "3fa8g"] {
+            assert!(
+                matches!(match_short_id(handle, &ids()), ShortIdMatch::TooShort),
+                "{}",
+                handle
+            );
+        }
+    }
+
+    #[test]
+    fn unknown_ids_are_not_found() {
+        assert!(matches!(
+            match_short_id("abcd", &ids()),
+            ShortIdMatch::NotFound
+        ));
+        assert!(matches!(
+            match_short_id("3fa85f", &[]),
+            ShortIdMatch::NotFound
+        ));
+    }
+
+    #[test]
+    fn lookup_explains_what_went_wrong() {
+        let ids = ids();
+        assert_eq!(lookup_short_id("9b2c", &ids, "events"), Ok(ids[2]));
+        assert!(lookup_short_id("3", &ids, "events")
+            .unwrap_err()
+            .contains("`.events`"));
+        assert!(lookup_short_id("3fa8", &ids, "events")
+            .unwrap_err()
+            .contains("more than one item"));
+        assert!(lookup_short_id("abcd", &ids, "pins")
+            .unwrap_err()
+            .contains("`.pins`"));
+    }
+}